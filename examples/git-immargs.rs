@@ -1,8 +1,8 @@
 // Example of what argument parsing for a program like "git" could look like,
 // using immargs!
 //
-// Demonstrates short-/long-options, (sub)commands, variadic arguments,
-// conflicting arguments, etc.
+// Demonstrates short-/long-options, (sub)commands parsed into their own
+// argument structs, variadic arguments, conflicting arguments, etc.
 
 use immargs::immargs;
 use std::path::PathBuf;
@@ -13,10 +13,10 @@ immargs! {
     --version                   "print version information",
     -h --help                   "print help message",
     <command> GitCommand        "command to run" {
-        clone                   "clone repository",
-        add                     "add file(s)",
-        move_ mv                "move or rename file(s)",
-        commit co               "commit changes",
+        clone: CloneArgs        "clone repository",
+        add: AddArgs            "add file(s)",
+        move_ mv: MoveArgs      "move or rename file(s)",
+        commit co: CommitArgs   "commit changes",
     },
 }
 
@@ -63,10 +63,10 @@ fn main() {
     println!();
 
     match args.command {
-        GitCommand::Clone(args) => git_clone(args.into()),
-        GitCommand::Add(args) => git_add(args.into()),
-        GitCommand::Move(args) => git_move(args.into()),
-        GitCommand::Commit(args) => git_commit(args.into()),
+        GitCommand::Clone(args) => git_clone(args),
+        GitCommand::Add(args) => git_add(args),
+        GitCommand::Move(args) => git_move(args),
+        GitCommand::Commit(args) => git_commit(args),
     }
 }
 
@@ -1,31 +1,66 @@
 #![doc(hidden)]
 
 use proc_macro2::Span;
+use proc_macro2::TokenStream;
+use quote::ToTokens;
+use quote::quote;
+use syn::Block;
+use syn::Error;
+use syn::Expr;
+use syn::ExprPath;
 use syn::Ident;
+use syn::Lit;
+use syn::LitChar;
+use syn::LitFloat;
+use syn::LitInt;
 use syn::LitStr;
+use syn::RangeLimits;
 use syn::Result;
 use syn::TypePath;
 use syn::braced;
 use syn::bracketed;
+use syn::parenthesized;
 use syn::parse::Parse;
 use syn::parse::ParseStream;
+use syn::parse_quote_spanned;
 use syn::punctuated::Punctuated;
 use syn::spanned::Spanned;
+use syn::token::And;
 use syn::token::Brace;
 use syn::token::Bracket;
+use syn::token::Colon;
 use syn::token::Comma;
 use syn::token::DotDotDot;
+use syn::token::Eq;
 use syn::token::Gt;
+use syn::token::In;
 use syn::token::Lt;
 use syn::token::Minus;
 use syn::token::Not;
+use syn::token::Paren;
+use syn::token::Plus;
+use syn::token::Pound;
 use syn::token::Question;
+use syn::token::Underscore;
+
+const NO: &str = "no_";
+const ENV: &str = "env";
+const CHECK: &str = "check";
+const WITH: &str = "with";
+const REQUIRES: &str = "requires";
+const RAW: &str = "raw";
+const GLOBAL: &str = "global";
 
 pub struct Ast {
     pub ident: Option<Ident>,
+    pub settings: AstSettings,
     pub arguments: AstArguments,
 }
 
+pub struct AstSettings(pub Vec<AstSetting>);
+
+pub struct AstSetting(pub Ident);
+
 pub struct AstArguments(pub Vec<AstArgument>);
 
 pub enum AstArgument {
@@ -39,16 +74,27 @@ pub struct AstOption {
     pub longs: AstOptionLongs,
     pub variadic: AstVariadic,
     pub value: AstOptionValue,
+    pub parser: AstParser,
+    pub check: AstCheck,
+    pub default: AstDefault,
+    pub env: AstEnv,
+    pub requires: AstRequires,
+    pub global: AstGlobal,
     pub conflicts: AstConflicts,
     pub help: AstHelp,
 }
 
 pub struct AstNonOption {
     pub span: Span,
+    pub trailing: bool,
     pub optional: bool,
     pub ident: Ident,
     pub variadic: AstVariadic,
-    pub ty: TypePath,
+    pub raw: AstRaw,
+    pub ty: AstType,
+    pub parser: AstParser,
+    pub check: AstCheck,
+    pub requires: AstRequires,
     pub conflicts: AstConflicts,
     pub help: AstHelp,
     pub commands: AstCommands,
@@ -60,12 +106,53 @@ pub struct AstOptionShort(pub Ident);
 
 pub struct AstOptionLongs(pub Vec<AstOptionLong>);
 
-pub struct AstOptionLong(pub Ident);
+pub struct AstOptionLong(pub Ident, pub bool);
+
+pub struct AstOptionValue(pub Option<AstValue>);
+
+pub struct AstValue {
+    pub optional: bool,
+    pub idents: Vec<Ident>,
+    pub delimited: bool,
+    pub ty: AstType,
+    pub choices: AstChoices,
+}
+
+pub enum AstType {
+    Single(TypePath),
+    Pair(TypePath, TypePath),
+}
+
+pub struct AstChoices(pub Option<Vec<AstChoice>>);
 
-pub struct AstOptionValue(pub Option<(Ident, TypePath)>);
+pub struct AstChoice(pub Vec<Ident>);
 
 pub struct AstVariadic(pub Option<DotDotDot>);
 
+pub struct AstRaw(pub Option<Span>);
+
+pub struct AstParser(pub Option<(Span, ExprPath)>);
+
+pub struct AstCheck(pub Option<AstCheckKind>);
+
+pub enum AstCheckKind {
+    Range(Span, Option<TokenStream>, RangeLimits, Option<TokenStream>),
+    Func(Span, Expr),
+}
+
+pub struct AstDefault(pub Option<AstDefaultValue>);
+
+pub enum AstDefaultValue {
+    Arg(String),
+    Expr(Block),
+}
+
+pub struct AstEnv(pub Option<Ident>);
+
+pub struct AstRequires(pub Vec<Ident>);
+
+pub struct AstGlobal(pub bool);
+
 pub struct AstConflicts(pub Vec<AstConflict>);
 
 pub struct AstConflict(pub char, pub Span, pub Option<Ident>);
@@ -75,8 +162,17 @@ pub struct AstHelp(pub Option<LitStr>);
 pub struct AstCommands(pub Option<Vec<AstCommand>>);
 
 pub struct AstCommand {
+    pub external: Option<Span>,
     pub idents: Vec<Ident>,
+    pub ty: Option<TypePath>,
     pub help: AstHelp,
+    pub body: Option<AstBody>,
+}
+
+pub struct AstBody {
+    pub settings: AstSettings,
+    pub arguments: AstArguments,
+    pub commands: Vec<AstCommand>,
 }
 
 impl Parse for Ast {
@@ -89,8 +185,36 @@ impl Parse for Ast {
             None
         };
 
+        let settings = input.parse::<AstSettings>()?;
         let arguments = input.parse::<AstArguments>()?;
-        Ok(Self { ident, arguments })
+        Ok(Self {
+            ident,
+            settings,
+            arguments,
+        })
+    }
+}
+
+impl Parse for AstSettings {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let mut settings = vec![];
+
+        while input.peek(Pound) && input.peek2(Not) {
+            settings.push(input.parse::<AstSetting>()?);
+        }
+
+        Ok(Self(settings))
+    }
+}
+
+impl Parse for AstSetting {
+    fn parse(input: ParseStream) -> Result<Self> {
+        input.parse::<Pound>()?;
+        input.parse::<Not>()?;
+        let content;
+        bracketed!(content in input);
+        let setting = content.parse::<Ident>()?;
+        Ok(Self(setting))
     }
 }
 
@@ -106,7 +230,22 @@ impl Parse for AstArguments {
 
 impl Parse for AstArgument {
     fn parse(input: ParseStream) -> Result<Self> {
-        if input.peek(Minus) {
+        // A trailing non-option, `-- <name>` or `-- [<name>]`, not a negatable `--[no_]name`
+        let trailing = || -> Result<bool> {
+            let fork = input.fork();
+            fork.parse::<Minus>()?;
+            fork.parse::<Minus>()?;
+            if !fork.peek(Bracket) {
+                return Ok(fork.peek(Lt));
+            }
+            let content;
+            bracketed!(content in fork);
+            Ok(content.peek(Lt))
+        };
+
+        if trailing().unwrap_or(false) {
+            input.parse().map(AstArgument::NonOption)
+        } else if input.peek(Minus) {
             input.parse().map(AstArgument::Option)
         } else if input.peek(Lt) || input.peek(Bracket) {
             input.parse().map(AstArgument::NonOption)
@@ -123,6 +262,12 @@ impl Parse for AstOption {
         let longs = input.parse::<AstOptionLongs>()?;
         let variadic = input.parse::<AstVariadic>()?;
         let value = input.parse::<AstOptionValue>()?;
+        let parser = input.parse::<AstParser>()?;
+        let check = input.parse::<AstCheck>()?;
+        let default = input.parse::<AstDefault>()?;
+        let env = input.parse::<AstEnv>()?;
+        let requires = input.parse::<AstRequires>()?;
+        let global = input.parse::<AstGlobal>()?;
         let conflicts = input.parse::<AstConflicts>()?;
         let help = input.parse::<AstHelp>()?;
 
@@ -132,6 +277,12 @@ impl Parse for AstOption {
             longs,
             variadic,
             value,
+            parser,
+            check,
+            default,
+            env,
+            requires,
+            global,
             conflicts,
             help,
         })
@@ -149,6 +300,12 @@ impl Parse for AstNonOption {
         }
 
         let span = input.span();
+        let trailing = input.peek(Minus);
+        if trailing {
+            input.parse::<Minus>()?;
+            input.parse::<Minus>()?;
+        }
+
         let (optional, ident, variadic) = if input.peek(Bracket) {
             let content;
             bracketed!(content in input);
@@ -159,17 +316,29 @@ impl Parse for AstNonOption {
             (false, ident, variadic)
         };
 
-        let ty = input.parse::<TypePath>()?;
+        let raw = input.parse::<AstRaw>()?;
+        let ty = match raw.0 {
+            Some(span) => AstType::Single(parse_quote_spanned! { span => String }),
+            None => input.parse::<AstType>()?,
+        };
+        let parser = input.parse::<AstParser>()?;
+        let check = input.parse::<AstCheck>()?;
+        let requires = input.parse::<AstRequires>()?;
         let conflicts = input.parse::<AstConflicts>()?;
         let help = input.parse::<AstHelp>()?;
         let commands = input.parse::<AstCommands>()?;
 
         Ok(Self {
             span,
+            trailing,
             optional,
             ident,
             variadic,
+            raw,
             ty,
+            parser,
+            check,
+            requires,
             conflicts,
             help,
             commands,
@@ -213,24 +382,121 @@ impl Parse for AstOptionLong {
     fn parse(input: ParseStream) -> Result<Self> {
         input.parse::<Minus>()?;
         input.parse::<Minus>()?;
+
+        let negatable = if input.peek(Bracket) {
+            let content;
+            bracketed!(content in input);
+            let no = content.parse::<Ident>()?;
+            if no != NO {
+                return Err(Error::new(no.span(), format!("expected '{NO}'")));
+            }
+            true
+        } else {
+            false
+        };
+
         let long = input.parse::<Ident>()?;
-        Ok(Self(long))
+        Ok(Self(long, negatable))
     }
 }
 
 impl Parse for AstOptionValue {
     fn parse(input: ParseStream) -> Result<Self> {
-        let value = if input.peek(Lt) {
+        fn value(input: ParseStream, delimited: &mut bool) -> Result<Ident> {
             input.parse::<Lt>()?;
             let ident = input.parse::<Ident>()?;
+            if input.peek(Comma) {
+                input.parse::<Comma>()?;
+                input.parse::<DotDotDot>()?;
+                *delimited = true;
+            }
             input.parse::<Gt>()?;
-            let type_path = input.parse::<TypePath>()?;
-            Some((ident, type_path))
+            Ok(ident)
+        }
+
+        let mut delimited = false;
+
+        let (optional, idents) = if input.peek(Lt) {
+            let mut idents = vec![];
+            while input.peek(Lt) {
+                idents.push(value(input, &mut delimited)?);
+            }
+            (false, idents)
+        } else if input.peek(Bracket) {
+            let content;
+            bracketed!(content in input);
+            (true, vec![value(&content, &mut delimited)?])
         } else {
-            None
+            return Ok(Self(None));
         };
 
-        Ok(Self(value))
+        let ty = input.parse::<AstType>()?;
+        let choices = input.parse::<AstChoices>()?;
+
+        Ok(Self(Some(AstValue {
+            optional,
+            idents,
+            delimited,
+            ty,
+            choices,
+        })))
+    }
+}
+
+impl Parse for AstType {
+    fn parse(input: ParseStream) -> Result<Self> {
+        if !input.peek(Paren) {
+            return Ok(Self::Single(input.parse::<TypePath>()?));
+        }
+
+        let content;
+        parenthesized!(content in input);
+        let key = content.parse::<TypePath>()?;
+        content.parse::<Comma>()?;
+        let value = content.parse::<TypePath>()?;
+
+        if !content.is_empty() {
+            return Err(content.error("expected key and value type"));
+        }
+
+        Ok(Self::Pair(key, value))
+    }
+}
+
+impl Parse for AstChoices {
+    fn parse(input: ParseStream) -> Result<Self> {
+        if !input.peek(Brace) {
+            return Ok(Self(None));
+        }
+
+        let content;
+        braced!(content in input);
+        let choices = Punctuated::<AstChoice, Comma>::parse_terminated(&content)?
+            .into_iter()
+            .collect();
+
+        Ok(Self(Some(choices)))
+    }
+}
+
+impl Parse for AstChoice {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let mut idents = vec![input.parse::<Ident>()?];
+
+        while input.peek(Ident) {
+            idents.push(input.parse::<Ident>()?);
+        }
+
+        Ok(Self(idents))
+    }
+}
+
+impl ToTokens for AstType {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        match self {
+            Self::Single(ty) => ty.to_tokens(tokens),
+            Self::Pair(key, value) => quote! { (#key, #value) }.to_tokens(tokens),
+        }
     }
 }
 
@@ -246,11 +512,200 @@ impl Parse for AstVariadic {
     }
 }
 
+impl Parse for AstRaw {
+    fn parse(input: ParseStream) -> Result<Self> {
+        if !input
+            .fork()
+            .parse::<Ident>()
+            .is_ok_and(|ident| ident == RAW)
+        {
+            return Ok(Self(None));
+        }
+
+        let span = input.parse::<Ident>()?.span();
+
+        Ok(Self(Some(span)))
+    }
+}
+
+impl Parse for AstParser {
+    fn parse(input: ParseStream) -> Result<Self> {
+        if !input
+            .fork()
+            .parse::<Ident>()
+            .is_ok_and(|ident| ident == WITH)
+        {
+            return Ok(Self(None));
+        }
+
+        let span = input.parse::<Ident>()?.span();
+        let path = input.parse::<ExprPath>()?;
+
+        Ok(Self(Some((span, path))))
+    }
+}
+
+impl Parse for AstCheck {
+    fn parse(input: ParseStream) -> Result<Self> {
+        fn bound(input: ParseStream) -> Result<Option<TokenStream>> {
+            let minus = match input.peek(Minus) {
+                true => Some(input.parse::<Minus>()?),
+                false => None,
+            };
+
+            if input.peek(LitInt) || input.peek(LitFloat) || input.peek(LitChar) {
+                let lit = input.parse::<Lit>()?;
+                Ok(Some(quote! { #minus #lit }))
+            } else if minus.is_some() {
+                Err(input.error("expected numeric literal"))
+            } else {
+                Ok(None)
+            }
+        }
+
+        if input.peek(In) {
+            let span = input.parse::<In>()?.span();
+            let start = bound(input)?;
+            let limits = input.parse::<RangeLimits>()?;
+            let end = bound(input)?;
+
+            if start.is_none() && end.is_none() {
+                return Err(Error::new(span, "expected range with at least one bound"));
+            }
+
+            if matches!(limits, RangeLimits::Closed(_)) && end.is_none() {
+                return Err(input.error("expected end bound of inclusive range"));
+            }
+
+            return Ok(Self(Some(AstCheckKind::Range(span, start, limits, end))));
+        }
+
+        if !input
+            .fork()
+            .parse::<Ident>()
+            .is_ok_and(|ident| ident == CHECK)
+        {
+            return Ok(Self(None));
+        }
+
+        let span = input.parse::<Ident>()?.span();
+        let content;
+        parenthesized!(content in input);
+        let expr = content.parse::<Expr>()?;
+
+        Ok(Self(Some(AstCheckKind::Func(span, expr))))
+    }
+}
+
+impl Parse for AstDefault {
+    fn parse(input: ParseStream) -> Result<Self> {
+        if !input.peek(Eq) {
+            return Ok(Self(None));
+        }
+
+        input.parse::<Eq>()?;
+
+        if input.peek(Brace) {
+            return Ok(Self(Some(AstDefaultValue::Expr(input.parse::<Block>()?))));
+        }
+
+        let minus = match input.peek(Minus) {
+            true => Some(input.parse::<Minus>()?),
+            false => None,
+        };
+
+        let arg = match input.parse::<Lit>()? {
+            Lit::Str(lit) if minus.is_none() => lit.value(),
+            Lit::Char(lit) if minus.is_none() => lit.value().to_string(),
+            Lit::Bool(lit) if minus.is_none() => lit.value().to_string(),
+            Lit::Int(lit) => lit.base10_digits().to_string(),
+            Lit::Float(lit) => lit.base10_digits().to_string(),
+            lit => return Err(Error::new(lit.span(), "unsupported default value")),
+        };
+
+        let arg = match minus {
+            Some(_) => format!("-{arg}"),
+            None => arg,
+        };
+
+        Ok(Self(Some(AstDefaultValue::Arg(arg))))
+    }
+}
+
+impl Parse for AstEnv {
+    fn parse(input: ParseStream) -> Result<Self> {
+        if !input.peek(Ident)
+            || input
+                .fork()
+                .parse::<Ident>()
+                .is_ok_and(|ident| ident == REQUIRES || ident == GLOBAL)
+        {
+            return Ok(Self(None));
+        }
+
+        let env = input.parse::<Ident>()?;
+        if env != ENV {
+            return Err(Error::new(
+                env.span(),
+                format!("expected '{ENV}', '{REQUIRES}' or '{GLOBAL}'"),
+            ));
+        }
+
+        let content;
+        parenthesized!(content in input);
+        let var = content.parse::<Ident>()?;
+
+        Ok(Self(Some(var)))
+    }
+}
+
+impl Parse for AstRequires {
+    fn parse(input: ParseStream) -> Result<Self> {
+        if !input.peek(Ident)
+            || input
+                .fork()
+                .parse::<Ident>()
+                .is_ok_and(|ident| ident == GLOBAL)
+        {
+            return Ok(Self(vec![]));
+        }
+
+        let requires = input.parse::<Ident>()?;
+        if requires != REQUIRES {
+            return Err(Error::new(
+                requires.span(),
+                format!("expected '{REQUIRES}'"),
+            ));
+        }
+
+        let content;
+        parenthesized!(content in input);
+        let idents = Punctuated::<Ident, Comma>::parse_separated_nonempty(&content)?;
+
+        Ok(Self(idents.into_iter().collect()))
+    }
+}
+
+impl Parse for AstGlobal {
+    fn parse(input: ParseStream) -> Result<Self> {
+        if !input.peek(Ident) {
+            return Ok(Self(false));
+        }
+
+        let global = input.parse::<Ident>()?;
+        if global != GLOBAL {
+            return Err(Error::new(global.span(), format!("expected '{GLOBAL}'")));
+        }
+
+        Ok(Self(true))
+    }
+}
+
 impl Parse for AstConflicts {
     fn parse(input: ParseStream) -> Result<Self> {
         let mut conflicts = vec![];
 
-        while input.peek(Question) || input.peek(Not) {
+        while input.peek(Question) || input.peek(Not) || input.peek(Plus) || input.peek(And) {
             conflicts.push(input.parse::<AstConflict>()?);
         }
 
@@ -263,6 +718,12 @@ impl Parse for AstConflict {
         let (kind, span) = if input.peek(Question) {
             let question = input.parse::<Question>()?;
             ('?', question.span())
+        } else if input.peek(Plus) {
+            let plus = input.parse::<Plus>()?;
+            ('+', plus.span())
+        } else if input.peek(And) {
+            let and = input.parse::<And>()?;
+            ('&', and.span())
         } else {
             let not = input.parse::<Not>()?;
             ('!', not.span())
@@ -291,15 +752,80 @@ impl Parse for AstHelp {
 
 impl Parse for AstCommand {
     fn parse(input: ParseStream) -> Result<Self> {
-        let mut idents = vec![];
+        // Unknown commands are external commands, if declared by `_`
+        if input.peek(Underscore) {
+            let underscore = input.parse::<Underscore>()?;
+            return Ok(Self {
+                external: Some(underscore.span),
+                idents: vec![],
+                ty: None,
+                help: AstHelp(None),
+                body: None,
+            });
+        }
 
+        let mut idents = vec![];
         while input.peek(Ident) {
             idents.push(input.parse::<Ident>()?);
         }
 
+        // The command names are followed by an optional arguments type, declared as `: Type`
+        let ty = match input.peek(Colon) {
+            true => {
+                input.parse::<Colon>()?;
+                Some(input.parse::<TypePath>()?)
+            }
+            false => None,
+        };
+
         let help = input.parse::<AstHelp>()?;
 
-        Ok(Self { idents, help })
+        let body = if input.peek(Brace) {
+            Some(input.parse::<AstBody>()?)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            external: None,
+            idents,
+            ty,
+            help,
+            body,
+        })
+    }
+}
+
+// The arguments of a nested command, where commands of the nested command are declared
+// directly, without a command argument
+impl Parse for AstBody {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let content;
+        braced!(content in input);
+
+        let settings = content.parse::<AstSettings>()?;
+        let mut arguments = vec![];
+        let mut commands = vec![];
+
+        while !content.is_empty() {
+            if content.peek(Ident) || content.peek(Underscore) {
+                commands.push(content.parse::<AstCommand>()?);
+            } else {
+                arguments.push(content.parse::<AstArgument>()?);
+            }
+
+            if content.is_empty() {
+                break;
+            }
+
+            content.parse::<Comma>()?;
+        }
+
+        Ok(Self {
+            settings,
+            arguments: AstArguments(arguments),
+            commands,
+        })
     }
 }
 
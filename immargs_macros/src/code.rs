@@ -27,14 +27,65 @@ pub fn emit(ir: Ir) -> Result<TokenStream> {
         let field = &arg.field;
         let shorts = &arg.shorts;
         let longs = &arg.longs;
+        let negations = &arg.negations;
+        let default = &arg.default;
+        let env = &arg.env;
+        let global = &arg.global;
+        let parser = &arg.parser;
+        let check = &arg.check;
+        let requires = &arg.requires;
         let conflicts = &arg.conflicts;
         let variable = format_ident!("option_{field}");
+        let wrapper = format_ident!("Parse_{field}");
+
+        let value_ty = match kind {
+            IrOptionKind::Value(ty)
+            | IrOptionKind::Values(ty, _)
+            | IrOptionKind::Delimited(ty)
+            | IrOptionKind::OptionalValue(ty) => Some(ty),
+            _ => None,
+        };
+
+        let value_ty = match (value_ty, parser) {
+            (Some(ty), Some(parser)) => {
+                declare_options.push(wrapper_type(&wrapper, ty, parser));
+                code! { #wrapper }
+            }
+            (ty, _) => code! { #ty },
+        };
+
+        let parse = parse_fn(&quote! { #value_ty });
+
+        let into = unwrap(quote! { #variable.into() }, parser.is_some());
 
         let field_ty = match (kind, variadic) {
+            (IrOptionKind::Value(ty), false) if default.is_some() => code! { #ty },
+            (IrOptionKind::Delimited(ty), false) if default.is_some() => code! { Vec<#ty> },
             (IrOptionKind::NoValue, false) => code! { bool },
             (IrOptionKind::NoValue, true) => code! { usize },
+            (IrOptionKind::Negatable, _) if default.is_some() => code! { bool },
+            (IrOptionKind::Negatable, _) => code! { Option<bool> },
             (IrOptionKind::Value(ty), false) => code! { Option<#ty> },
             (IrOptionKind::Value(ty), true) => code! { Vec<#ty> },
+            (IrOptionKind::Values(ty, names), false) => {
+                let tys = names.iter().map(|_| ty);
+                code! { Option<(#(#tys),*)> }
+            }
+            (IrOptionKind::Values(ty, names), true) => {
+                let tys = names.iter().map(|_| ty);
+                code! { Vec<(#(#tys),*)> }
+            }
+            (IrOptionKind::Delimited(ty), false) => code! { Option<Vec<#ty>> },
+            (IrOptionKind::Delimited(ty), true) => code! { Vec<#ty> },
+            (IrOptionKind::OptionalValue(ty), false) => code! { Option<Option<#ty>> },
+            (IrOptionKind::OptionalValue(ty), true) => code! { Vec<Option<#ty>> },
+            (IrOptionKind::KeyValue(IrKeyValue { key, value, .. }), false) => {
+                code! { Option<(#key, #value)> }
+            }
+            (IrOptionKind::KeyValue(IrKeyValue { map: Some(map), .. }), true) => code! { #map },
+            (IrOptionKind::KeyValue(IrKeyValue { key, value, .. }), true) => {
+                code! { Vec<(#key, #value)> }
+            }
             _ => None,
         };
 
@@ -52,8 +103,54 @@ pub fn emit(ir: Ir) -> Result<TokenStream> {
             [&shorts[..], &longs[..]].concat()
         };
 
+        let negations = negations
+            .iter()
+            .map(|negation| format!("--{negation}"))
+            .collect::<Vec<_>>();
+
+        let names = [&names[..], &negations[..]].concat();
+
+        // Default values are only evaluated if the option is absent
+        let default_name = names
+            .iter()
+            .find(|name| name.starts_with("--"))
+            .unwrap_or(&names[0]);
+
+        let default = match (kind, default) {
+            (_, Some(IrDefault::Expr(expr))) => code! {
+                #into.unwrap_or_else(|| #expr)
+            },
+            (IrOptionKind::Negatable, Some(IrDefault::Arg(arg))) => {
+                let value = arg == "true";
+                code! { #into.unwrap_or(#value) }
+            }
+            (IrOptionKind::Delimited(_), Some(IrDefault::Arg(arg))) => {
+                let default = quote! { __private::default_delimited(#default_name, #arg, #parse) };
+                let default = unwrap_result(default, parser.is_some());
+                code! { #into.map_or_else(|| #default, Ok)? }
+            }
+            (IrOptionKind::Value(_), Some(IrDefault::Arg(arg))) => {
+                let default = quote! { __private::default(#default_name, #arg, #parse) };
+                let default = unwrap_result(default, parser.is_some());
+                code! { #into.map_or_else(|| #default, Ok)? }
+            }
+            _ => None,
+        };
+
         let build_value_or_version_or_help = match kind {
-            IrOptionKind::Value(ty) => code! { .value::<#ty>() },
+            IrOptionKind::Negatable => code! { .negatable(&[#(#negations),*]) },
+            IrOptionKind::Value(_) => code! { .value::<#value_ty>(#parse) },
+            IrOptionKind::Values(_, names) => {
+                let n = names.len();
+                code! { .values::<#value_ty, #n>(&[#(#names),*], #parse) }
+            }
+            IrOptionKind::Delimited(_) => code! { .delimited::<#value_ty>(#parse) },
+            IrOptionKind::OptionalValue(_) => code! { .optional_value::<#value_ty>(#parse) },
+            IrOptionKind::KeyValue(IrKeyValue { key, value, .. }) => {
+                let parse_key = parse_fn(key);
+                let parse_value = parse_fn(value);
+                code! { .key_value::<#key, #value>(#parse_key, #parse_value) }
+            }
             IrOptionKind::Version => code! { .version(name, version) },
             IrOptionKind::Help => code! { .help(&help) },
             _ => None,
@@ -64,16 +161,43 @@ pub fn emit(ir: Ir) -> Result<TokenStream> {
             _ => None,
         };
 
+        let build_env = match env {
+            Some(env) => code! { .env(#env) },
+            _ => None,
+        };
+
+        let build_global = match global {
+            true => code! { .global() },
+            _ => None,
+        };
+
         let build_conflicts = match conflicts.is_empty() {
             false => code! { .conflicts(&[#(#conflicts),*]) },
             _ => None,
         };
 
+        let build_requires = match requires.is_empty() {
+            false => code! { .requires(&[#(#requires),*]) },
+            _ => None,
+        };
+
+        let build_check = match (&value_ty, check) {
+            (Some(_), Some(check)) => {
+                let check = check_fn(check, parser.is_some());
+                code! { .check(#check) }
+            }
+            _ => None,
+        };
+
         declare_options.push(code! {
             let mut #variable = __private::option(&[#(#names),*])
                 #build_value_or_version_or_help
                 #build_variadic
+                #build_env
+                #build_global
                 #build_conflicts
+                #build_requires
+                #build_check
                 ;
         });
 
@@ -83,10 +207,31 @@ pub fn emit(ir: Ir) -> Result<TokenStream> {
             _ => code! { pub #field: #field_ty, },
         });
 
-        assign_fields.push(match kind {
-            IrOptionKind::Version => None,
-            IrOptionKind::Help => None,
-            _ => code! { #field: #variable.into(), },
+        assign_fields.push(match (kind, variadic) {
+            _ if default.is_some() => code! {
+                #field: #default,
+            },
+            (IrOptionKind::Version, _) => None,
+            (IrOptionKind::Help, _) => None,
+            (IrOptionKind::Values(_, names), false) => {
+                let values = (0..names.len()).map(|i| format_ident!("value{i}"));
+                let tuple = values.clone();
+                code! { #field: #into.map(|[#(#values),*]| (#(#tuple),*)), }
+            }
+            (IrOptionKind::Values(_, names), true) => {
+                let values = (0..names.len()).map(|i| format_ident!("value{i}"));
+                let tuple = values.clone();
+                code! {
+                    #field: #into
+                        .into_iter()
+                        .map(|[#(#values),*]| (#(#tuple),*))
+                        .collect(),
+                }
+            }
+            (IrOptionKind::KeyValue(IrKeyValue { map: Some(_), .. }), true) => code! {
+                #field: #variable.into().into_iter().collect(),
+            },
+            _ => code! { #field: #into, },
         });
 
         setters_options.push(code! { #variable.as_setter(), });
@@ -96,22 +241,59 @@ pub fn emit(ir: Ir) -> Result<TokenStream> {
         let kind = &arg.kind;
         let optional = &arg.optional;
         let variadic = &arg.variadic;
+        let trailing = &arg.trailing;
+        let raw = &arg.raw;
         let field = &arg.field;
         let name = &arg.name;
+        let parser = &arg.parser;
+        let check = &arg.check;
+        let requires = &arg.requires;
         let conflicts = &arg.conflicts;
         let variable = format_ident!("non_option_{field}");
+        let wrapper = format_ident!("Parse_{field}");
+
+        let value_ty = match (kind, parser) {
+            (IrNonOptionKind::Value(ty), Some(parser)) => {
+                declare_non_options.push(wrapper_type(&wrapper, ty, parser));
+                code! { #wrapper }
+            }
+            (IrNonOptionKind::Value(ty), None) => code! { #ty },
+            _ => None,
+        };
+
+        let into = unwrap(quote! { #variable.into() }, parser.is_some());
 
         let field_ty = match (kind, optional, variadic) {
             (IrNonOptionKind::Value(ty), false, false) => code! { #ty },
             (IrNonOptionKind::Value(ty), true, false) => code! { Option<#ty> },
             (IrNonOptionKind::Value(ty), _, true) => code! { Vec<#ty> },
-            (IrNonOptionKind::Command((ty, _)), false, _) => code! { #ty },
-            (IrNonOptionKind::Command((ty, _)), true, _) => code! { Option<#ty> },
+            (IrNonOptionKind::KeyValue(IrKeyValue { key, value, .. }), false, false) => {
+                code! { (#key, #value) }
+            }
+            (IrNonOptionKind::KeyValue(IrKeyValue { key, value, .. }), true, false) => {
+                code! { Option<(#key, #value)> }
+            }
+            (IrNonOptionKind::KeyValue(IrKeyValue { map: Some(map), .. }), _, true) => {
+                code! { #map }
+            }
+            (IrNonOptionKind::KeyValue(IrKeyValue { key, value, .. }), _, true) => {
+                code! { Vec<(#key, #value)> }
+            }
+            (IrNonOptionKind::Command((ty, ..)), false, _) => code! { #ty },
+            (IrNonOptionKind::Command((ty, ..)), true, _) => code! { Option<#ty> },
         };
 
         let build_value_or_command = match kind {
-            IrNonOptionKind::Value(ty) => code! { .value::<#ty>() },
-            IrNonOptionKind::Command((ty, _)) => code! { .command::<#ty>() },
+            IrNonOptionKind::Value(_) => {
+                let parse = parse_fn(&quote! { #value_ty });
+                code! { .value::<#value_ty>(#parse) }
+            }
+            IrNonOptionKind::KeyValue(IrKeyValue { key, value, .. }) => {
+                let parse_key = parse_fn(key);
+                let parse_value = parse_fn(value);
+                code! { .key_value::<#key, #value>(#parse_key, #parse_value) }
+            }
+            IrNonOptionKind::Command((ty, ..)) => code! { .command::<#ty>() },
         };
 
         let build_optional = match optional {
@@ -124,17 +306,50 @@ pub fn emit(ir: Ir) -> Result<TokenStream> {
             _ => None,
         };
 
+        let build_trailing = match trailing {
+            true => code! { .trailing() },
+            _ => None,
+        };
+
+        let build_raw = match raw {
+            true => code! { .raw() },
+            _ => None,
+        };
+
         let build_conflicts = match conflicts.is_empty() {
             false => code! { .conflicts(&[#(#conflicts),*]) },
             _ => None,
         };
 
+        let build_requires = match requires.is_empty() {
+            false => code! { .requires(&[#(#requires),*]) },
+            _ => None,
+        };
+
+        let build_check = match check {
+            Some(check) => {
+                let check = check_fn(check, parser.is_some());
+                code! { .check(#check) }
+            }
+            _ => None,
+        };
+
+        let build_abbreviate = match (kind, ir.settings.abbreviate) {
+            (IrNonOptionKind::Command(_), true) => code! { .abbreviate() },
+            _ => None,
+        };
+
         declare_non_options.push(code! {
             let mut #variable = __private::non_option(#name)
                 #build_value_or_command
                 #build_optional
                 #build_variadic
+                #build_trailing
+                #build_raw
                 #build_conflicts
+                #build_requires
+                #build_check
+                #build_abbreviate
                 ;
         });
 
@@ -144,10 +359,13 @@ pub fn emit(ir: Ir) -> Result<TokenStream> {
 
         assign_fields.push(match kind {
             IrNonOptionKind::Command(_) => code! {
-                #field: #variable.into(&bin_name)?,
+                #field: #variable.into(),
+            },
+            IrNonOptionKind::KeyValue(IrKeyValue { map: Some(_), .. }) => code! {
+                #field: #variable.into().into_iter().collect(),
             },
             _ => code! {
-                #field: #variable.into(),
+                #field: #into,
             },
         });
 
@@ -155,9 +373,11 @@ pub fn emit(ir: Ir) -> Result<TokenStream> {
     }
 
     let ident = &ir.ident;
+    let settings = settings(&ir);
     let version = version(&ir);
     let help = help(&ir);
     let command = command(&ir);
+    let choices = choices(&ir);
 
     Ok(quote! {
         #[allow(unused)]
@@ -168,17 +388,32 @@ pub fn emit(ir: Ir) -> Result<TokenStream> {
 
         #command
 
+        #(#choices)*
+
         #[allow(unused)]
         #[automatically_derived]
         impl ::immargs::FromArgs for #ident {
-            fn from_args(mut args: ::immargs::Args) -> ::immargs::Result<Self> {
+            fn from_args(
+                args: ::immargs::Args,
+                config: Option<&::std::path::Path>,
+            ) -> ::immargs::Result<Self> {
+                let config = config.map(::immargs::__private::Config::new);
+                Self::from_args_with_globals(args, config.as_ref(), &mut [])
+            }
+
+            fn from_args_with_globals(
+                mut args: ::immargs::Args,
+                config: Option<&::immargs::__private::Config>,
+                globals: &mut [&mut dyn ::immargs::__private::ArgOptionSetter],
+            ) -> ::immargs::Result<Self> {
                 use ::immargs::__private;
                 #version
                 let bin_name = __private::bin_name(&mut args);
                 #help
+                #settings
                 #(#declare_options)*
                 #(#declare_non_options)*
-                __private::parse(args, &mut [#(#setters_options)*], &mut [#(#setters_non_options)*])?;
+                __private::parse(args, &bin_name, settings, config, globals, &mut [#(#setters_options)*], &mut [#(#setters_non_options)*])?;
                 Ok(Self {
                     #(#assign_fields)*
                 })
@@ -188,49 +423,144 @@ pub fn emit(ir: Ir) -> Result<TokenStream> {
         #[allow(unused)]
         #[automatically_derived]
         impl #ident {
-            pub fn try_from<T: IntoIterator<Item: Into<String>>>(args: T) -> ::immargs::Result<Self> {
+            pub fn try_from<T: IntoIterator<Item: Into<::std::ffi::OsString>>>(args: T) -> ::immargs::Result<Self> {
                 ::immargs::__private::try_from(args)
             }
 
+            pub fn try_from_os<T: IntoIterator<Item = ::std::ffi::OsString>>(args: T) -> ::immargs::Result<Self> {
+                ::immargs::__private::try_from_os(args)
+            }
+
             pub fn try_from_env() -> ::immargs::Result<Self> {
                 ::immargs::__private::try_from_env()
             }
 
-            pub fn from<T: IntoIterator<Item: Into<String>>>(args: T) -> Self {
+            pub fn from<T: IntoIterator<Item: Into<::std::ffi::OsString>>>(args: T) -> Self {
                 ::immargs::__private::from(args)
             }
 
+            pub fn from_os<T: IntoIterator<Item = ::std::ffi::OsString>>(args: T) -> Self {
+                ::immargs::__private::from_os(args)
+            }
+
             pub fn from_env() -> Self {
                 ::immargs::__private::from_env()
             }
+
+            pub fn try_from_with_config<T: IntoIterator<Item: Into<::std::ffi::OsString>>>(
+                args: T,
+                config: impl AsRef<::std::path::Path>,
+            ) -> ::immargs::Result<Self> {
+                ::immargs::__private::try_from_with_config(args, config.as_ref())
+            }
+
+            pub fn try_from_env_with_config(
+                config: impl AsRef<::std::path::Path>,
+            ) -> ::immargs::Result<Self> {
+                ::immargs::__private::try_from_env_with_config(config.as_ref())
+            }
+
+            pub fn from_with_config<T: IntoIterator<Item: Into<::std::ffi::OsString>>>(
+                args: T,
+                config: impl AsRef<::std::path::Path>,
+            ) -> Self {
+                ::immargs::__private::from_with_config(args, config.as_ref())
+            }
+
+            pub fn from_env_with_config(config: impl AsRef<::std::path::Path>) -> Self {
+                ::immargs::__private::from_env_with_config(config.as_ref())
+            }
         }
     })
 }
 
-fn command(ir: &Ir) -> Option<TokenStream> {
-    fn variant(s: &str) -> Ident {
-        let mut next_to_uppercase = true;
-        let normalized = s
-            .chars()
-            .filter_map(|c| match (c, next_to_uppercase) {
-                ('-', _) => {
-                    next_to_uppercase = true;
-                    None
-                }
-                (_, true) => {
-                    next_to_uppercase = false;
-                    c.to_uppercase().next()
-                }
-                (_, false) => {
-                    next_to_uppercase = false;
-                    c.to_lowercase().next()
-                }
-            })
-            .collect::<String>();
-        format_ident!("{normalized}")
+fn check_fn(check: &IrCheck, wrapped: bool) -> TokenStream {
+    let value = match wrapped {
+        true => quote! { &value.0 },
+        false => quote! { value },
+    };
+
+    match check {
+        IrCheck::Range(range, text) => quote! {
+            |value| __private::check_range(#value, #range, #text)
+        },
+        IrCheck::Func(func) => quote! { |value| (#func)(#value) },
+    }
+}
+
+// Parse function of a value type, preferring `FromOsStr` over `Value` when the type
+// implements both, see `Probe`
+fn parse_fn(ty: &TokenStream) -> TokenStream {
+    quote! {
+        {
+            use __private::ProbeFromOsStr as _;
+            use __private::ProbeValue as _;
+            (&&__private::Probe::<#ty>::new()).parse_fn()
+        }
     }
+}
 
-    let (ty, commands) = ir.non_options.iter().find_map(|arg| match &arg.kind {
+fn unwrap(value: TokenStream, wrapped: bool) -> TokenStream {
+    match wrapped {
+        true => quote! { __private::Unwrap::unwrap(#value) },
+        false => value,
+    }
+}
+
+fn unwrap_result(value: TokenStream, wrapped: bool) -> TokenStream {
+    match wrapped {
+        true => quote! { #value.map(__private::Unwrap::unwrap) },
+        false => value,
+    }
+}
+
+// Type wrapping a value parsed using a custom parser function, declared inside
+// `from_args()` to not be visible outside of it
+fn wrapper_type(wrapper: &Ident, ty: &TokenStream, parser: &TokenStream) -> Option<TokenStream> {
+    code! {
+        #[allow(non_camel_case_types)]
+        struct #wrapper(#ty);
+
+        impl ::immargs::Value for #wrapper {
+            fn parse(value: ::std::ffi::OsString) -> ::immargs::Result<Self> {
+                ::immargs::__private::parse_with(value, #parser).map(Self)
+            }
+        }
+
+        impl ::immargs::__private::Wrapper for #wrapper {
+            type Inner = #ty;
+
+            fn into_inner(self) -> #ty {
+                self.0
+            }
+        }
+    }
+}
+
+pub fn variant(s: &str) -> Ident {
+    let mut next_to_uppercase = true;
+    let normalized = s
+        .chars()
+        .filter_map(|c| match (c, next_to_uppercase) {
+            ('-', _) => {
+                next_to_uppercase = true;
+                None
+            }
+            (_, true) => {
+                next_to_uppercase = false;
+                c.to_uppercase().next()
+            }
+            (_, false) => {
+                next_to_uppercase = false;
+                c.to_lowercase().next()
+            }
+        })
+        .collect::<String>();
+    format_ident!("{normalized}")
+}
+
+fn command(ir: &Ir) -> Option<TokenStream> {
+    let (ty, commands, external) = ir.non_options.iter().find_map(|arg| match &arg.kind {
         IrNonOptionKind::Command(commands) => Some(commands),
         _ => None,
     })?;
@@ -240,10 +570,19 @@ fn command(ir: &Ir) -> Option<TokenStream> {
         .map(|command| {
             let first = &command.names.first().unwrap();
             let variant = variant(first);
-            quote! { #variant(::immargs::Args), }
+            let ty = match &command.ty {
+                Some(ty) => quote! { #ty },
+                None => quote! { ::immargs::Args },
+            };
+            quote! { #variant(#ty), }
         })
         .collect::<Vec<_>>();
 
+    let names = commands
+        .iter()
+        .flat_map(|command| &command.names)
+        .collect::<Vec<_>>();
+
     let match_normalize = commands
         .iter()
         .map(|command| {
@@ -258,10 +597,32 @@ fn command(ir: &Ir) -> Option<TokenStream> {
         .map(|command| {
             let first = &command.names.first().unwrap();
             let variant = variant(first);
-            quote! { #first => Ok(Self::#variant(args)), }
+            match &command.ty {
+                Some(ty) => quote! {
+                    #first => Ok(Self::#variant(<#ty as ::immargs::FromArgs>::from_args_with_globals(args, config, globals)?)),
+                },
+                None => quote! { #first => Ok(Self::#variant(args)), },
+            }
         })
         .collect::<Vec<_>>();
 
+    let (variant_external, fn_external, match_external) = match external {
+        true => (
+            quote! { External(String, ::immargs::Args), },
+            quote! {
+                fn external() -> bool {
+                    true
+                }
+            },
+            quote! { _ => Ok(Self::External(command.to_string(), args)), },
+        ),
+        false => (
+            quote! {},
+            quote! {},
+            quote! { _ => Err(::immargs::__private::invalid_command(command, Self::names())), },
+        ),
+    };
+
     let match_into_str = commands
         .iter()
         .map(|command| {
@@ -271,38 +632,155 @@ fn command(ir: &Ir) -> Option<TokenStream> {
         })
         .collect::<Vec<_>>();
 
+    // Only available if no command carries a parsed arguments type, and no command is external
+    let into_str = match !external && commands.iter().all(|command| command.ty.is_none()) {
+        true => code! {
+            #[allow(unused)]
+            #[automatically_derived]
+            impl #ty {
+                pub fn into_str(self) -> (&'static str, ::immargs::Args) {
+                    match self {
+                        #(#match_into_str)*
+                    }
+                }
+            }
+        },
+        _ => None,
+    };
+
     code! {
         #[allow(unused)]
         #[derive(Debug)]
         pub enum #ty {
             #(#variants)*
+            #variant_external
         }
         #[allow(unused)]
         #[automatically_derived]
         impl ::immargs::__private::Command for #ty {
+            fn names() -> &'static [&'static str] {
+                &[#(#names),*]
+            }
+
             fn normalize(command: &str) -> ::immargs::Result<&'static str> {
                 match command {
                     #(#match_normalize)*
-                    _ => Err(::immargs::Error::InvalidCommand { arg: command.to_string() }),
+                    _ => Err(::immargs::__private::invalid_command(command, Self::names())),
                 }
             }
 
-            fn from(command: &str, args: ::immargs::Args) -> ::immargs::Result<Self> {
+            fn from(
+                command: &str,
+                args: ::immargs::Args,
+                config: Option<&::immargs::__private::Config>,
+                globals: &mut [&mut dyn ::immargs::__private::ArgOptionSetter],
+            ) -> ::immargs::Result<Self> {
                 match command {
                     #(#match_from)*
-                    _ => Err(::immargs::Error::InvalidCommand { arg: command.to_string() }),
+                    #match_external
                 }
             }
+
+            #fn_external
         }
-        #[allow(unused)]
-        #[automatically_derived]
-        impl #ty {
-            pub fn into_str(self) -> (&'static str, ::immargs::Args) {
-                match self {
-                    #(#match_into_str)*
+        #into_str
+    }
+}
+
+fn choices(ir: &Ir) -> Vec<TokenStream> {
+    let mut enums = vec![];
+
+    for IrChoices { ident, choices } in ir.options.iter().filter_map(|arg| arg.choices.as_ref()) {
+        let variants = choices
+            .iter()
+            .map(|choice| variant(&choice.names[0]))
+            .collect::<Vec<_>>();
+
+        let names = choices
+            .iter()
+            .flat_map(|choice| &choice.names)
+            .collect::<Vec<_>>();
+
+        let match_from_str = choices
+            .iter()
+            .zip(&variants)
+            .map(|(choice, variant)| {
+                let all = &choice.names;
+                quote! { #(#all)|* => Ok(Self::#variant), }
+            })
+            .collect::<Vec<_>>();
+
+        let match_as_str = choices
+            .iter()
+            .zip(&variants)
+            .map(|(choice, variant)| {
+                let first = &choice.names[0];
+                quote! { Self::#variant => #first, }
+            })
+            .collect::<Vec<_>>();
+
+        enums.push(quote! {
+            #[allow(unused)]
+            #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+            pub enum #ident {
+                #(#variants),*
+            }
+            #[allow(unused)]
+            #[automatically_derived]
+            impl ::std::str::FromStr for #ident {
+                type Err = ::immargs::__private::InvalidChoice;
+
+                fn from_str(value: &str) -> ::std::result::Result<Self, Self::Err> {
+                    match value {
+                        #(#match_from_str)*
+                        _ => Err(::immargs::__private::InvalidChoice::new(value, &[#(#names),*])),
+                    }
                 }
             }
-        }
+            #[allow(unused)]
+            #[automatically_derived]
+            impl #ident {
+                pub fn as_str(&self) -> &'static str {
+                    match self {
+                        #(#match_as_str)*
+                    }
+                }
+            }
+            #[allow(unused)]
+            #[automatically_derived]
+            impl ::std::fmt::Display for #ident {
+                fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                    f.write_str(self.as_str())
+                }
+            }
+        });
+    }
+
+    enums
+}
+
+fn settings(ir: &Ir) -> Option<TokenStream> {
+    let build_permute = match ir.settings.permute {
+        true => code! { .permute() },
+        _ => None,
+    };
+
+    let build_abbreviate = match ir.settings.abbreviate {
+        true => code! { .abbreviate() },
+        _ => None,
+    };
+
+    let build_response_files = match ir.settings.response_files {
+        true => code! { .response_files() },
+        _ => None,
+    };
+
+    code! {
+        let settings = __private::settings()
+            #build_permute
+            #build_abbreviate
+            #build_response_files
+            ;
     }
 }
 
@@ -325,6 +803,13 @@ fn help(ir: &Ir) -> Option<TokenStream> {
             .collect::<Vec<_>>()
     }
 
+    fn help_globals(ir: &Ir) -> Vec<(&String, Option<&String>)> {
+        ir.globals
+            .iter()
+            .map(|arg| (&arg.usage, arg.help.as_ref()))
+            .collect::<Vec<_>>()
+    }
+
     fn help_non_options(ir: &Ir) -> Vec<(&String, Option<&String>)> {
         ir.non_options
             .iter()
@@ -337,7 +822,7 @@ fn help(ir: &Ir) -> Option<TokenStream> {
             .last()
             .filter(|arg| matches!(arg.kind, IrNonOptionKind::Command(_)))
             .map(|arg| {
-                let IrNonOptionKind::Command((_, commands)) = &arg.kind else {
+                let IrNonOptionKind::Command((_, commands, _)) = &arg.kind else {
                     unreachable!();
                 };
                 commands
@@ -355,15 +840,84 @@ fn help(ir: &Ir) -> Option<TokenStream> {
             .unwrap_or_default()
     }
 
+    // Groups of conflicting arguments are shown in place of their first non-option, if any,
+    // otherwise after the options. Returns the non-options of each group and its usage.
+    fn help_groups(ir: &Ir) -> Vec<(Vec<usize>, String)> {
+        let mut groups = Vec::<(&String, Vec<String>, Vec<usize>)>::new();
+
+        let options = ir.options.iter().map(|arg| {
+            let name = match (arg.longs.first(), arg.shorts.first()) {
+                (Some(long), _) => format!("--{long}"),
+                (_, Some(short)) => format!("-{short}"),
+                _ => unreachable!(),
+            };
+            (None, name, &arg.conflicts)
+        });
+
+        let non_options = ir.non_options.iter().enumerate().map(|(index, arg)| {
+            let name = match (arg.trailing, arg.variadic) {
+                (true, _) => format!("-- {}...", arg.name),
+                (false, true) => format!("{}...", arg.name),
+                (false, false) => arg.name.clone(),
+            };
+            (Some(index), name, &arg.conflicts)
+        });
+
+        for (index, name, conflicts) in options.chain(non_options) {
+            for conflict in conflicts {
+                match groups.iter_mut().find(|(group, ..)| *group == conflict) {
+                    Some((_, names, indices)) => {
+                        names.push(name.clone());
+                        indices.extend(index);
+                    }
+                    None => groups.push((conflict, vec![name.clone()], Vec::from_iter(index))),
+                }
+            }
+        }
+
+        groups
+            .into_iter()
+            .map(|(group, names, indices)| {
+                let usage = match group.chars().next() {
+                    Some('!') => format!("[{}]", names.join(" | ")),
+                    Some('?') => format!("({})", names.join(" | ")),
+                    Some('+') => format!("({})...", names.join(" | ")),
+                    _ => format!("[{}]", names.join(" ")),
+                };
+                (indices, usage)
+            })
+            .collect()
+    }
+
     fn help_usage(ir: &Ir) -> (String, String) {
         let usage0 = String::from("usage: ");
         let mut usage1 = String::new();
+        let groups = help_groups(ir);
 
         if !ir.options.is_empty() {
             usage1.push_str(" [options]");
         }
 
-        for arg in &ir.non_options {
+        for (_, usage) in groups.iter().filter(|(indices, _)| indices.is_empty()) {
+            usage1.push(' ');
+            usage1.push_str(usage);
+        }
+
+        for (index, arg) in ir.non_options.iter().enumerate() {
+            let mut in_group = false;
+
+            for (indices, usage) in &groups {
+                in_group |= indices.contains(&index);
+                if indices.first() == Some(&index) {
+                    usage1.push(' ');
+                    usage1.push_str(usage);
+                }
+            }
+
+            if in_group {
+                continue;
+            }
+
             usage1.push(' ');
             usage1.push_str(&arg.usage);
             if matches!(arg.kind, IrNonOptionKind::Command(_)) {
@@ -403,18 +957,21 @@ fn help(ir: &Ir) -> Option<TokenStream> {
         .find(|option| matches!(option.kind, IrOptionKind::Help))?;
 
     let options = help_options(ir);
+    let globals = help_globals(ir);
     let non_options = help_non_options(ir);
     let commands = help_commands(ir);
 
     let width = help0_width(&options)
+        .max(help0_width(&globals))
         .max(help0_width(&non_options))
         .max(help0_width(&commands));
 
     let (usage0, usage1) = help_usage(ir);
     let options = help_section("options", width, options);
+    let globals = help_section("global options", width, globals);
     let non_options = help_section("arguments", width, non_options);
     let commands = help_section("commands", width, commands);
-    let sections = [options, non_options, commands].concat();
+    let sections = [options, globals, non_options, commands].concat();
 
     code! {
         let help = [
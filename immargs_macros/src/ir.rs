@@ -1,6 +1,7 @@
 #![doc(hidden)]
 
 use crate::ast::*;
+use crate::code::variant;
 use proc_macro2::Span;
 use proc_macro2::TokenStream;
 use quote::format_ident;
@@ -8,13 +9,26 @@ use quote::quote;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use syn::Error;
+use syn::GenericArgument;
 use syn::Ident;
+use syn::PathArguments;
 use syn::Result;
+use syn::parse_quote;
+use syn::spanned::Spanned;
 
 pub struct Ir {
     pub ident: Ident,
+    pub settings: IrSettings,
     pub options: Vec<IrOption>,
     pub non_options: Vec<IrNonOption>,
+    pub globals: Vec<IrGlobal>,
+}
+
+#[derive(Default)]
+pub struct IrSettings {
+    pub permute: bool,
+    pub abbreviate: bool,
+    pub response_files: bool,
 }
 
 pub struct IrOption {
@@ -23,24 +37,74 @@ pub struct IrOption {
     pub field: Ident,
     pub shorts: Vec<char>,
     pub longs: Vec<String>,
+    pub negations: Vec<String>,
+    pub parser: Option<TokenStream>,
+    pub check: Option<IrCheck>,
+    pub default: Option<IrDefault>,
+    pub env: Option<String>,
+    pub global: bool,
+    pub choices: Option<IrChoices>,
+    pub requires: Vec<String>,
     pub conflicts: Vec<String>,
     pub usage: String,
     pub help: Option<String>,
 }
 
+// A global option of a command above, as shown in the help
+#[derive(Clone)]
+pub struct IrGlobal {
+    pub usage: String,
+    pub help: Option<String>,
+}
+
 pub enum IrOptionKind {
     NoValue,
+    Negatable,
     Value(TokenStream),
+    Values(TokenStream, Vec<String>),
+    Delimited(TokenStream),
+    OptionalValue(TokenStream),
+    KeyValue(IrKeyValue),
     Version,
     Help,
 }
 
+pub struct IrKeyValue {
+    pub key: TokenStream,
+    pub value: TokenStream,
+    pub map: Option<TokenStream>,
+}
+
+pub struct IrChoices {
+    pub ident: Ident,
+    pub choices: Vec<IrChoice>,
+}
+
+pub struct IrChoice {
+    pub names: Vec<String>,
+}
+
+pub enum IrCheck {
+    Range(TokenStream, String),
+    Func(TokenStream),
+}
+
+pub enum IrDefault {
+    Arg(String),
+    Expr(TokenStream),
+}
+
 pub struct IrNonOption {
     pub kind: IrNonOptionKind,
     pub optional: bool,
     pub variadic: bool,
+    pub trailing: bool,
+    pub raw: bool,
     pub field: Ident,
     pub name: String,
+    pub parser: Option<TokenStream>,
+    pub check: Option<IrCheck>,
+    pub requires: Vec<String>,
     pub conflicts: Vec<String>,
     pub usage: String,
     pub help: Option<String>,
@@ -48,11 +112,13 @@ pub struct IrNonOption {
 
 pub enum IrNonOptionKind {
     Value(TokenStream),
-    Command((TokenStream, Vec<IrCommand>)),
+    KeyValue(IrKeyValue),
+    Command((TokenStream, Vec<IrCommand>, bool)),
 }
 
 pub struct IrCommand {
     pub names: Vec<String>,
+    pub ty: Option<TokenStream>,
     pub usage: String,
     pub help: Option<String>,
 }
@@ -65,26 +131,181 @@ macro_rules! bail {
 
 const VERSION: &str = "version";
 const HELP: &str = "help";
+const PERMUTE: &str = "permute";
+const ABBREVIATE: &str = "abbreviate";
+const RESPONSE_FILES: &str = "response_files";
+const EXTERNAL: &str = "external";
+const MAPS: [&str; 2] = ["HashMap", "BTreeMap"];
 
-pub fn lower(ast: Ast) -> Result<Ir> {
+pub fn lower(ast: Ast, globals: Vec<IrGlobal>) -> Result<Ir> {
     let mut verify = Verify::default();
 
     verify_conflicts(&ast)?;
     verify_help(&ast)?;
 
     let ident = ident(&ast);
-    let options = options(&ast, &mut verify)?;
-    let non_options = non_options(&ast, &mut verify)?;
+    let settings = settings(&ast)?;
+    let mut options = options(&ast, &mut verify)?;
+    let mut non_options = non_options(&ast, &settings, &mut verify)?;
+
+    requirements(&ast, &mut options, &mut non_options)?;
 
     let ir = Ir {
         ident,
+        settings,
         options,
         non_options,
+        globals,
     };
 
     Ok(ir)
 }
 
+// Nested commands are flattened into one arguments struct each, named after the path of
+// commands leading to it, e.g. `RemoteAddArgs` for `remote add`, unless the command names
+// its struct. Commands declared directly in a nested command become a `<command>` argument,
+// with a command enum named after the same path, e.g. `RemoteCommand`. Each struct comes
+// with the global options of the commands above it, and the settings of the commands above
+// it are also applied to it.
+pub fn flatten(mut ast: Ast) -> Result<Vec<(Ast, Vec<IrGlobal>)>> {
+    let mut nested = vec![];
+    let settings = ast
+        .settings
+        .0
+        .iter()
+        .map(|AstSetting(setting)| setting.clone())
+        .collect::<Vec<_>>();
+    flatten_arguments("", &mut ast.arguments, &[], &settings, &mut nested)?;
+    nested.insert(0, (ast, vec![]));
+    Ok(nested)
+}
+
+fn flatten_globals(globals: &[IrGlobal], arguments: &AstArguments) -> Vec<IrGlobal> {
+    let mut globals = globals.to_vec();
+
+    for arg in &arguments.0 {
+        if let AstArgument::Option(arg) = arg
+            && arg.global.0
+        {
+            globals.push(IrGlobal {
+                usage: option_usage(arg),
+                help: option_help(arg),
+            });
+        }
+    }
+
+    globals
+}
+
+fn flatten_arguments(
+    prefix: &str,
+    arguments: &mut AstArguments,
+    globals: &[IrGlobal],
+    settings: &[Ident],
+    nested: &mut Vec<(Ast, Vec<IrGlobal>)>,
+) -> Result<()> {
+    let inner_globals = flatten_globals(globals, arguments);
+
+    for arg in &mut arguments.0 {
+        if let AstArgument::NonOption(AstNonOption {
+            commands: AstCommands(Some(commands)),
+            ..
+        }) = arg
+        {
+            flatten_commands(prefix, commands, &inner_globals, settings, nested)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn flatten_commands(
+    prefix: &str,
+    commands: &mut [AstCommand],
+    globals: &[IrGlobal],
+    settings: &[Ident],
+    nested: &mut Vec<(Ast, Vec<IrGlobal>)>,
+) -> Result<()> {
+    for command in commands {
+        let Some(first) = command.idents.first() else {
+            continue;
+        };
+
+        let Some(body) = command.body.take() else {
+            continue;
+        };
+
+        let path = format!("{prefix}{}", variant(&normalize_underscore(first)));
+        let ident = match &command.ty {
+            Some(ty) => match ty.path.get_ident() {
+                Some(ident) => ident.clone(),
+                None => bail!(ty.span(), "expected struct name"),
+            },
+            None => format_ident!("{path}Args", span = first.span()),
+        };
+
+        command.ty = Some(parse_quote! { #ident });
+
+        let AstBody {
+            settings: AstSettings(mut inner_settings),
+            mut arguments,
+            mut commands,
+        } = body;
+
+        for setting in settings {
+            if !inner_settings
+                .iter()
+                .any(|AstSetting(inner)| inner == setting)
+            {
+                inner_settings.push(AstSetting(setting.clone()));
+            }
+        }
+
+        let settings = inner_settings
+            .iter()
+            .map(|AstSetting(setting)| setting.clone())
+            .collect::<Vec<_>>();
+
+        flatten_arguments(&path, &mut arguments, globals, &settings, nested)?;
+
+        if !commands.is_empty() {
+            let inner_globals = flatten_globals(globals, &arguments);
+            flatten_commands(&path, &mut commands, &inner_globals, &settings, nested)?;
+
+            let span = commands[0]
+                .idents
+                .first()
+                .map_or(Span::call_site(), |ident| ident.span());
+            let ty = format_ident!("{path}Command", span = span);
+            arguments.0.push(AstArgument::NonOption(AstNonOption {
+                span,
+                trailing: false,
+                optional: false,
+                ident: format_ident!("command", span = span),
+                variadic: AstVariadic(None),
+                raw: AstRaw(None),
+                ty: AstType::Single(parse_quote! { #ty }),
+                parser: AstParser(None),
+                check: AstCheck(None),
+                requires: AstRequires(vec![]),
+                conflicts: AstConflicts(vec![]),
+                help: AstHelp(None),
+                commands: AstCommands(Some(commands)),
+            }));
+        }
+
+        let ast = Ast {
+            ident: Some(ident),
+            settings: AstSettings(inner_settings),
+            arguments,
+        };
+
+        nested.push((ast, globals.to_vec()));
+    }
+
+    Ok(())
+}
+
 fn normalize_underscore(ident: &Ident) -> String {
     ident
         .to_string()
@@ -97,10 +318,76 @@ fn normalize_ident(ident: &Ident) -> String {
     normalize_underscore(ident).to_lowercase()
 }
 
+// A key-value pair is declared as `(K, V)`, or as `HashMap<K, V>` or `BTreeMap<K, V>`
+// to collect the pairs of a variadic argument into a map.
+fn key_value(ty: &AstType) -> Option<IrKeyValue> {
+    let path = match ty {
+        AstType::Pair(key, value) => {
+            return Some(IrKeyValue {
+                key: quote! { #key },
+                value: quote! { #value },
+                map: None,
+            });
+        }
+        AstType::Single(path) => path,
+    };
+
+    let segment = path.path.segments.last()?;
+    if !MAPS.contains(&segment.ident.to_string().as_str()) {
+        return None;
+    }
+
+    let PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+
+    let mut tys = args.args.iter().filter_map(|arg| match arg {
+        GenericArgument::Type(ty) => Some(ty),
+        _ => None,
+    });
+
+    match (tys.next(), tys.next(), tys.next()) {
+        (Some(key), Some(value), None) => Some(IrKeyValue {
+            key: quote! { #key },
+            value: quote! { #value },
+            map: Some(quote! { #path }),
+        }),
+        _ => None,
+    }
+}
+
 fn ident(ast: &Ast) -> Ident {
     ast.ident.clone().unwrap_or(format_ident!("ImmArgs"))
 }
 
+fn settings(ast: &Ast) -> Result<IrSettings> {
+    let mut settings = IrSettings::default();
+    let mut seen = HashSet::new();
+
+    for AstSetting(setting) in &ast.settings.0 {
+        let span = setting.span();
+        let name = setting.to_string();
+
+        if !seen.insert(name.clone()) {
+            bail!(span, "duplicate setting");
+        }
+
+        match name.as_str() {
+            PERMUTE => settings.permute = true,
+            ABBREVIATE => settings.abbreviate = true,
+            RESPONSE_FILES => settings.response_files = true,
+            _ => bail!(
+                span,
+                format!(
+                    "unknown setting, expected '{PERMUTE}', '{ABBREVIATE}' or '{RESPONSE_FILES}'"
+                )
+            ),
+        }
+    }
+
+    Ok(settings)
+}
+
 fn options(ast: &Ast, verify: &mut Verify) -> Result<Vec<IrOption>> {
     let mut options = vec![];
     let mut allow_option = true;
@@ -116,11 +403,19 @@ fn options(ast: &Ast, verify: &mut Verify) -> Result<Vec<IrOption>> {
         }
 
         let option = IrOption {
-            kind: option_kind(arg),
+            kind: option_kind(arg)?,
             variadic: option_variadic(arg),
             field: option_field(arg, verify)?,
             shorts: option_shorts(arg, verify)?,
             longs: option_longs(arg, verify)?,
+            negations: option_negations(arg, verify)?,
+            parser: option_parser(arg)?,
+            check: option_check(arg)?,
+            default: option_default(arg)?,
+            env: option_env(arg)?,
+            global: option_global(arg),
+            choices: option_choices(arg)?,
+            requires: vec![],
             conflicts: option_conflicts(arg),
             usage: option_usage(arg),
             help: option_help(arg),
@@ -132,11 +427,64 @@ fn options(ast: &Ast, verify: &mut Verify) -> Result<Vec<IrOption>> {
     Ok(options)
 }
 
-fn option_kind(arg: &AstOption) -> IrOptionKind {
-    match &arg.value.0 {
-        None => IrOptionKind::NoValue,
-        Some((_, ty)) => IrOptionKind::Value(quote! { #ty }),
+fn option_kind(arg: &AstOption) -> Result<IrOptionKind> {
+    if let Some(value) = &arg.value.0
+        && value.delimited
+        && (value.optional || value.idents.len() > 1)
+    {
+        bail!(
+            arg.span,
+            "delimited value cannot be optional or one of multiple values"
+        );
     }
+
+    if let Some(value) = &arg.value.0
+        && let Some(key_value) = key_value(&value.ty)
+    {
+        if value.optional || value.delimited || value.idents.len() > 1 {
+            bail!(
+                arg.span,
+                "key-value pair cannot be optional, delimited or one of multiple values"
+            );
+        }
+
+        if key_value.map.is_some() && arg.variadic.0.is_none() {
+            bail!(arg.span, "map requires variadic argument");
+        }
+
+        return Ok(IrOptionKind::KeyValue(key_value));
+    }
+
+    Ok(match &arg.value.0 {
+        None if arg.longs.0.iter().any(|long| long.1) => IrOptionKind::Negatable,
+        None => IrOptionKind::NoValue,
+        Some(AstValue {
+            optional: false,
+            idents,
+            ty,
+            ..
+        }) if idents.len() > 1 => IrOptionKind::Values(
+            quote! { #ty },
+            idents
+                .iter()
+                .map(|ident| format!("<{}>", normalize_underscore(ident)))
+                .collect(),
+        ),
+        Some(AstValue {
+            optional: false,
+            delimited: true,
+            ty,
+            ..
+        }) => IrOptionKind::Delimited(quote! { #ty }),
+        Some(AstValue {
+            optional: false,
+            ty,
+            ..
+        }) => IrOptionKind::Value(quote! { #ty }),
+        Some(AstValue {
+            optional: true, ty, ..
+        }) => IrOptionKind::OptionalValue(quote! { #ty }),
+    })
 }
 
 fn option_variadic(arg: &AstOption) -> bool {
@@ -195,6 +543,200 @@ fn option_longs(arg: &AstOption, verify: &mut Verify) -> Result<Vec<String>> {
     Ok(longs)
 }
 
+fn option_negations(arg: &AstOption, verify: &mut Verify) -> Result<Vec<String>> {
+    let mut negations = vec![];
+
+    for long in arg.longs.0.iter().filter(|long| long.1) {
+        let span = long.0.span();
+
+        if arg.value.0.is_some() || arg.variadic.0.is_some() {
+            bail!(span, "negatable option cannot take a value or be variadic");
+        }
+
+        let negation = format!("no-{}", normalize_ident(&long.0));
+        verify.unique_long(&negation, span)?;
+        negations.push(negation);
+    }
+
+    Ok(negations)
+}
+
+fn option_parser(arg: &AstOption) -> Result<Option<TokenStream>> {
+    let Some((span, func)) = &arg.parser.0 else {
+        return Ok(None);
+    };
+
+    if arg
+        .value
+        .0
+        .as_ref()
+        .is_none_or(|value| key_value(&value.ty).is_some())
+    {
+        bail!(
+            *span,
+            "parser requires option with a value other than a key-value pair"
+        );
+    }
+
+    if arg
+        .value
+        .0
+        .as_ref()
+        .is_some_and(|value| value.choices.0.is_some())
+    {
+        bail!(*span, "parser cannot be combined with choices");
+    }
+
+    Ok(Some(quote! { #func }))
+}
+
+fn option_check(arg: &AstOption) -> Result<Option<IrCheck>> {
+    let Some(kind) = &arg.check.0 else {
+        return Ok(None);
+    };
+
+    if arg.value.0.is_none() {
+        bail!(check_span(kind), "validation requires option with a value");
+    }
+
+    Ok(Some(check(kind)))
+}
+
+fn check_span(kind: &AstCheckKind) -> Span {
+    match kind {
+        AstCheckKind::Range(span, ..) => *span,
+        AstCheckKind::Func(span, _) => *span,
+    }
+}
+
+fn check(kind: &AstCheckKind) -> IrCheck {
+    match kind {
+        AstCheckKind::Range(_, start, limits, end) => {
+            let range = quote! { #start #limits #end };
+            let text = range.to_string().replace(' ', "");
+            IrCheck::Range(range, text)
+        }
+        AstCheckKind::Func(_, expr) => IrCheck::Func(quote! { #expr }),
+    }
+}
+
+fn option_default(arg: &AstOption) -> Result<Option<IrDefault>> {
+    let Some(default) = &arg.default.0 else {
+        return Ok(None);
+    };
+
+    // A negatable option with a default value is a plain bool
+    if arg.value.0.is_none() && arg.longs.0.iter().any(|long| long.1) {
+        return match default {
+            AstDefaultValue::Arg(value) if value == "true" || value == "false" => {
+                Ok(Some(IrDefault::Arg(value.clone())))
+            }
+            AstDefaultValue::Arg(_) => bail!(
+                arg.span,
+                "default value of negatable option must be 'true' or 'false'"
+            ),
+            AstDefaultValue::Expr(block) => Ok(Some(IrDefault::Expr(quote! { #block }))),
+        };
+    }
+
+    if arg.variadic.0.is_some()
+        || !arg
+            .value
+            .0
+            .as_ref()
+            .is_some_and(|value| !value.optional && value.idents.len() == 1)
+    {
+        bail!(
+            arg.span,
+            "default value requires non-variadic option with a single value"
+        );
+    }
+
+    if arg
+        .value
+        .0
+        .as_ref()
+        .is_some_and(|value| key_value(&value.ty).is_some())
+    {
+        bail!(arg.span, "default value not supported for key-value pair");
+    }
+
+    match default {
+        AstDefaultValue::Arg(value) => Ok(Some(IrDefault::Arg(value.clone()))),
+        AstDefaultValue::Expr(block) => Ok(Some(IrDefault::Expr(quote! { #block }))),
+    }
+}
+
+fn option_env(arg: &AstOption) -> Result<Option<String>> {
+    let Some(env) = &arg.env.0 else {
+        return Ok(None);
+    };
+
+    if !arg
+        .value
+        .0
+        .as_ref()
+        .is_some_and(|value| !value.optional && value.idents.len() == 1)
+    {
+        bail!(
+            env.span(),
+            "environment variable requires option with a single value"
+        );
+    }
+
+    Ok(Some(env.to_string()))
+}
+
+fn option_global(arg: &AstOption) -> bool {
+    arg.global.0
+}
+
+fn option_choices(arg: &AstOption) -> Result<Option<IrChoices>> {
+    let Some(AstValue {
+        ty,
+        choices: AstChoices(Some(choices)),
+        ..
+    }) = &arg.value.0
+    else {
+        return Ok(None);
+    };
+
+    let ident = match ty {
+        AstType::Single(path) if path.qself.is_none() => path.path.get_ident(),
+        _ => None,
+    };
+
+    let Some(ident) = ident else {
+        bail!(arg.span, "choices require a type name");
+    };
+
+    if choices.is_empty() {
+        bail!(ident.span(), "expected at least one choice");
+    }
+
+    let mut seen = HashSet::new();
+    let mut ir_choices = vec![];
+
+    for AstChoice(idents) in choices {
+        let mut names = vec![];
+
+        for ident in idents {
+            let name = normalize_underscore(ident);
+            if !seen.insert(name.clone()) {
+                bail!(ident.span(), "duplicate choice");
+            }
+            names.push(name);
+        }
+
+        ir_choices.push(IrChoice { names });
+    }
+
+    Ok(Some(IrChoices {
+        ident: ident.clone(),
+        choices: ir_choices,
+    }))
+}
+
 fn option_conflicts(arg: &AstOption) -> Vec<String> {
     let mut conflicts = vec![];
 
@@ -217,19 +759,61 @@ fn option_usage(arg: &AstOption) -> String {
     }
 
     for long in &arg.longs.0 {
-        usage.push(format!("--{}", normalize_ident(&long.0)));
+        match long.1 {
+            true => usage.push(format!("--[no-]{}", normalize_ident(&long.0))),
+            false => usage.push(format!("--{}", normalize_ident(&long.0))),
+        }
     }
 
-    if let Some((value, _)) = &arg.value.0 {
+    if let Some(value) = &arg.value.0 {
         let last = usage.last_mut().unwrap();
-        last.push_str(&format!(" <{}>", normalize_underscore(value)));
+        for ident in &value.idents {
+            let name = match value.delimited {
+                true => format!("{},...", normalize_underscore(ident)),
+                false => normalize_underscore(ident),
+            };
+            match value.optional {
+                true => last.push_str(&format!("[=<{name}>]")),
+                false => last.push_str(&format!(" <{name}>")),
+            }
+        }
     }
 
     usage.join(", ")
 }
 
 fn option_help(arg: &AstOption) -> Option<String> {
-    arg.help.0.as_ref().map(|help| help.value())
+    let mut help = arg.help.0.as_ref().map(|help| help.value());
+
+    let mut annotate = |annotation: String| {
+        help = match help.take() {
+            Some(help) => Some(format!("{help} {annotation}")),
+            None => Some(annotation),
+        }
+    };
+
+    if let Some(AstValue {
+        choices: AstChoices(Some(choices)),
+        ..
+    }) = &arg.value.0
+    {
+        let names = choices
+            .iter()
+            .map(|AstChoice(idents)| normalize_underscore(&idents[0]))
+            .collect::<Vec<_>>()
+            .join(", ");
+        annotate(format!("[possible values: {names}]"));
+    }
+
+    if let Some(env) = &arg.env.0 {
+        annotate(format!("[env: {env}]"));
+    }
+
+    if let Some(AstDefaultValue::Arg(value)) = &arg.default.0 {
+        annotate(format!("[default: {value}]"));
+    }
+
+    help
 }
 
 fn option_special(arg: &AstOption, mut option: IrOption) -> Result<IrOption> {
@@ -239,13 +823,15 @@ fn option_special(arg: &AstOption, mut option: IrOption) -> Result<IrOption> {
         if option.longs.iter().any(|long| long == special_long) {
             if !matches!(option.kind, IrOptionKind::NoValue)
                 || option.variadic
+                || option.global
                 || !option.conflicts.is_empty()
+                || !arg.requires.0.is_empty()
             {
                 bail!(
                     arg.span,
                     format!(
                         "special option --{special_long} cannot take a value, \
-                        be variadic, or have conflcits"
+                        be variadic or global, or have conflicts or requirements"
                     )
                 );
             }
@@ -257,10 +843,12 @@ fn option_special(arg: &AstOption, mut option: IrOption) -> Result<IrOption> {
     Ok(option)
 }
 
-fn non_options(ast: &Ast, verify: &mut Verify) -> Result<Vec<IrNonOption>> {
+fn non_options(ast: &Ast, settings: &IrSettings, verify: &mut Verify) -> Result<Vec<IrNonOption>> {
     let mut non_options = vec![];
     let mut has_variadic = false;
     let mut has_command = false;
+    let mut has_trailing = false;
+    let mut has_raw = false;
 
     for arg in &ast.arguments.0 {
         let AstArgument::NonOption(arg) = arg else {
@@ -269,6 +857,42 @@ fn non_options(ast: &Ast, verify: &mut Verify) -> Result<Vec<IrNonOption>> {
 
         let is_variadic = arg.variadic.0.is_some();
         let is_command = arg.commands.0.is_some();
+        let is_raw = arg.raw.0.is_some();
+
+        if has_trailing {
+            bail!(arg.span, "arguments cannot follow trailing argument");
+        }
+
+        if has_raw {
+            bail!(arg.span, "arguments cannot follow raw argument");
+        }
+
+        if has_command {
+            bail!(arg.span, "arguments cannot follow command argument")
+        }
+
+        if arg.trailing {
+            if !is_variadic {
+                bail!(arg.span, "trailing argument must be variadic");
+            }
+
+            has_trailing = true;
+            non_options.push(non_option(arg, verify)?);
+            continue;
+        }
+
+        if is_raw {
+            if !is_variadic {
+                bail!(arg.span, "raw argument must be variadic");
+            }
+
+            if settings.permute {
+                bail!(
+                    arg.span,
+                    format!("raw argument cannot be used with setting '{PERMUTE}'")
+                );
+            }
+        }
 
         if is_variadic && is_command {
             bail!(arg.span, "command argument cannot be variadic");
@@ -282,52 +906,99 @@ fn non_options(ast: &Ast, verify: &mut Verify) -> Result<Vec<IrNonOption>> {
             bail!(arg.span, "command argument cannot follow variadic argument")
         }
 
-        if has_command {
-            bail!(arg.span, "arguments cannot follow command argument")
-        }
-
         has_variadic |= is_variadic;
         has_command |= is_command;
+        has_raw |= is_raw;
 
-        let non_option = IrNonOption {
-            kind: non_option_kind(arg),
-            optional: non_option_optional(arg),
-            variadic: non_option_variadic(arg),
-            field: non_option_field(arg, verify)?,
-            name: non_option_name(arg),
-            conflicts: non_option_conflicts(arg),
-            usage: non_option_usage(arg),
-            help: non_option_help(arg),
-        };
-
-        non_options.push(non_option);
+        non_options.push(non_option(arg, verify)?);
     }
 
     Ok(non_options)
 }
 
-fn non_option_kind(arg: &AstNonOption) -> IrNonOptionKind {
+fn non_option(arg: &AstNonOption, verify: &mut Verify) -> Result<IrNonOption> {
+    let non_option = IrNonOption {
+        kind: non_option_kind(arg)?,
+        optional: non_option_optional(arg),
+        variadic: non_option_variadic(arg),
+        trailing: non_option_trailing(arg),
+        raw: non_option_raw(arg),
+        field: non_option_field(arg, verify)?,
+        name: non_option_name(arg),
+        parser: non_option_parser(arg)?,
+        check: non_option_check(arg)?,
+        requires: vec![],
+        conflicts: non_option_conflicts(arg),
+        usage: non_option_usage(arg),
+        help: non_option_help(arg),
+    };
+
+    Ok(non_option)
+}
+
+fn non_option_kind(arg: &AstNonOption) -> Result<IrNonOptionKind> {
     let ty = &arg.ty;
 
     let Some(commands) = &arg.commands.0 else {
-        return IrNonOptionKind::Value(quote! { #ty });
+        let Some(key_value) = key_value(ty) else {
+            return Ok(IrNonOptionKind::Value(quote! { #ty }));
+        };
+
+        if key_value.map.is_some() && arg.variadic.0.is_none() {
+            bail!(arg.span, "map requires variadic argument");
+        }
+
+        return Ok(IrNonOptionKind::KeyValue(key_value));
     };
 
+    if matches!(ty, AstType::Pair(..)) {
+        bail!(arg.span, "command argument cannot be a key-value pair");
+    }
+
     let mut cmds = vec![];
+    let mut external = false;
 
     for command in commands {
+        if let Some(span) = command.external {
+            if external {
+                bail!(span, "duplicate external commands");
+            }
+
+            external = true;
+            continue;
+        }
+
         let names = command
             .idents
             .iter()
             .map(normalize_underscore)
             .collect::<Vec<_>>();
+
+        if names.is_empty() {
+            bail!(arg.span, "expected command name");
+        }
+
+        let ty = command.ty.as_ref().map(|ty| quote! { #ty });
         let help = command.help.0.as_ref().map(|help| help.value());
         let usage = names.join(", ");
-        let command = IrCommand { names, usage, help };
+        let command = IrCommand {
+            names,
+            ty,
+            usage,
+            help,
+        };
         cmds.push(command)
     }
 
-    IrNonOptionKind::Command((quote! { #ty }, cmds))
+    // The first name of a command gives its variant, which must not clash with `External`
+    if external && cmds.iter().any(|command| command.names[0] == EXTERNAL) {
+        bail!(
+            arg.span,
+            "command 'external' cannot be used with external commands"
+        );
+    }
+
+    Ok(IrNonOptionKind::Command((quote! { #ty }, cmds, external)))
 }
 
 fn non_option_optional(arg: &AstNonOption) -> bool {
@@ -338,6 +1009,14 @@ fn non_option_variadic(arg: &AstNonOption) -> bool {
     arg.variadic.0.is_some()
 }
 
+fn non_option_trailing(arg: &AstNonOption) -> bool {
+    arg.trailing
+}
+
+fn non_option_raw(arg: &AstNonOption) -> bool {
+    arg.raw.0.is_some()
+}
+
 fn non_option_field(arg: &AstNonOption, verify: &mut Verify) -> Result<Ident> {
     let ident = &arg.ident;
     verify.unique_field(ident)?;
@@ -349,6 +1028,36 @@ fn non_option_name(arg: &AstNonOption) -> String {
     format!("<{name}>")
 }
 
+fn non_option_parser(arg: &AstNonOption) -> Result<Option<TokenStream>> {
+    let Some((span, func)) = &arg.parser.0 else {
+        return Ok(None);
+    };
+
+    if arg.commands.0.is_some() || key_value(&arg.ty).is_some() {
+        bail!(
+            *span,
+            "parser not supported for command argument or key-value pair"
+        );
+    }
+
+    Ok(Some(quote! { #func }))
+}
+
+fn non_option_check(arg: &AstNonOption) -> Result<Option<IrCheck>> {
+    let Some(kind) = &arg.check.0 else {
+        return Ok(None);
+    };
+
+    if arg.commands.0.is_some() {
+        bail!(
+            check_span(kind),
+            "validation not supported for command argument"
+        );
+    }
+
+    Ok(Some(check(kind)))
+}
+
 fn non_option_conflicts(arg: &AstNonOption) -> Vec<String> {
     let mut conflicts = vec![];
 
@@ -366,11 +1075,13 @@ fn non_option_conflicts(arg: &AstNonOption) -> Vec<String> {
 fn non_option_usage(arg: &AstNonOption) -> String {
     let name = normalize_ident(&arg.ident);
 
-    match (arg.optional, arg.variadic.0) {
-        (true, None) => format!("[<{name}>]"),
-        (true, Some(_)) => format!("[<{name}>...]"),
-        (false, None) => format!("<{name}>"),
-        (false, Some(_)) => format!("<{name}>..."),
+    match (arg.trailing, arg.optional, arg.variadic.0) {
+        (true, true, _) => format!("[-- <{name}>...]"),
+        (true, false, _) => format!("-- <{name}>..."),
+        (false, true, None) => format!("[<{name}>]"),
+        (false, true, Some(_)) => format!("[<{name}>...]"),
+        (false, false, None) => format!("<{name}>"),
+        (false, false, Some(_)) => format!("<{name}>..."),
     }
 }
 
@@ -382,9 +1093,14 @@ fn verify_conflicts(ast: &Ast) -> Result<()> {
     let mut conflict_map = HashMap::new();
 
     for arg in &ast.arguments.0 {
-        let (non_option, conflicts) = match arg {
-            AstArgument::Option(option) => (false, &option.conflicts.0),
-            AstArgument::NonOption(non_option) => (true, &non_option.conflicts.0),
+        let (non_option, command, trailing, conflicts) = match arg {
+            AstArgument::Option(option) => (false, false, false, &option.conflicts.0),
+            AstArgument::NonOption(non_option) => (
+                true,
+                non_option.commands.0.is_some(),
+                non_option.trailing,
+                &non_option.conflicts.0,
+            ),
         };
 
         for AstConflict(kind, span, id) in conflicts {
@@ -396,7 +1112,7 @@ fn verify_conflicts(ast: &Ast) -> Result<()> {
             conflict_map
                 .entry(id)
                 .or_insert(vec![])
-                .push((*span, non_option));
+                .push((*span, non_option, command, trailing));
         }
     }
 
@@ -408,20 +1124,110 @@ fn verify_conflicts(ast: &Ast) -> Result<()> {
         }
     }
 
-    for (_, spans) in conflict_map {
+    for (id, spans) in conflict_map {
         if spans.len() == 1 {
             let span = spans[0].0;
             bail!(span, "conflict has no effect");
         }
 
-        let mut num_non_options = 0;
-        for (span, non_option) in spans {
-            if non_option {
-                num_non_options += 1;
+        // Non-options in the same `!` or `?` group are alternatives sharing one position
+        if !id.starts_with(['!', '?']) {
+            continue;
+        }
+
+        let non_options = spans
+            .iter()
+            .filter(|(_, non_option, ..)| *non_option)
+            .collect::<Vec<_>>();
+
+        if non_options.len() > 1
+            && let Some((span, ..)) = non_options.iter().find(|(_, _, command, _)| *command)
+        {
+            bail!(*span, "command argument cannot have alternatives");
+        }
+
+        if non_options.len() > 1
+            && let Some((span, ..)) = non_options.iter().find(|(.., trailing)| *trailing)
+        {
+            bail!(*span, "trailing argument cannot have alternatives");
+        }
+    }
+
+    Ok(())
+}
+
+// Requirements refer to other arguments by field name, and are resolved to the name used
+// at run-time, i.e. the first long option name, or the first short option name if there
+// is no long, or the non-option name.
+fn requirements(
+    ast: &Ast,
+    options: &mut [IrOption],
+    non_options: &mut [IrNonOption],
+) -> Result<()> {
+    let mut names = HashMap::new();
+
+    for option in options.iter() {
+        let name = match (&option.kind, option.longs.first(), option.shorts.first()) {
+            (IrOptionKind::Version | IrOptionKind::Help, _, _) => None,
+            (_, Some(long), _) => Some(format!("--{long}")),
+            (_, _, Some(short)) => Some(format!("-{short}")),
+            _ => unreachable!(),
+        };
+        names.insert(option.field.to_string(), name);
+    }
+
+    for non_option in non_options.iter() {
+        names.insert(non_option.field.to_string(), Some(non_option.name.clone()));
+    }
+
+    let mut edges = vec![];
+    let mut options = options.iter_mut();
+    let mut non_options = non_options.iter_mut();
+
+    for arg in &ast.arguments.0 {
+        let (field, requires, ir_requires) = match arg {
+            AstArgument::Option(arg) => {
+                let option = options.next().unwrap();
+                (&option.field, &arg.requires.0, &mut option.requires)
+            }
+            AstArgument::NonOption(arg) => {
+                let non_option = non_options.next().unwrap();
+                (&non_option.field, &arg.requires.0, &mut non_option.requires)
+            }
+        };
+
+        for ident in requires {
+            let name = match names.get(&ident.to_string()) {
+                Some(Some(name)) => name,
+                Some(None) => bail!(ident.span(), "cannot require special option"),
+                None => bail!(ident.span(), "no argument with this name"),
+            };
+
+            if ir_requires.contains(name) {
+                bail!(ident.span(), "duplicate requirement");
             }
 
-            if num_non_options > 1 {
-                bail!(span, "non-options cannot conflict with each other");
+            ir_requires.push(name.clone());
+        }
+
+        edges.push((field.to_string(), requires));
+    }
+
+    let graph = edges.iter().cloned().collect::<HashMap<_, _>>();
+
+    for (field, requires) in &edges {
+        for ident in requires.iter() {
+            let mut visited = HashSet::new();
+            let mut pending = vec![ident.to_string()];
+
+            while let Some(next) = pending.pop() {
+                if next == *field {
+                    bail!(ident.span(), "circular requirement");
+                }
+
+                if visited.insert(next.clone()) {
+                    pending.extend(graph[&next].iter().map(|ident| ident.to_string()));
+                }
             }
         }
     }
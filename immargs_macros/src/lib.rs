@@ -3,6 +3,7 @@
 
 use ast::Ast;
 use code::emit;
+use ir::flatten;
 use ir::lower;
 use proc_macro::TokenStream;
 use syn::parse_macro_input;
@@ -29,53 +30,97 @@ macro_rules! catch_error {
 ///
 /// `immargs! {`
 ///     \[ ___StructName___ `,` \]
+///     \[ ___Setting___ \]*
 ///     \[ ___Option___ `,` \]*
 ///     \[ ___NonOption___ `,` \]*
 /// `}`
 ///
+/// ___Setting___ := `#![` \( `permute` | `abbreviate` | `response_files` \) `]`
+///
 /// ___Option___ := \[ `-` ___Short___ \]*
-///                 \[`--` ___Long___ \]*
+///                 \[`--` \[ `[no_]` \] ___Long___ \]*
 ///                 \[ `...` \]
-///                 \[ `<` ___Value___ `>` ___Type___ \]
-///                 \[ \( `!` | `?` \) \[ ___ConflictId___ \] \]*
+///                 \[ \( \( `<` ___Value___ `>` \)+ | `<` ___Value___ `,...>` | `[<` ___Value___ `>]` \)
+///                    ___Type___ \[ ___Choices___ \] \[ `with` ___Parser___ \] \[ ___Check___ \]
+///                    \[ `=` ___Default___ \]
+///                    \[ `env(` ___Variable___ `)` \] \]
+///                 \[ `requires(` ___Field___ \[ `,` ___Field___ \]* `)` \]
+///                 \[ `global` \]
+///                 \[ \( `!` | `?` | `+` | `&` \) \[ ___ConflictId___ \] \]*
 ///                 \[ ___Help___ \]
 ///                 `,`
 ///
 /// ___NonOption___ := \( ___RequiredNonOption___ | ___OptionalNonOption___  \)*
 ///
-/// ___RequiredNonOption___ := `<` ___Name___ `>`
+/// ___RequiredNonOption___ := \[ `--` \] `<` ___Name___ `>`
 ///                            \[ `...` \]
-///                            ___Type___
-///                            \[ \( `!` | `?` \) \[ ___ConflictId___ \] \]*
+///                            \( ___Type___ | `raw` \) \[ `with` ___Parser___ \] \[ ___Check___ \]
+///                            \[ `requires(` ___Field___ \[ `,` ___Field___ \]* `)` \]
+///                            \[ \( `!` | `?` | `+` | `&` \) \[ ___ConflictId___ \] \]*
 ///                            \[ ___Help___ \]
 ///                            \[ ___Commands___ \]
 ///                            `,`
 ///
-/// ___OptionalNonOption___ := `[<` ___Name___ `>` \[ `...` \] `]`
-///                            ___Type___
-///                            \[ \( `!` | `?` \) \[ ___ConflictId___ \] \]*
+/// ___OptionalNonOption___ := \[ `--` \] `[<` ___Name___ `>` \[ `...` \] `]`
+///                            \( ___Type___ | `raw` \) \[ `with` ___Parser___ \] \[ ___Check___ \]
+///                            \[ `requires(` ___Field___ \[ `,` ___Field___ \]* `)` \]
+///                            \[ \( `!` | `?` | `+` | `&` \) \[ ___ConflictId___ \] \]*
 ///                            \[ ___Help___ \]
 ///                            \[ ___Commands___ \]
 ///                            `,`
 ///
-/// ___Commands___ := `{` \[ ___Command___ `,` \]* `}`
+/// ___Commands___ := `{` \[ \( ___Command___ | `_` \) `,` \]* `}`
+///
+/// ___Command___ := ___Name___ \[ ___Alias___ \]* \[ `:` ___ArgsType___ \] \[ ___Help___ \]
+///                 \[ `{` \[ ___Setting___ \]* \[ ___Option___ `,` \]* \[ ___NonOption___ `,` \]*
+///                     \[ \( ___Command___ | `_` \) `,` \]* `}` \]
+///
+/// ___Choices___ := `{` \[ ___Choice___ \[ ___Alias___ \]* `,` \]* `}`
 ///
-/// ___Command___ := ___Name___ \[ ___Alias___ \]* \[ ___Help___ \]
+/// ___Check___ := `in` ___Range___ | `check(` ___Function___ `)`
 ///
 /// ___StructName___ /
 /// ___Short___ /
 /// ___Long___ /
 /// ___Value___ /
 /// ___Name___ /
+/// ___Choice___ /
 /// ___Alias___ /
+/// ___Variable___ /
+/// ___Field___ /
 /// ___ConflictId___ := A Rust [non-keyword identifier](https://doc.rust-lang.org/reference/identifiers.html)
 ///
-/// ___Type___ := A Rust type that implements [`FromStr`](std::str::FromStr) + [`Debug`](std::fmt::Debug)
+/// ___Type___ := ___ValueType___ | `(` ___ValueType___ `,` ___ValueType___ `)` |
+///             \( `HashMap` | `BTreeMap` \) `<` ___ValueType___ `,` ___ValueType___ `>`
+///
+/// ___ArgsType___ := A Rust path to an arguments `struct` generated by `immargs!`, starting with
+/// an uppercase letter
+///
+/// ___ValueType___ := A Rust type that implements [`FromStr`](std::str::FromStr) (or `Value`) + [`Debug`](std::fmt::Debug)
+///
+/// ___Parser___ := A Rust path to a function callable as `Fn(&str) -> Result<T, E>` or
+/// `Fn(String) -> Result<T, E>`, where `T` is the value type
+///
+/// ___Range___ := A Rust [range expression](https://doc.rust-lang.org/reference/expressions/range-expr.html)
+/// with numeric or character literal bounds, e.g. `1..=100` or `0.0..`
+///
+/// ___Function___ := A Rust expression callable as `Fn(&T) -> Result<(), String>`, where `T` is the value type
+///
+/// ___Default___ := A Rust [literal](https://doc.rust-lang.org/reference/expressions/literal-expr.html),
+/// optionally negated, or a Rust [block expression](https://doc.rust-lang.org/reference/expressions/block-expr.html)
 ///
 /// ___Help___ := A Rust [string literal](https://doc.rust-lang.org/reference/tokens.html#r-lex.token.literal.str)
 ///
 /// ## Examples
 ///
+/// Settings:
+///
+/// ```no_rust
+/// #![permute]                                // Allow options after non-options
+/// #![abbreviate]                             // Allow abbreviated long options and commands
+/// #![response_files]                         // Expand @file arguments
+/// ```
+///
 /// Options:
 ///
 /// ```no_rust
@@ -83,6 +128,7 @@ macro_rules! catch_error {
 /// --foo,                                     // Long-option
 /// -f --foo,                                  // Short-option + long-option
 /// -f -F --foo,                               // Multiple short-options + long-option
+/// --[no_]foo,                                // Negatable long-option, --foo and --no-foo
 ///
 /// -f <bar> u64,                              // With u64 value named "bar"
 /// --foo <bar> u64,                           // With u64 value named "bar"
@@ -105,6 +151,31 @@ macro_rules! catch_error {
 /// -f --foo...                 "Help text",   // ...
 /// -f --foo... <bar> u64       "Help text",   // ...
 /// -f --foo... <bar> String    "Help text",   // ...
+///
+/// -f --foo [<bar>] u64        "Help text",   // With optional u64 value named "bar"
+/// -f --foo... [<bar>] u64     "Help text",   // ...
+///
+/// -f --foo <x> <y> u64        "Help text",   // With two u64 values named "x" and "y"
+/// -f --foo... <x> <y> u64     "Help text",   // ...
+///
+/// -f --foo <bar,...> u64      "Help text",   // With comma-delimited list of u64 values
+/// -f --foo... <bar,...> u64   "Help text",   // ...
+///
+/// -f --foo <bar> Bar { x, y z },             // With generated enum Bar, choices "x" and "y" (alias "z")
+///
+/// -D --foo <kv> (String, u64) "Help text",   // With key-value pair, e.g. -D name=4
+/// -D --foo... <kv> (String, u64),            // ...
+/// -D --foo... <kv> HashMap<String, u64>,     // With key-value pairs collected into a map
+///
+/// -f --foo <bar> u64 in 1..=8 "Help text",   // With value validated to be in range 1..=8
+/// -f --foo <bar> u64 check(f) "Help text",   // With value validated by function f
+///
+/// -f --foo <bar> u64 env(FOO) "Help text",   // With fallback to environment variable FOO
+///
+/// -f --foo global             "Help text",   // Also accepted after (sub)commands
+///
+/// -f --foo <bar> u64 = 4      "Help text",   // With default value, field type u64
+/// -f --foo <bar> u64 = { 4 }  "Help text",   // With default value from block expression
 /// ```
 ///
 /// Non-options:
@@ -124,6 +195,16 @@ macro_rules! catch_error {
 /// [<foo>...] String ! "Help text",           // With default conflict-id
 /// [<foo>...] String !A !B "Help text",       // With conflict-ids "A" and "B"
 ///
+/// <foo> u64 in 1..=8,                        // With value validated to be in range 1..=8
+/// [<foo>...] PathBuf check(f),               // With values validated by function f
+///
+/// <foo> (String, u64),                       // Key-value pair argument, e.g. name=4
+/// [<foo>...] BTreeMap<String, u64>,          // With key-value pairs collected into a map
+///
+/// -- <foo>... String,                        // Trailing argument, with all arguments after --
+/// -- [<foo>...] String,                      // Optional trailing argument
+/// [<foo>...] raw,                            // Raw argument, with the remaining arguments as is
+///
 /// <command> Command {                        // Required command argument
 ///     add,                                   // Command "add"
 ///     remove rm,                             // Command "remove" with alias "rm"
@@ -153,11 +234,39 @@ macro_rules! catch_error {
 ///     remove rm   "Help text for remove",
 ///     list ls l   "Help text for list",
 /// }
+///
+/// <command> Command {                        // With arguments structs
+///     add: AddArgs           "Help text for add",     // Command::Add(AddArgs)
+///     remove rm: RemoveArgs  "Help text for remove",  // Command::Remove(RemoveArgs)
+///     list ls l              "Help text for list",    // Command::List(Args)
+/// }
+///
+/// <command> Command {                        // With nested arguments
+///     add "Help text for add" {              // Generates struct AddArgs
+///         -f --force,
+///         <file>... PathBuf,
+///     },
+///     remote {                               // Generates struct RemoteArgs, with field
+///         -v --verbose,                      // command: RemoteCommand
+///         add { <name> String, },            // Generates struct RemoteAddArgs
+///         remove { <name> String, },         // Generates struct RemoteRemoveArgs
+///     },
+/// }
+///
+/// <command> Command {                        // With external commands
+///     add,                                   // Command::Add(Args)
+///     _,                                     // Any other command, Command::External(String, Args)
+/// }
 /// ```
 #[proc_macro]
 pub fn immargs(input: TokenStream) -> TokenStream {
     let ast = parse_macro_input!(input as Ast);
-    let ir = catch_error!(lower(ast));
-    let code = catch_error!(emit(ir));
+    let mut code = proc_macro2::TokenStream::new();
+
+    for (ast, globals) in catch_error!(flatten(ast)) {
+        let ir = catch_error!(lower(ast, globals));
+        code.extend(catch_error!(emit(ir)));
+    }
+
     code.into()
 }
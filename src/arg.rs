@@ -3,19 +3,75 @@
 use crate::Args;
 use crate::Error;
 use crate::Result;
+use crate::choice;
+use crate::config;
+use crate::config::Config;
 use crate::lexer::Lexer;
+use crate::os;
 use std::collections::HashMap;
+use std::ffi::OsString;
 use std::marker::PhantomData;
+use std::ops::RangeBounds;
+use std::path::PathBuf;
 use std::str::FromStr;
 
 pub struct NoValue;
 
+pub struct Negatable(bool);
+
+pub struct OptionalValue<T>(Option<T>);
+
+pub struct Values<T, const N: usize>([T; N]);
+
+pub struct Delimited<T>(Vec<T>);
+
+pub struct KeyValue<K, V>(K, V);
+
+type Check<T> = Box<dyn Fn(&T) -> std::result::Result<(), String>>;
+
+const DELIMITER: u8 = b',';
+const SEPARATOR: u8 = b'=';
+
+/// A value parsed from a command line argument.
+///
+/// Implemented for all types implementing [`FromStr`], where the error converts into
+/// `Box<dyn Error>`. Types not implementing [`FromStr`] can implement this trait directly,
+/// for example to parse arguments that are not valid unicode. Foreign types can instead be
+/// parsed using a custom parser function, see [Custom Parsers](crate#custom-parsers).
+///
+/// ```
+/// use immargs::Error;
+/// use immargs::Value;
+/// use immargs::immargs;
+/// use std::ffi::OsString;
+///
+/// #[derive(Debug)]
+/// struct Bytes(Vec<u8>);
+///
+/// impl Value for Bytes {
+///     fn parse(value: OsString) -> immargs::Result<Self> {
+///         Ok(Self(value.into_encoded_bytes()))
+///     }
+/// }
+///
+/// immargs! {
+///     <data> Bytes,
+/// }
+///
+/// let args = ImmArgs::from(["test", "abc"]);
+/// assert!(args.data.0 == b"abc");
+/// ```
 pub trait Value: Sized {
-    fn parse(value: String) -> Result<Self>;
+    /// Parse the value from an argument.
+    fn parse(value: OsString) -> Result<Self>;
 }
 
 impl<T: FromStr<Err: Into<Box<dyn std::error::Error>>>> Value for T {
-    fn parse(value: String) -> Result<Self> {
+    fn parse(value: OsString) -> Result<Self> {
+        let value = value.into_string().map_err(|value| Error::InvalidUnicode {
+            arg: value.to_string_lossy().into_owned(),
+        })?;
+
         match value.parse::<Self>() {
             Ok(value) => Ok(value),
             Err(error) => Err(Error::ParsingFailed {
@@ -26,18 +82,387 @@ impl<T: FromStr<Err: Into<Box<dyn std::error::Error>>>> Value for T {
     }
 }
 
+/// A value built directly from a command line argument, without a UTF-8 round trip.
+///
+/// Implemented for [`OsString`] and [`PathBuf`]. Values of types implementing this trait are
+/// built using [`from_os_str()`](FromOsStr::from_os_str) instead of [`Value::parse()`], so
+/// that arguments that are not valid unicode are not rejected.
+///
+/// ```
+/// use immargs::FromOsStr;
+/// use immargs::immargs;
+/// use std::ffi::OsString;
+/// use std::path::PathBuf;
+/// use std::str::FromStr;
+///
+/// #[derive(Debug)]
+/// struct Dir(PathBuf);
+///
+/// impl FromStr for Dir {
+///     type Err = std::convert::Infallible;
+///
+///     fn from_str(value: &str) -> Result<Self, Self::Err> {
+///         Ok(Self(PathBuf::from(value)))
+///     }
+/// }
+///
+/// impl FromOsStr for Dir {
+///     fn from_os_str(value: OsString) -> immargs::Result<Self> {
+///         Ok(Self(PathBuf::from(value)))
+///     }
+/// }
+///
+/// immargs! {
+///     <dir> Dir,
+/// }
+///
+/// let args = ImmArgs::from(["test", "abc"]);
+/// assert!(args.dir.0 == PathBuf::from("abc"));
+/// ```
+pub trait FromOsStr: Value {
+    /// Build the value from an argument.
+    fn from_os_str(value: OsString) -> Result<Self>;
+}
+
+impl FromOsStr for OsString {
+    fn from_os_str(value: OsString) -> Result<Self> {
+        Ok(value)
+    }
+}
+
+impl FromOsStr for PathBuf {
+    fn from_os_str(value: OsString) -> Result<Self> {
+        Ok(PathBuf::from(value))
+    }
+}
+
+// Selects the parse function of a value type in generated code, where the type is known, by
+// preferring `FromOsStr` over `Value` through autoref-based method resolution. The probe is
+// called as `(&&Probe::<T>::new()).parse_fn()`, matching `ProbeFromOsStr` on `&&Probe<T>`
+// before `ProbeValue` on `&Probe<T>`.
+pub struct Probe<T>(PhantomData<T>);
+
+impl<T> Probe<T> {
+    #[allow(clippy::new_without_default)]
+    #[inline]
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+pub trait ProbeFromOsStr<T> {
+    fn parse_fn(&self) -> fn(OsString) -> Result<T>;
+}
+
+impl<T: FromOsStr> ProbeFromOsStr<T> for &Probe<T> {
+    #[inline]
+    fn parse_fn(&self) -> fn(OsString) -> Result<T> {
+        T::from_os_str
+    }
+}
+
+pub trait ProbeValue<T> {
+    fn parse_fn(&self) -> fn(OsString) -> Result<T>;
+}
+
+impl<T: Value> ProbeValue<T> for Probe<T> {
+    #[inline]
+    fn parse_fn(&self) -> fn(OsString) -> Result<T> {
+        T::parse
+    }
+}
+
+pub struct ByRef;
+
+pub struct ByValue;
+
+// Custom parser function taking either &str or String, with the marker type
+// selecting the implementation
+pub trait ParseFn<T, M> {
+    fn call(&self, value: String) -> std::result::Result<T, Box<dyn std::error::Error>>;
+}
+
+impl<T, E: Into<Box<dyn std::error::Error>>, F: Fn(&str) -> std::result::Result<T, E>>
+    ParseFn<T, ByRef> for F
+{
+    fn call(&self, value: String) -> std::result::Result<T, Box<dyn std::error::Error>> {
+        self(&value).map_err(Into::into)
+    }
+}
+
+impl<T, E: Into<Box<dyn std::error::Error>>, F: Fn(String) -> std::result::Result<T, E>>
+    ParseFn<T, ByValue> for F
+{
+    fn call(&self, value: String) -> std::result::Result<T, Box<dyn std::error::Error>> {
+        self(value).map_err(Into::into)
+    }
+}
+
+pub fn parse_with<T, M>(value: OsString, parse: impl ParseFn<T, M>) -> Result<T> {
+    let value = value.into_string().map_err(|value| Error::InvalidUnicode {
+        arg: value.to_string_lossy().into_owned(),
+    })?;
+
+    match parse.call(value.clone()) {
+        Ok(value) => Ok(value),
+        Err(error) => Err(Error::ParsingFailed { value, error }),
+    }
+}
+
+// Values parsed using a custom parser function are wrapped in a type implementing
+// `Value`, which is unwrapped after parsing.
+pub trait Wrapper {
+    type Inner;
+    fn into_inner(self) -> Self::Inner;
+}
+
+pub trait Unwrap {
+    type Output;
+    fn unwrap(self) -> Self::Output;
+}
+
+impl<W: Wrapper> Unwrap for W {
+    type Output = W::Inner;
+
+    fn unwrap(self) -> Self::Output {
+        self.into_inner()
+    }
+}
+
+impl<X: Unwrap> Unwrap for Option<X> {
+    type Output = Option<X::Output>;
+
+    fn unwrap(self) -> Self::Output {
+        self.map(Unwrap::unwrap)
+    }
+}
+
+impl<X: Unwrap> Unwrap for Vec<X> {
+    type Output = Vec<X::Output>;
+
+    fn unwrap(self) -> Self::Output {
+        self.into_iter().map(Unwrap::unwrap).collect()
+    }
+}
+
+impl<X: Unwrap, const N: usize> Unwrap for [X; N] {
+    type Output = [X::Output; N];
+
+    fn unwrap(self) -> Self::Output {
+        self.map(Unwrap::unwrap)
+    }
+}
+
+// The parse function and the optional validation of the values of an argument
+pub struct Parser<T> {
+    parse: fn(OsString) -> Result<T>,
+    check: Option<Check<T>>,
+}
+
+impl<T> Parser<T> {
+    #[inline]
+    fn new(parse: fn(OsString) -> Result<T>) -> Self {
+        Self { parse, check: None }
+    }
+
+    // Parse and validate a value, with a failed validation naming the argument and the value
+    fn parse(&self, name: &str, arg: OsString) -> Result<T> {
+        let Some(check) = &self.check else {
+            return (self.parse)(arg);
+        };
+
+        let lossy = arg.to_string_lossy().into_owned();
+        let value = (self.parse)(arg)?;
+
+        match check(&value) {
+            Ok(()) => Ok(value),
+            Err(message) => Err(Error::ValidationFailed {
+                arg: name.to_string(),
+                value: lossy,
+                message,
+            }),
+        }
+    }
+
+    fn parse_delimited(&self, name: &str, arg: OsString) -> Result<Vec<T>> {
+        let mut values = vec![];
+        if !arg.is_empty() {
+            for arg in os::split(arg, DELIMITER) {
+                values.push(self.parse(name, arg)?);
+            }
+        }
+        Ok(values)
+    }
+}
+
+// The parsers of the values of an argument, one for each value type
+pub trait Kind {
+    type Parser;
+}
+
+impl Kind for NoValue {
+    type Parser = ();
+}
+
+impl Kind for Negatable {
+    type Parser = ();
+}
+
+impl<T: Value> Kind for T {
+    type Parser = Parser<T>;
+}
+
+impl<T: Value, const N: usize> Kind for Values<T, N> {
+    type Parser = Parser<T>;
+}
+
+impl<T: Value> Kind for Delimited<T> {
+    type Parser = Parser<T>;
+}
+
+impl<K: Value + PartialEq, V: Value> Kind for KeyValue<K, V> {
+    type Parser = (Parser<K>, Parser<V>);
+}
+
+impl<T: Value> Kind for OptionalValue<T> {
+    type Parser = Parser<T>;
+}
+
+pub fn check_range<T: PartialOrd, R: RangeBounds<T>>(
+    value: &T,
+    range: R,
+    text: &str,
+) -> std::result::Result<(), String> {
+    match range.contains(value) {
+        true => Ok(()),
+        false => Err(format!("expected value in range {text}")),
+    }
+}
+
+// Split on the first '=', with the key compared to previous keys after parsing, so that
+// keys that parse to the same value are detected as duplicates.
+fn parse_key_value<K: Value + PartialEq, V: Value>(
+    name: &str,
+    mut arg: OsString,
+    pairs: &[KeyValue<K, V>],
+    parsers: &(Parser<K>, Parser<V>),
+) -> Result<KeyValue<K, V>> {
+    let at = match arg.as_encoded_bytes().iter().position(|&b| b == SEPARATOR) {
+        Some(at) if at > 0 => at,
+        _ => {
+            return Err(Error::MalformedPair {
+                arg: name.to_string(),
+                value: arg.to_string_lossy().into_owned(),
+            });
+        }
+    };
+
+    let mut value = os::split_off(&mut arg, at);
+    let value = os::split_off(&mut value, 1);
+    let key_lossy = arg.to_string_lossy().into_owned();
+    let key = parsers.0.parse(name, arg)?;
+
+    if pairs.iter().any(|pair| pair.0 == key) {
+        return Err(Error::DuplicateKey {
+            arg: name.to_string(),
+            key: key_lossy,
+        });
+    }
+
+    Ok(KeyValue(key, parsers.1.parse(name, value)?))
+}
+
+// Default values are parsed only when the option is absent, after parsing the arguments.
+pub fn default<T>(option: &str, arg: &str, parse: fn(OsString) -> Result<T>) -> Result<T> {
+    parse(OsString::from(arg)).map_err(|error| invalid_default(option, arg, error))
+}
+
+pub fn default_delimited<T>(
+    option: &str,
+    arg: &str,
+    parse: fn(OsString) -> Result<T>,
+) -> Result<Vec<T>> {
+    Parser::new(parse)
+        .parse_delimited(option, OsString::from(arg))
+        .map_err(|error| invalid_default(option, arg, error))
+}
+
+fn invalid_default(option: &str, arg: &str, error: Error) -> Error {
+    let message = match error {
+        Error::ParsingFailed { error, .. } => error.to_string(),
+        error => error.to_string(),
+    };
+
+    Error::InvalidDefault {
+        option: option.to_string(),
+        value: arg.to_string(),
+        message,
+    }
+}
+
 pub trait Command: Sized {
+    fn names() -> &'static [&'static str];
     fn normalize(command: &str) -> Result<&'static str>;
-    fn from(command: &str, args: Args) -> Result<Self>;
+    fn from(
+        command: &str,
+        args: Args,
+        config: Option<&Config>,
+        globals: &mut [&mut dyn ArgOptionSetter],
+    ) -> Result<Self>;
+
+    // Unknown commands are external commands
+    fn external() -> bool {
+        false
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct Settings {
+    permute: bool,
+    abbreviate: bool,
+    response_files: bool,
+}
+
+impl Settings {
+    #[inline]
+    pub fn permute(mut self) -> Self {
+        self.permute = true;
+        self
+    }
+
+    #[inline]
+    pub fn abbreviate(mut self) -> Self {
+        self.abbreviate = true;
+        self
+    }
+
+    #[inline]
+    pub fn response_files(mut self) -> Self {
+        self.response_files = true;
+        self
+    }
+}
+
+#[inline]
+pub fn settings() -> Settings {
+    Settings::default()
 }
 
 #[inline]
 pub fn option(names: &'static [&'static str]) -> ArgOption<NoValue, false> {
     ArgOption {
-        names,
-        conflicts: &[],
-        used_name: None,
-        on_set: None,
+        decl: OptionDecl {
+            names,
+            negations: &[],
+            value_names: &[],
+            conflicts: &[],
+            requires: &[],
+            env: None,
+            global: false,
+            used_name: None,
+            on_set: None,
+        },
+        parser: (),
         value: vec![],
     }
 }
@@ -47,16 +472,40 @@ pub fn non_option(name: &'static str) -> ArgNonOption<NoValue, false, false> {
     ArgNonOption {
         name,
         conflicts: &[],
+        requires: &[],
         grants: 0,
+        trailing: false,
+        raw: false,
+        parser: (),
         value: vec![],
     }
 }
 
-pub struct ArgOption<T, const VARIADIC: bool> {
+// Declaration of an option, common to all kinds of options, and moved whole when the
+// builder changes the kind of the option
+struct OptionDecl {
     names: &'static [&'static str],
+    negations: &'static [&'static str],
+    value_names: &'static [&'static str],
     conflicts: &'static [&'static str],
+    requires: &'static [&'static str],
+    env: Option<&'static str>,
+    global: bool,
     used_name: Option<&'static str>,
     on_set: Option<Error>,
+}
+
+impl OptionDecl {
+    fn try_match(&self, option: &str) -> Option<&'static str> {
+        self.names
+            .iter()
+            .find_map(|opt| if *opt == option { Some(*opt) } else { None })
+    }
+}
+
+pub struct ArgOption<T: Kind, const VARIADIC: bool> {
+    decl: OptionDecl,
+    parser: T::Parser,
     value: Vec<T>,
 }
 
@@ -68,24 +517,76 @@ pub struct ArgOptionAction<'a, T: Fn() -> Error> {
 
 impl ArgOption<NoValue, false> {
     #[inline]
-    pub fn value<T: Value>(self) -> ArgOption<T, false> {
+    pub fn value<T: Value>(self, parse: fn(OsString) -> Result<T>) -> ArgOption<T, false> {
         ArgOption {
-            names: self.names,
-            conflicts: self.conflicts,
-            used_name: self.used_name,
-            on_set: self.on_set,
+            decl: self.decl,
+            parser: Parser::new(parse),
             value: vec![],
         }
     }
 
     #[inline]
-    pub fn variadic(self) -> ArgOption<NoValue, true> {
+    pub fn negatable(self, negations: &'static [&'static str]) -> ArgOption<Negatable, false> {
         ArgOption {
-            names: self.names,
-            conflicts: self.conflicts,
-            used_name: self.used_name,
-            on_set: self.on_set,
-            value: self.value,
+            decl: OptionDecl {
+                negations,
+                ..self.decl
+            },
+            parser: (),
+            value: vec![],
+        }
+    }
+
+    #[inline]
+    pub fn values<T: Value, const N: usize>(
+        self,
+        value_names: &'static [&'static str; N],
+        parse: fn(OsString) -> Result<T>,
+    ) -> ArgOption<Values<T, N>, false> {
+        ArgOption {
+            decl: OptionDecl {
+                value_names,
+                ..self.decl
+            },
+            parser: Parser::new(parse),
+            value: vec![],
+        }
+    }
+
+    #[inline]
+    pub fn delimited<T: Value>(
+        self,
+        parse: fn(OsString) -> Result<T>,
+    ) -> ArgOption<Delimited<T>, false> {
+        ArgOption {
+            decl: self.decl,
+            parser: Parser::new(parse),
+            value: vec![],
+        }
+    }
+
+    #[inline]
+    pub fn key_value<K: Value + PartialEq, V: Value>(
+        self,
+        key: fn(OsString) -> Result<K>,
+        value: fn(OsString) -> Result<V>,
+    ) -> ArgOption<KeyValue<K, V>, false> {
+        ArgOption {
+            decl: self.decl,
+            parser: (Parser::new(key), Parser::new(value)),
+            value: vec![],
+        }
+    }
+
+    #[inline]
+    pub fn optional_value<T: Value>(
+        self,
+        parse: fn(OsString) -> Result<T>,
+    ) -> ArgOption<OptionalValue<T>, false> {
+        ArgOption {
+            decl: self.decl,
+            parser: Parser::new(parse),
+            value: vec![],
         }
     }
 
@@ -96,7 +597,7 @@ impl ArgOption<NoValue, false> {
         version: &'a str,
     ) -> ArgOptionAction<'a, impl Fn() -> Error> {
         ArgOptionAction {
-            names: self.names,
+            names: self.decl.names,
             action: move || Error::Version {
                 message: format!("{name} {version}"),
             },
@@ -107,7 +608,7 @@ impl ArgOption<NoValue, false> {
     #[inline]
     pub fn help<'a>(self, message: &'a [&'a str]) -> ArgOptionAction<'a, impl Fn() -> Error> {
         ArgOptionAction {
-            names: self.names,
+            names: self.decl.names,
             action: move || Error::Help {
                 message: message.concat(),
             },
@@ -116,30 +617,51 @@ impl ArgOption<NoValue, false> {
     }
 }
 
-impl<T: Value> ArgOption<T, false> {
+impl<T: Kind> ArgOption<T, false> {
     #[inline]
     pub fn variadic(self) -> ArgOption<T, true> {
         ArgOption {
-            names: self.names,
-            conflicts: self.conflicts,
-            used_name: self.used_name,
-            on_set: self.on_set,
+            decl: self.decl,
+            parser: self.parser,
             value: self.value,
         }
     }
 }
 
-impl<T, const VARIADIC: bool> ArgOption<T, VARIADIC> {
+impl<T: Kind<Parser = Parser<V>>, V, const VARIADIC: bool> ArgOption<T, VARIADIC> {
+    #[inline]
+    pub fn check(
+        mut self,
+        check: impl Fn(&V) -> std::result::Result<(), String> + 'static,
+    ) -> Self {
+        self.parser.check = Some(Box::new(check));
+        self
+    }
+}
+
+impl<T: Kind, const VARIADIC: bool> ArgOption<T, VARIADIC> {
     #[inline]
     pub fn conflicts(mut self, conflicts: &'static [&'static str]) -> Self {
-        self.conflicts = conflicts;
+        self.decl.conflicts = conflicts;
         self
     }
 
-    fn try_match(&self, option: &str) -> Option<&'static str> {
-        self.names
-            .iter()
-            .find_map(|opt| if *opt == option { Some(*opt) } else { None })
+    #[inline]
+    pub fn requires(mut self, requires: &'static [&'static str]) -> Self {
+        self.decl.requires = requires;
+        self
+    }
+
+    #[inline]
+    pub fn env(mut self, env: &'static str) -> Self {
+        self.decl.env = Some(env);
+        self
+    }
+
+    #[inline]
+    pub fn global(mut self) -> Self {
+        self.decl.global = true;
+        self
     }
 }
 
@@ -157,6 +679,51 @@ impl<T: Value, const VARIADIC: bool> ArgOption<T, VARIADIC> {
     }
 }
 
+impl ArgOption<Negatable, false> {
+    #[inline]
+    pub fn as_setter(&mut self) -> &mut dyn ArgOptionSetter {
+        self as &mut dyn ArgOptionSetter
+    }
+}
+
+impl<T: Value, const N: usize, const VARIADIC: bool> ArgOption<Values<T, N>, VARIADIC> {
+    #[inline]
+    pub fn as_setter(&mut self) -> &mut dyn ArgOptionSetter {
+        self as &mut dyn ArgOptionSetter
+    }
+}
+
+impl<T: Value, const VARIADIC: bool> ArgOption<Delimited<T>, VARIADIC> {
+    #[inline]
+    pub fn as_setter(&mut self) -> &mut dyn ArgOptionSetter {
+        self as &mut dyn ArgOptionSetter
+    }
+}
+
+impl<K: Value + PartialEq, V: Value, const VARIADIC: bool> ArgOption<KeyValue<K, V>, VARIADIC> {
+    // Validation of a key-value pair applies to the value
+    #[inline]
+    pub fn check(
+        mut self,
+        check: impl Fn(&V) -> std::result::Result<(), String> + 'static,
+    ) -> Self {
+        self.parser.1.check = Some(Box::new(check));
+        self
+    }
+
+    #[inline]
+    pub fn as_setter(&mut self) -> &mut dyn ArgOptionSetter {
+        self as &mut dyn ArgOptionSetter
+    }
+}
+
+impl<T: Value, const VARIADIC: bool> ArgOption<OptionalValue<T>, VARIADIC> {
+    #[inline]
+    pub fn as_setter(&mut self) -> &mut dyn ArgOptionSetter {
+        self as &mut dyn ArgOptionSetter
+    }
+}
+
 impl<T: Fn() -> Error> ArgOptionAction<'_, T> {
     #[inline]
     pub fn as_setter(&mut self) -> &mut dyn ArgOptionSetter {
@@ -178,9 +745,9 @@ impl<T: Fn() -> Error> ArgOptionAction<'_, T> {
 
 impl<const VARIADIC: bool> ArgOption<NoValue, VARIADIC> {
     fn set(&mut self, option: &'static str) -> Result<()> {
-        self.used_name = Some(option);
+        self.decl.used_name = Some(option);
         self.value.push(NoValue);
-        match self.on_set.take() {
+        match self.decl.on_set.take() {
             Some(error) => Err(error),
             _ => Ok(()),
         }
@@ -188,55 +755,205 @@ impl<const VARIADIC: bool> ArgOption<NoValue, VARIADIC> {
 }
 
 impl<T: Value, const VARIADIC: bool> ArgOption<T, VARIADIC> {
-    fn set(&mut self, option: &'static str, arg: String) -> Result<()> {
-        self.used_name = Some(option);
-        self.value.push(T::parse(arg)?);
+    fn set(&mut self, option: &'static str, arg: OsString) -> Result<()> {
+        self.decl.used_name = Some(option);
+        self.value.push(self.parser.parse(option, arg)?);
         Ok(())
     }
 }
 
-impl ArgOption<NoValue, false> {
-    #[inline]
-    pub fn into(self) -> bool {
-        !self.value.is_empty()
+impl ArgOption<Negatable, false> {
+    fn set(&mut self, option: &'static str) -> Result<()> {
+        self.decl.used_name = Some(option);
+        self.value
+            .push(Negatable(!self.decl.negations.contains(&option)));
+        Ok(())
     }
 }
 
-impl ArgOption<NoValue, true> {
-    #[inline]
-    pub fn into(self) -> usize {
-        self.value.len()
+impl<T: Value, const N: usize, const VARIADIC: bool> ArgOption<Values<T, N>, VARIADIC> {
+    fn set(&mut self, option: &'static str, args: Vec<OsString>) -> Result<()> {
+        self.decl.used_name = Some(option);
+        let mut values = Vec::with_capacity(N);
+        for arg in args {
+            values.push(self.parser.parse(option, arg)?);
+        }
+        match values.try_into() {
+            Ok(values) => self.value.push(Values(values)),
+            Err(_) => unreachable!(),
+        }
+        Ok(())
     }
 }
 
-impl<T: Value> ArgOption<T, false> {
-    #[inline]
-    pub fn into(mut self) -> Option<T> {
-        self.value.pop()
+impl<T: Value, const VARIADIC: bool> ArgOption<Delimited<T>, VARIADIC> {
+    fn set(&mut self, option: &'static str, arg: OsString) -> Result<()> {
+        self.decl.used_name = Some(option);
+        let values = self.parser.parse_delimited(option, arg)?;
+        self.value.push(Delimited(values));
+        Ok(())
     }
 }
 
-impl<T: Value> ArgOption<T, true> {
-    #[inline]
+impl<K: Value + PartialEq, V: Value, const VARIADIC: bool> ArgOption<KeyValue<K, V>, VARIADIC> {
+    fn set(&mut self, option: &'static str, arg: OsString) -> Result<()> {
+        self.decl.used_name = Some(option);
+        let pairs = if VARIADIC { &self.value[..] } else { &[] };
+        let pair = parse_key_value(option, arg, pairs, &self.parser)?;
+        self.value.push(pair);
+        Ok(())
+    }
+}
+
+impl<T: Value, const VARIADIC: bool> ArgOption<OptionalValue<T>, VARIADIC> {
+    fn set(&mut self, option: &'static str, arg: Option<OsString>) -> Result<()> {
+        self.decl.used_name = Some(option);
+        let value = match arg {
+            Some(arg) => Some(self.parser.parse(option, arg)?),
+            None => None,
+        };
+        self.value.push(OptionalValue(value));
+        Ok(())
+    }
+}
+
+impl ArgOption<NoValue, false> {
+    #[inline]
+    pub fn into(self) -> bool {
+        !self.value.is_empty()
+    }
+}
+
+impl ArgOption<NoValue, true> {
+    #[inline]
+    pub fn into(self) -> usize {
+        self.value.len()
+    }
+}
+
+impl ArgOption<Negatable, false> {
+    #[inline]
+    pub fn into(mut self) -> Option<bool> {
+        self.value.pop().map(|value| value.0)
+    }
+}
+
+impl<T: Value> ArgOption<T, false> {
+    #[inline]
+    pub fn into(mut self) -> Option<T> {
+        self.value.pop()
+    }
+}
+
+impl<T: Value> ArgOption<T, true> {
+    #[inline]
     pub fn into(self) -> Vec<T> {
         self.value
     }
 }
 
-pub struct ArgNonOption<T, const OPTIONAL: bool, const VARIADIC: bool> {
+impl<T: Value, const N: usize> ArgOption<Values<T, N>, false> {
+    #[inline]
+    pub fn into(mut self) -> Option<[T; N]> {
+        self.value.pop().map(|value| value.0)
+    }
+}
+
+impl<T: Value, const N: usize> ArgOption<Values<T, N>, true> {
+    #[inline]
+    pub fn into(self) -> Vec<[T; N]> {
+        self.value.into_iter().map(|value| value.0).collect()
+    }
+}
+
+impl<T: Value> ArgOption<Delimited<T>, false> {
+    #[inline]
+    pub fn into(mut self) -> Option<Vec<T>> {
+        self.value.pop().map(|value| value.0)
+    }
+}
+
+impl<T: Value> ArgOption<Delimited<T>, true> {
+    #[inline]
+    pub fn into(self) -> Vec<T> {
+        self.value.into_iter().flat_map(|value| value.0).collect()
+    }
+}
+
+impl<K: Value + PartialEq, V: Value> ArgOption<KeyValue<K, V>, false> {
+    #[inline]
+    pub fn into(mut self) -> Option<(K, V)> {
+        self.value.pop().map(|pair| (pair.0, pair.1))
+    }
+}
+
+impl<K: Value + PartialEq, V: Value> ArgOption<KeyValue<K, V>, true> {
+    #[inline]
+    pub fn into(self) -> Vec<(K, V)> {
+        self.value
+            .into_iter()
+            .map(|pair| (pair.0, pair.1))
+            .collect()
+    }
+}
+
+impl<T: Value> ArgOption<OptionalValue<T>, false> {
+    #[inline]
+    pub fn into(mut self) -> Option<Option<T>> {
+        self.value.pop().map(|value| value.0)
+    }
+}
+
+impl<T: Value> ArgOption<OptionalValue<T>, true> {
+    #[inline]
+    pub fn into(self) -> Vec<Option<T>> {
+        self.value.into_iter().map(|value| value.0).collect()
+    }
+}
+
+pub struct ArgNonOption<T: Kind, const OPTIONAL: bool, const VARIADIC: bool> {
     name: &'static str,
     conflicts: &'static [&'static str],
+    requires: &'static [&'static str],
     grants: usize,
+    trailing: bool,
+    raw: bool,
+    parser: T::Parser,
     value: Vec<T>,
 }
 
 impl ArgNonOption<NoValue, false, false> {
     #[inline]
-    pub fn value<T: Value>(self) -> ArgNonOption<T, false, false> {
+    pub fn value<T: Value>(
+        self,
+        parse: fn(OsString) -> Result<T>,
+    ) -> ArgNonOption<T, false, false> {
         ArgNonOption {
             name: self.name,
             conflicts: self.conflicts,
+            requires: self.requires,
             grants: self.grants,
+            trailing: self.trailing,
+            raw: self.raw,
+            parser: Parser::new(parse),
+            value: vec![],
+        }
+    }
+
+    #[inline]
+    pub fn key_value<K: Value + PartialEq, V: Value>(
+        self,
+        key: fn(OsString) -> Result<K>,
+        value: fn(OsString) -> Result<V>,
+    ) -> ArgNonOption<KeyValue<K, V>, false, false> {
+        ArgNonOption {
+            name: self.name,
+            conflicts: self.conflicts,
+            requires: self.requires,
+            grants: self.grants,
+            trailing: self.trailing,
+            raw: self.raw,
+            parser: (Parser::new(key), Parser::new(value)),
             value: vec![],
         }
     }
@@ -246,9 +963,11 @@ impl ArgNonOption<NoValue, false, false> {
         ArgNonOptionCommand {
             name: self.name,
             conflicts: self.conflicts,
+            requires: self.requires,
             grants: self.grants,
+            abbreviate: false,
             value: vec![],
-            marker: PhantomData,
+            parsed: None,
         }
     }
 }
@@ -259,7 +978,11 @@ impl<T: Value> ArgNonOption<T, false, false> {
         ArgNonOption {
             name: self.name,
             conflicts: self.conflicts,
+            requires: self.requires,
             grants: self.grants,
+            trailing: self.trailing,
+            raw: self.raw,
+            parser: self.parser,
             value: self.value,
         }
     }
@@ -271,7 +994,11 @@ impl<T: Value, const OPTIONAL: bool> ArgNonOption<T, OPTIONAL, false> {
         ArgNonOption {
             name: self.name,
             conflicts: self.conflicts,
+            requires: self.requires,
             grants: self.grants,
+            trailing: self.trailing,
+            raw: self.raw,
+            parser: self.parser,
             value: self.value,
         }
     }
@@ -284,6 +1011,98 @@ impl<T: Value, const OPTIONAL: bool, const VARIADIC: bool> ArgNonOption<T, OPTIO
         self
     }
 
+    #[inline]
+    pub fn requires(mut self, requires: &'static [&'static str]) -> Self {
+        self.requires = requires;
+        self
+    }
+
+    #[inline]
+    pub fn check(
+        mut self,
+        check: impl Fn(&T) -> std::result::Result<(), String> + 'static,
+    ) -> Self {
+        self.parser.check = Some(Box::new(check));
+        self
+    }
+
+    #[inline]
+    pub fn trailing(mut self) -> Self {
+        self.trailing = true;
+        self
+    }
+
+    #[inline]
+    pub fn raw(mut self) -> Self {
+        self.raw = true;
+        self
+    }
+
+    #[inline]
+    pub fn as_setter(&mut self) -> &mut dyn ArgNonOptionSetter {
+        self as &mut dyn ArgNonOptionSetter
+    }
+}
+
+impl<K: Value + PartialEq, V: Value> ArgNonOption<KeyValue<K, V>, false, false> {
+    #[inline]
+    pub fn optional(self) -> ArgNonOption<KeyValue<K, V>, true, false> {
+        ArgNonOption {
+            name: self.name,
+            conflicts: self.conflicts,
+            requires: self.requires,
+            grants: self.grants,
+            trailing: self.trailing,
+            raw: self.raw,
+            parser: self.parser,
+            value: self.value,
+        }
+    }
+}
+
+impl<K: Value + PartialEq, V: Value, const OPTIONAL: bool>
+    ArgNonOption<KeyValue<K, V>, OPTIONAL, false>
+{
+    #[inline]
+    pub fn variadic(self) -> ArgNonOption<KeyValue<K, V>, OPTIONAL, true> {
+        ArgNonOption {
+            name: self.name,
+            conflicts: self.conflicts,
+            requires: self.requires,
+            grants: self.grants,
+            trailing: self.trailing,
+            raw: self.raw,
+            parser: self.parser,
+            value: self.value,
+        }
+    }
+}
+
+impl<K: Value + PartialEq, V: Value, const OPTIONAL: bool, const VARIADIC: bool>
+    ArgNonOption<KeyValue<K, V>, OPTIONAL, VARIADIC>
+{
+    #[inline]
+    pub fn conflicts(mut self, conflicts: &'static [&'static str]) -> Self {
+        self.conflicts = conflicts;
+        self
+    }
+
+    #[inline]
+    pub fn requires(mut self, requires: &'static [&'static str]) -> Self {
+        self.requires = requires;
+        self
+    }
+
+    // Validation of a key-value pair applies to the value
+    #[inline]
+    pub fn check(
+        mut self,
+        check: impl Fn(&V) -> std::result::Result<(), String> + 'static,
+    ) -> Self {
+        self.parser.1.check = Some(Box::new(check));
+        self
+    }
+
     #[inline]
     pub fn as_setter(&mut self) -> &mut dyn ArgNonOptionSetter {
         self as &mut dyn ArgNonOptionSetter
@@ -318,12 +1137,40 @@ impl<T: Value> ArgNonOption<T, true, true> {
     }
 }
 
+impl<K: Value + PartialEq, V: Value> ArgNonOption<KeyValue<K, V>, false, false> {
+    #[inline]
+    pub fn into(mut self) -> (K, V) {
+        self.value.pop().map(|pair| (pair.0, pair.1)).unwrap()
+    }
+}
+
+impl<K: Value + PartialEq, V: Value> ArgNonOption<KeyValue<K, V>, true, false> {
+    #[inline]
+    pub fn into(mut self) -> Option<(K, V)> {
+        self.value.pop().map(|pair| (pair.0, pair.1))
+    }
+}
+
+impl<K: Value + PartialEq, V: Value, const OPTIONAL: bool>
+    ArgNonOption<KeyValue<K, V>, OPTIONAL, true>
+{
+    #[inline]
+    pub fn into(self) -> Vec<(K, V)> {
+        self.value
+            .into_iter()
+            .map(|pair| (pair.0, pair.1))
+            .collect()
+    }
+}
+
 pub struct ArgNonOptionCommand<T, const OPTIONAL: bool> {
     name: &'static str,
     conflicts: &'static [&'static str],
+    requires: &'static [&'static str],
     grants: usize,
-    value: Vec<String>,
-    marker: PhantomData<T>,
+    abbreviate: bool,
+    value: Vec<OsString>,
+    parsed: Option<T>,
 }
 
 impl<T: Command> ArgNonOptionCommand<T, false> {
@@ -332,9 +1179,11 @@ impl<T: Command> ArgNonOptionCommand<T, false> {
         ArgNonOptionCommand {
             name: self.name,
             conflicts: self.conflicts,
+            requires: self.requires,
             grants: self.grants,
+            abbreviate: self.abbreviate,
             value: self.value,
-            marker: self.marker,
+            parsed: self.parsed,
         }
     }
 }
@@ -346,81 +1195,344 @@ impl<T: Command, const OPTIONAL: bool> ArgNonOptionCommand<T, OPTIONAL> {
         self
     }
 
+    #[inline]
+    pub fn requires(mut self, requires: &'static [&'static str]) -> Self {
+        self.requires = requires;
+        self
+    }
+
+    #[inline]
+    pub fn abbreviate(mut self) -> Self {
+        self.abbreviate = true;
+        self
+    }
+
     #[inline]
     pub fn as_setter(&mut self) -> &mut dyn ArgNonOptionSetter {
         self as &mut dyn ArgNonOptionSetter
     }
+
+    fn is_command(arg: &str) -> bool {
+        T::normalize(arg).is_ok()
+    }
+
+    fn is_command_prefix(arg: &str) -> bool {
+        !arg.is_empty() && T::names().iter().any(|name| name.starts_with(arg))
+    }
+
+    fn normalize(abbreviate: bool, command: &str) -> Result<&'static str> {
+        let error = match T::normalize(command) {
+            Err(error) if abbreviate && !command.is_empty() => error,
+            result => return result,
+        };
+
+        let mut candidates: Vec<(&'static str, &'static str)> = vec![];
+
+        for name in T::names().iter().filter(|name| name.starts_with(command)) {
+            let normalized = T::normalize(name)?;
+            if !candidates.iter().any(|(_, other)| *other == normalized) {
+                candidates.push((name, normalized));
+            }
+        }
+
+        match candidates[..] {
+            [] => Err(error),
+            [(_, normalized)] => Ok(normalized),
+            _ => Err(Error::AmbiguousArgument {
+                arg: command.to_string(),
+                candidates: candidates
+                    .iter()
+                    .map(|(name, _)| name.to_string())
+                    .collect(),
+            }),
+        }
+    }
 }
 
 impl<T: Command> ArgNonOptionCommand<T, false> {
-    pub fn into(self, bin_name: &str) -> Result<T> {
-        let mut args = Args::from_vec(self.value);
-        let arg0 = args.peek().unwrap();
-        let command = T::normalize(arg0)?;
-        args.set_bin_name(format!("{bin_name} {command}"));
-        T::from(command, args)
+    pub fn into(self) -> T {
+        self.parsed.unwrap()
     }
 }
 
 impl<T: Command> ArgNonOptionCommand<T, true> {
-    pub fn into(self, bin_name: &str) -> Result<Option<T>> {
-        let mut args = Args::from_vec(self.value);
-        let Some(arg0) = args.peek() else {
-            return Ok(None);
-        };
-        let command = T::normalize(arg0)?;
-        args.set_bin_name(format!("{bin_name} {command}"));
-        Ok(Some(T::from(command, args)?))
+    pub fn into(self) -> Option<T> {
+        self.parsed
     }
 }
 
+// Methods of `ArgOptionSetter` implemented the same way for all kinds of options
+macro_rules! option_setter {
+    () => {
+        fn names(&self) -> &'_ [&'_ str] {
+            self.decl.names
+        }
+
+        fn used_name(&self) -> &'_ str {
+            self.decl.used_name.unwrap()
+        }
+
+        fn is_set(&self) -> bool {
+            !self.value.is_empty()
+        }
+
+        fn conflicts(&self) -> &'_ [&'_ str] {
+            self.decl.conflicts
+        }
+
+        fn requires(&self) -> &'_ [&'_ str] {
+            self.decl.requires
+        }
+
+        fn env(&self) -> Option<&'static str> {
+            self.decl.env
+        }
+
+        fn is_global(&self) -> bool {
+            self.decl.global
+        }
+
+        fn try_match(&self, option: &str) -> Option<&'static str> {
+            self.decl.try_match(option)
+        }
+    };
+}
+
 pub trait ArgOptionSetter {
     fn names(&self) -> &'_ [&'_ str];
     fn used_name(&self) -> &'_ str;
     fn conflicts(&self) -> &'_ [&'_ str];
+    fn requires(&self) -> &'_ [&'_ str];
+    fn env(&self) -> Option<&'static str>;
+    fn is_global(&self) -> bool;
     fn is_set(&self) -> bool;
     fn try_match(&self, option: &str) -> Option<&'static str>;
-    fn takes_value(&self) -> bool;
-    fn set(&mut self, option: &'static str, value: Option<String>) -> Result<()>;
+    fn num_values(&self) -> usize;
+    fn set(&mut self, option: &'static str, values: Vec<OsString>) -> Result<()>;
+
+    fn value_names(&self) -> &'_ [&'_ str] {
+        &[]
+    }
+
+    fn takes_optional_value(&self) -> bool {
+        false
+    }
+
+    // Present in argument groups, which an explicit negation is not
+    fn is_present(&self) -> bool {
+        self.is_set()
+    }
+
+    // Help and version options, which cannot be set from a configuration file
+    fn is_action(&self) -> bool {
+        false
+    }
 }
 
 impl<const VARIADIC: bool> ArgOptionSetter for ArgOption<NoValue, VARIADIC> {
+    option_setter!();
+
+    fn num_values(&self) -> usize {
+        0
+    }
+
+    fn set(&mut self, option: &'static str, _values: Vec<OsString>) -> Result<()> {
+        self.set(option)
+    }
+}
+
+impl<T: Value, const VARIADIC: bool> ArgOptionSetter for ArgOption<T, VARIADIC> {
+    option_setter!();
+
+    fn num_values(&self) -> usize {
+        1
+    }
+
+    fn set(&mut self, option: &'static str, mut values: Vec<OsString>) -> Result<()> {
+        self.set(option, values.pop().unwrap())
+    }
+}
+
+impl ArgOptionSetter for ArgOption<Negatable, false> {
+    option_setter!();
+
+    fn num_values(&self) -> usize {
+        0
+    }
+
+    fn set(&mut self, option: &'static str, _values: Vec<OsString>) -> Result<()> {
+        self.set(option)
+    }
+
+    fn is_present(&self) -> bool {
+        matches!(self.value.last(), Some(Negatable(true)))
+    }
+}
+
+impl<T: Value, const N: usize, const VARIADIC: bool> ArgOptionSetter
+    for ArgOption<Values<T, N>, VARIADIC>
+{
+    option_setter!();
+
+    fn num_values(&self) -> usize {
+        N
+    }
+
+    fn value_names(&self) -> &'_ [&'_ str] {
+        self.decl.value_names
+    }
+
+    fn set(&mut self, option: &'static str, values: Vec<OsString>) -> Result<()> {
+        self.set(option, values)
+    }
+}
+
+impl<T: Value, const VARIADIC: bool> ArgOptionSetter for ArgOption<Delimited<T>, VARIADIC> {
+    option_setter!();
+
+    fn num_values(&self) -> usize {
+        1
+    }
+
+    fn set(&mut self, option: &'static str, mut values: Vec<OsString>) -> Result<()> {
+        self.set(option, values.pop().unwrap())
+    }
+}
+
+impl<K: Value + PartialEq, V: Value, const VARIADIC: bool> ArgOptionSetter
+    for ArgOption<KeyValue<K, V>, VARIADIC>
+{
+    option_setter!();
+
+    fn num_values(&self) -> usize {
+        1
+    }
+
+    fn set(&mut self, option: &'static str, mut values: Vec<OsString>) -> Result<()> {
+        self.set(option, values.pop().unwrap())
+    }
+}
+
+impl<T: Value, const VARIADIC: bool> ArgOptionSetter for ArgOption<OptionalValue<T>, VARIADIC> {
+    option_setter!();
+
+    fn num_values(&self) -> usize {
+        0
+    }
+
+    fn value_names(&self) -> &'_ [&'_ str] {
+        &[]
+    }
+
+    fn takes_optional_value(&self) -> bool {
+        true
+    }
+
+    fn set(&mut self, option: &'static str, mut values: Vec<OsString>) -> Result<()> {
+        self.set(option, values.pop())
+    }
+}
+
+impl<T: Fn() -> Error> ArgOptionSetter for ArgOptionAction<'_, T> {
     fn names(&self) -> &'_ [&'_ str] {
         self.names
     }
 
     fn used_name(&self) -> &'_ str {
-        self.used_name.unwrap()
+        unreachable!()
     }
 
     fn is_set(&self) -> bool {
-        !self.value.is_empty()
+        false
     }
 
     fn conflicts(&self) -> &'_ [&'_ str] {
-        self.conflicts
+        &[]
+    }
+
+    fn requires(&self) -> &'_ [&'_ str] {
+        &[]
+    }
+
+    fn env(&self) -> Option<&'static str> {
+        None
+    }
+
+    fn is_global(&self) -> bool {
+        false
     }
 
     fn try_match(&self, option: &str) -> Option<&'static str> {
         self.try_match(option)
     }
 
-    fn takes_value(&self) -> bool {
+    fn num_values(&self) -> usize {
+        0
+    }
+
+    fn set(&mut self, _option: &'static str, _values: Vec<OsString>) -> Result<()> {
+        self.set()
+    }
+
+    fn is_action(&self) -> bool {
+        true
+    }
+}
+
+pub trait ArgNonOptionSetter {
+    fn name(&self) -> &'_ str;
+    fn is_optional(&self) -> bool;
+    fn is_variadic(&self) -> bool;
+    fn is_trailing(&self) -> bool;
+    fn is_set(&self) -> bool;
+    fn conflicts(&self) -> &'_ [&'_ str];
+    fn requires(&self) -> &'_ [&'_ str];
+    fn grant(&mut self, num_args: usize);
+    fn grants(&self) -> usize;
+    fn command(&self) -> Option<fn(&str) -> bool>;
+    fn set(&mut self, arg: OsString) -> Result<()>;
+
+    fn parse_command(
+        &mut self,
+        _bin_name: &str,
+        _program: Option<&str>,
+        _config: Option<&Config>,
+        _globals: &mut [&mut dyn ArgOptionSetter],
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    fn is_external_command(&self) -> bool {
         false
     }
 
-    fn set(&mut self, option: &'static str, _value: Option<String>) -> Result<()> {
-        self.set(option)
+    // Raw non-options take the remaining arguments as is
+    fn is_raw(&self) -> bool {
+        false
     }
 }
 
-impl<T: Value, const VARIADIC: bool> ArgOptionSetter for ArgOption<T, VARIADIC> {
-    fn names(&self) -> &'_ [&'_ str] {
-        self.names
+impl<T: Value, const OPTIONAL: bool, const VARIADIC: bool> ArgNonOptionSetter
+    for ArgNonOption<T, OPTIONAL, VARIADIC>
+{
+    fn name(&self) -> &'_ str {
+        self.name
     }
 
-    fn used_name(&self) -> &'_ str {
-        self.used_name.unwrap()
+    fn is_optional(&self) -> bool {
+        OPTIONAL
+    }
+
+    fn is_variadic(&self) -> bool {
+        VARIADIC
+    }
+
+    fn is_trailing(&self) -> bool {
+        self.trailing
+    }
+
+    fn is_raw(&self) -> bool {
+        self.raw
     }
 
     fn is_set(&self) -> bool {
@@ -431,226 +1543,681 @@ impl<T: Value, const VARIADIC: bool> ArgOptionSetter for ArgOption<T, VARIADIC>
         self.conflicts
     }
 
-    fn try_match(&self, option: &str) -> Option<&'static str> {
-        self.try_match(option)
+    fn requires(&self) -> &'_ [&'_ str] {
+        self.requires
     }
 
-    fn takes_value(&self) -> bool {
-        true
+    fn grant(&mut self, num_args: usize) {
+        self.grants += num_args;
     }
 
-    fn set(&mut self, option: &'static str, value: Option<String>) -> Result<()> {
-        self.set(option, value.unwrap())
+    fn grants(&self) -> usize {
+        self.grants
+    }
+
+    fn command(&self) -> Option<fn(&str) -> bool> {
+        None
+    }
+
+    fn set(&mut self, arg: OsString) -> Result<()> {
+        let value = self.parser.parse(self.name, arg)?;
+        self.value.push(value);
+        Ok(())
     }
 }
 
-impl<T: Fn() -> Error> ArgOptionSetter for ArgOptionAction<'_, T> {
-    fn names(&self) -> &'_ [&'_ str] {
-        self.names
+impl<K: Value + PartialEq, V: Value, const OPTIONAL: bool, const VARIADIC: bool> ArgNonOptionSetter
+    for ArgNonOption<KeyValue<K, V>, OPTIONAL, VARIADIC>
+{
+    fn name(&self) -> &'_ str {
+        self.name
     }
 
-    fn used_name(&self) -> &'_ str {
-        unreachable!()
+    fn is_optional(&self) -> bool {
+        OPTIONAL
+    }
+
+    fn is_variadic(&self) -> bool {
+        VARIADIC
+    }
+
+    fn is_trailing(&self) -> bool {
+        self.trailing
     }
 
     fn is_set(&self) -> bool {
+        !self.value.is_empty()
+    }
+
+    fn conflicts(&self) -> &'_ [&'_ str] {
+        self.conflicts
+    }
+
+    fn requires(&self) -> &'_ [&'_ str] {
+        self.requires
+    }
+
+    fn grant(&mut self, num_args: usize) {
+        self.grants += num_args;
+    }
+
+    fn grants(&self) -> usize {
+        self.grants
+    }
+
+    fn command(&self) -> Option<fn(&str) -> bool> {
+        None
+    }
+
+    fn set(&mut self, arg: OsString) -> Result<()> {
+        let pair = parse_key_value(self.name, arg, &self.value, &self.parser)?;
+        self.value.push(pair);
+        Ok(())
+    }
+}
+
+impl<T: Command, const OPTIONAL: bool> ArgNonOptionSetter for ArgNonOptionCommand<T, OPTIONAL> {
+    fn name(&self) -> &'_ str {
+        self.name
+    }
+
+    fn is_optional(&self) -> bool {
+        OPTIONAL
+    }
+
+    fn is_variadic(&self) -> bool {
+        true
+    }
+
+    fn is_trailing(&self) -> bool {
         false
     }
 
+    fn is_set(&self) -> bool {
+        !self.value.is_empty()
+    }
+
     fn conflicts(&self) -> &'_ [&'_ str] {
-        &[]
+        self.conflicts
     }
 
-    fn try_match(&self, option: &str) -> Option<&'static str> {
-        self.try_match(option)
+    fn requires(&self) -> &'_ [&'_ str] {
+        self.requires
+    }
+
+    fn grant(&mut self, num_args: usize) {
+        self.grants += num_args;
+    }
+
+    fn grants(&self) -> usize {
+        self.grants
     }
 
-    fn takes_value(&self) -> bool {
-        false
+    fn command(&self) -> Option<fn(&str) -> bool> {
+        match self.abbreviate {
+            true => Some(Self::is_command_prefix),
+            false => Some(Self::is_command),
+        }
+    }
+
+    fn is_external_command(&self) -> bool {
+        T::external()
+    }
+
+    fn set(&mut self, arg: OsString) -> Result<()> {
+        self.value.push(arg);
+        Ok(())
+    }
+
+    fn parse_command(
+        &mut self,
+        bin_name: &str,
+        program: Option<&str>,
+        config: Option<&Config>,
+        globals: &mut [&mut dyn ArgOptionSetter],
+    ) -> Result<()> {
+        let mut args = Args::from_vec(self.value.clone());
+        let Some(arg0) = args.peek().map(|arg0| arg0.to_string_lossy().into_owned()) else {
+            return Ok(());
+        };
+        let command = match Self::normalize(self.abbreviate, &arg0) {
+            Err(Error::InvalidCommand { .. }) if T::external() => &arg0,
+            result => result?,
+        };
+        args.set_command(self.value[0].clone());
+        args.set_bin_name(format!("{bin_name} {command}"));
+        args.set_program(program.map(String::from));
+        let config = config.map(|config| config.command(command));
+        self.parsed = Some(T::from(command, args, config.as_ref(), globals)?);
+        Ok(())
+    }
+}
+
+pub fn parse(
+    args: Args,
+    bin_name: &str,
+    settings: Settings,
+    config: Option<&Config>,
+    globals: &mut [&mut dyn ArgOptionSetter],
+    options: &mut [&mut dyn ArgOptionSetter],
+    non_options: &mut [&mut dyn ArgNonOptionSetter],
+) -> Result<()> {
+    const POSIXLY_CORRECT: &str = "POSIXLY_CORRECT";
+
+    let program = args.program().map(String::from);
+    let mut l = Lexer::new(args);
+
+    if settings.response_files {
+        l.response_files();
+
+        if let Some(at) = verbatim_at(non_options) {
+            l.verbatim_at(at);
+        }
+    }
+
+    if settings.permute && std::env::var_os(POSIXLY_CORRECT).is_none() {
+        let command = non_options.iter().find_map(|setter| setter.command());
+        l.permute(command);
+
+        if let Some(at) = external_command_at(non_options) {
+            l.command_at(at);
+        }
+    }
+
+    // Global options of the levels above are also accepted here, see set_options()
+    if !options
+        .iter()
+        .map(|setter| setter.names())
+        .chain(globals.iter().map(|setter| setter.names()))
+        .any(has_digit_short)
+    {
+        l.negative_numbers();
+    }
+
+    set_options(&mut l, settings, options, globals)?;
+
+    let deselected = options
+        .iter()
+        .filter(|setter| setter.is_present())
+        .flat_map(|setter| setter.conflicts())
+        .filter(|conflict| conflict.starts_with(['!', '?']))
+        .copied()
+        .collect::<Vec<_>>();
+
+    let before_dashdash = l.before_dashdash()?;
+    set_non_options(&mut l, non_options, &deselected)
+        .map_err(|error| misplaced_option(error, &before_dashdash, options, globals))?;
+
+    // Commands are parsed with the global options of all levels above them, so that global
+    // options following a command are set before falling back to environment variables
+    fn reborrow<'a>(setter: &'a mut &mut dyn ArgOptionSetter) -> &'a mut dyn ArgOptionSetter {
+        &mut **setter
+    }
+
+    let mut all_globals = options
+        .iter_mut()
+        .filter(|setter| setter.is_global())
+        .map(reborrow)
+        .chain(globals.iter_mut().map(reborrow))
+        .collect::<Vec<_>>();
+
+    for setter in non_options.iter_mut() {
+        setter.parse_command(bin_name, program.as_deref(), config, &mut all_globals)?;
+    }
+
+    set_env_options(options)?;
+
+    // Global options of the levels above can also be set in the section of this command,
+    // taking precedence over the sections above, but not over their environment variables
+    if let Some(config) = config {
+        set_env_options(globals)?;
+        config::set_options(config, options, globals, non_options)?;
+    }
+
+    check_conflicts_and_choices(options, non_options)?;
+    check_requirements(options, non_options)?;
+    Ok(())
+}
+
+// The position of a command with external commands
+fn external_command_at(setters: &[&mut dyn ArgNonOptionSetter]) -> Option<usize> {
+    let index = setters
+        .iter()
+        .position(|setter| setter.is_external_command())?;
+
+    position(&setters[..index])
+}
+
+// The position of a command or a raw non-option, where the expansion of response files
+// stops. If the position isn't fixed, none of the non-options are expanded.
+fn verbatim_at(setters: &[&mut dyn ArgNonOptionSetter]) -> Option<usize> {
+    let index = setters
+        .iter()
+        .position(|setter| setter.command().is_some() || setter.is_raw())?;
+
+    Some(position(&setters[..index]).unwrap_or(0))
+}
+
+// The position following the non-options, if they take exactly one argument each, where
+// alternatives share one position
+fn position(setters: &[&mut dyn ArgNonOptionSetter]) -> Option<usize> {
+    if setters
+        .iter()
+        .any(|setter| setter.is_optional() || setter.is_variadic())
+    {
+        return None;
     }
 
-    fn set(&mut self, _option: &'static str, _value: Option<String>) -> Result<()> {
-        self.set()
-    }
+    let alternatives = setters
+        .windows(2)
+        .filter(|pair| are_alternatives(&*pair[0], &*pair[1]))
+        .count();
+
+    Some(setters.len() - alternatives)
 }
 
-pub trait ArgNonOptionSetter {
-    fn name(&self) -> &'_ str;
-    fn is_optional(&self) -> bool;
-    fn is_variadic(&self) -> bool;
-    fn is_set(&self) -> bool;
-    fn conflicts(&self) -> &'_ [&'_ str];
-    fn grant(&mut self, num_args: usize);
-    fn grants(&self) -> usize;
-    fn set(&mut self, arg: String) -> Result<()>;
+fn has_digit_short(names: &[&str]) -> bool {
+    names.iter().any(|name| {
+        let mut chars = name.chars();
+        chars.next() == Some('-') && chars.next().is_some_and(|c| c.is_ascii_digit())
+    })
 }
 
-impl<T: Value, const OPTIONAL: bool, const VARIADIC: bool> ArgNonOptionSetter
-    for ArgNonOption<T, OPTIONAL, VARIADIC>
-{
-    fn name(&self) -> &'_ str {
-        self.name
-    }
+fn set_options(
+    lexer: &mut Lexer,
+    settings: Settings,
+    setters: &mut [&mut dyn ArgOptionSetter],
+    globals: &mut [&mut dyn ArgOptionSetter],
+) -> Result<()> {
+    'next: while let Some(option) = lexer.next_option()? {
+        // Global options of the levels above are only used if no option here matches
+        // exactly. Indexes past the options here are indexes into the global options.
+        let matched = match setters
+            .iter()
+            .map(|setter| &**setter)
+            .chain(globals.iter().map(|setter| &**setter))
+            .enumerate()
+            .find_map(|(index, setter)| setter.try_match(option).map(|option| (index, option)))
+        {
+            None if settings.abbreviate => try_match_prefix(setters, globals, option)?,
+            matched => matched,
+        };
 
-    fn is_optional(&self) -> bool {
-        OPTIONAL
-    }
+        let (setter, option): (&mut dyn ArgOptionSetter, _) = match matched {
+            Some((index, option)) if index < setters.len() => (setters[index], option),
+            Some((index, option)) => (globals[index - setters.len()], option),
+            None => {
+                let option = option.to_string();
+                let mut names = vec![];
+                names.extend(setters.iter().flat_map(|setter| setter.names()));
+                names.extend(globals.iter().flat_map(|setter| setter.names()));
+
+                return Err(Error::InvalidOption {
+                    suggestion: suggest_option(lexer.arg(), &names),
+                    option,
+                });
+            }
+        };
 
-    fn is_variadic(&self) -> bool {
-        VARIADIC
-    }
+        let mut values = vec![];
+
+        for index in 0..setter.num_values() {
+            let value = lexer.next_value().map_err(|error| match error {
+                Error::MissingValue { option, .. } => Error::MissingValue {
+                    option,
+                    value: setter.value_names().get(index).map(|name| name.to_string()),
+                },
+                error => error,
+            })?;
+            values.push(value);
+        }
 
-    fn is_set(&self) -> bool {
-        !self.value.is_empty()
-    }
+        if setter.takes_optional_value() {
+            values.extend(lexer.next_optional_value());
+        }
 
-    fn conflicts(&self) -> &'_ [&'_ str] {
-        self.conflicts
+        setter.set(option, values)?;
+        continue 'next;
     }
 
-    fn grant(&mut self, num_args: usize) {
-        self.grants += num_args;
-    }
+    Ok(())
+}
 
-    fn grants(&self) -> usize {
-        self.grants
-    }
+fn set_env_options(setters: &mut [&mut dyn ArgOptionSetter]) -> Result<()> {
+    for setter in setters.iter_mut().filter(|setter| !setter.is_set()) {
+        let Some(env) = setter.env() else {
+            continue;
+        };
 
-    fn set(&mut self, arg: String) -> Result<()> {
-        self.value.push(T::parse(arg)?);
-        Ok(())
+        let Some(value) = std::env::var_os(env) else {
+            continue;
+        };
+
+        setter.set(env, vec![value]).map_err(|error| match error {
+            Error::ParsingFailed { value, error } => Error::EnvParsingFailed {
+                env: env.to_string(),
+                value,
+                error,
+            },
+            Error::InvalidUnicode { arg } => Error::EnvParsingFailed {
+                env: env.to_string(),
+                value: arg,
+                error: "invalid unicode".into(),
+            },
+            error => error,
+        })?;
     }
+
+    Ok(())
 }
 
-impl<T: Command, const OPTIONAL: bool> ArgNonOptionSetter for ArgNonOptionCommand<T, OPTIONAL> {
-    fn name(&self) -> &'_ str {
-        self.name
-    }
+// Suggest the declared option closest to the argument of an invalid option, which also
+// catches a long option with a single dash, e.g. "-verbose", and a short option with two
+// dashes, e.g. "--v"
+fn suggest_option(arg: &str, names: &[&str]) -> Option<String> {
+    let arg = arg.split_once('=').map_or(arg, |(arg, _)| arg);
+    let undashed = names
+        .iter()
+        .map(|name| name.trim_start_matches('-'))
+        .collect::<Vec<_>>();
 
-    fn is_optional(&self) -> bool {
-        OPTIONAL
-    }
+    let name = arg.trim_start_matches('-');
+    let suggestion = match undashed.contains(&name) {
+        true => name,
+        false => choice::suggest(name, &undashed)?,
+    };
 
-    fn is_variadic(&self) -> bool {
-        true
-    }
+    undashed
+        .iter()
+        .position(|name| *name == suggestion)
+        .map(|index| names[index].to_string())
+}
 
-    fn is_set(&self) -> bool {
-        !self.value.is_empty()
+pub fn invalid_command(command: &str, names: &[&str]) -> Error {
+    Error::InvalidCommand {
+        arg: command.to_string(),
+        suggestion: choice::suggest(command, names).map(String::from),
     }
+}
 
-    fn conflicts(&self) -> &'_ [&'_ str] {
-        self.conflicts
-    }
+// Match an abbreviated long option against the options here and the global options of
+// the levels above, see set_options(). A global option shadowed by an option here with
+// the same name is not a candidate.
+fn try_match_prefix(
+    setters: &[&mut dyn ArgOptionSetter],
+    globals: &[&mut dyn ArgOptionSetter],
+    option: &str,
+) -> Result<Option<(usize, &'static str)>> {
+    const DASHDASH: &str = "--";
 
-    fn grant(&mut self, num_args: usize) {
-        self.grants += num_args;
+    if !option.starts_with(DASHDASH) || option.len() == DASHDASH.len() {
+        return Ok(None);
     }
 
-    fn grants(&self) -> usize {
-        self.grants
+    let mut candidates: Vec<(usize, &'static str)> = vec![];
+
+    let all = setters
+        .iter()
+        .map(|setter| &**setter)
+        .chain(globals.iter().map(|setter| &**setter));
+
+    for (index, setter) in all.enumerate() {
+        if let Some(name) = setter
+            .names()
+            .iter()
+            .find(|name| name.starts_with(DASHDASH) && name.starts_with(option))
+            .and_then(|name| setter.try_match(name))
+            && !candidates.iter().any(|(_, candidate)| *candidate == name)
+        {
+            candidates.push((index, name));
+        }
     }
 
-    fn set(&mut self, arg: String) -> Result<()> {
-        self.value.push(arg);
-        Ok(())
+    match candidates[..] {
+        [] => Ok(None),
+        [candidate] => Ok(Some(candidate)),
+        _ => Err(Error::AmbiguousArgument {
+            arg: option.to_string(),
+            candidates: candidates
+                .iter()
+                .map(|(_, name)| name.to_string())
+                .collect(),
+        }),
     }
 }
 
-pub fn parse(
-    args: Args,
-    options: &mut [&mut dyn ArgOptionSetter],
-    non_options: &mut [&mut dyn ArgNonOptionSetter],
+// Non-options in the same group of conflicting arguments (`!` or `?`) are alternatives
+// sharing one position, where each argument is set to the first alternative it parses as.
+// Non-options in a group with an option already set are deselected, and only take the
+// arguments left over, which then results in a conflict.
+fn set_non_options(
+    lexer: &mut Lexer,
+    setters: &mut [&mut dyn ArgNonOptionSetter],
+    deselected: &[&str],
 ) -> Result<()> {
-    let mut l = Lexer::new(args);
-    set_options(&mut l, options)?;
-    set_non_options(&mut l, non_options)?;
-    check_conflicts_and_choices(options, non_options)?;
-    Ok(())
-}
+    let trailing = setters.iter().position(|setter| setter.is_trailing());
 
-fn set_options(lexer: &mut Lexer, setters: &mut [&mut dyn ArgOptionSetter]) -> Result<()> {
-    'next: while let Some(option) = lexer.next_option()? {
-        let Some((setter, option)) = setters
-            .iter_mut()
-            .find_map(|setter| setter.try_match(option).map(|option| (setter, option)))
-        else {
-            return Err(Error::InvalidOption {
-                option: option.to_string(),
+    if let Some(index) = trailing {
+        for arg in lexer.trailing()?.0 {
+            setters[index].set(arg)?;
+        }
+
+        let setter = &setters[index];
+        if !setter.is_optional() && !setter.is_set() {
+            return Err(Error::MissingArgument {
+                arg: setter.name().to_string(),
             });
-        };
+        }
+    }
+
+    let args = lexer.non_options()?;
+    let mut num_args = args.0.len();
+
+    let is_deselected = |setter: &dyn ArgNonOptionSetter| {
+        setter
+            .conflicts()
+            .iter()
+            .any(|conflict| deselected.contains(conflict))
+    };
 
-        let value = if setter.takes_value() {
-            Some(lexer.next_value()?)
-        } else {
-            None
+    let mut slots = Vec::<Vec<usize>>::new();
+
+    for (index, setter) in setters.iter().enumerate() {
+        if Some(index) == trailing {
+            continue;
+        }
+
+        let alternative = match is_deselected(*setter) {
+            true => None,
+            false => slots.iter_mut().find(|slot| {
+                let other = &setters[slot[0]];
+                !is_deselected(*other) && are_alternatives(*setter, *other)
+            }),
         };
 
-        setter.set(option, value)?;
-        continue 'next;
+        match alternative {
+            Some(slot) => slot.push(index),
+            None => slots.push(vec![index]),
+        }
     }
 
-    Ok(())
-}
+    let slots = slots
+        .into_iter()
+        .map(|indices| Slot {
+            selected: !is_deselected(&*setters[indices[0]]),
+            optional: indices.iter().all(|&index| setters[index].is_optional()),
+            variadic: indices.iter().any(|&index| setters[index].is_variadic()),
+            indices,
+        })
+        .collect::<Vec<_>>();
 
-fn set_non_options(lexer: &mut Lexer, setters: &mut [&mut dyn ArgNonOptionSetter]) -> Result<()> {
-    let args = lexer.non_options()?;
-    let mut num_args = args.0.len();
+    let (selected, deselected) = slots.iter().partition::<Vec<_>, _>(|slot| slot.selected);
 
-    for required in setters
-        .iter_mut()
-        .filter(|setter| !setter.is_optional())
-        .take(num_args)
-    {
-        required.grant(1);
-        num_args -= 1;
-    }
+    for slots in [selected, deselected] {
+        let required = slots.iter().filter(|slot| !slot.optional);
+        let optional = slots.iter().filter(|slot| slot.optional);
 
-    for optional in setters
-        .iter_mut()
-        .filter(|setter| setter.is_optional())
-        .take(num_args)
-    {
-        optional.grant(1);
-        num_args -= 1;
-    }
+        for slot in required.chain(optional).take(num_args) {
+            setters[slot.indices[0]].grant(1);
+            num_args -= 1;
+        }
 
-    if num_args > 0
-        && let Some(variadic) = setters.iter_mut().find(|setter| setter.is_variadic())
-    {
-        variadic.grant(num_args);
+        if num_args > 0
+            && let Some(slot) = slots.iter().find(|slot| slot.variadic)
+        {
+            setters[slot.indices[0]].grant(num_args);
+            num_args = 0;
+        }
     }
 
-    for setter in setters.iter_mut() {
-        for arg in args.0.drain(..setter.grants()) {
-            setter.set(arg)?;
+    for slot in &slots {
+        for arg in args.0.drain(..setters[slot.indices[0]].grants()) {
+            set_alternative(setters, &slot.indices, arg)?;
         }
     }
 
-    if let Some(required) = setters
+    if let Some(slot) = slots
         .iter()
-        .find(|arg| !arg.is_optional() && !arg.is_set())
+        .find(|slot| !slot.optional && !slot.indices.iter().any(|&index| setters[index].is_set()))
     {
         return Err(Error::MissingArgument {
-            arg: required.name().to_string(),
+            arg: setters[slot.indices[0]].name().to_string(),
         });
     }
 
     match args.0.pop_front() {
         None => Ok(()),
-        Some(arg) => Err(Error::InvalidArgument { arg }),
+        Some(arg) => Err(Error::InvalidArgument {
+            arg: arg.to_string_lossy().into_owned(),
+        }),
+    }
+}
+
+struct Slot {
+    indices: Vec<usize>,
+    selected: bool,
+    optional: bool,
+    variadic: bool,
+}
+
+fn are_alternatives(setter0: &dyn ArgNonOptionSetter, setter1: &dyn ArgNonOptionSetter) -> bool {
+    setter0
+        .conflicts()
+        .iter()
+        .filter(|conflict| conflict.starts_with(['!', '?']))
+        .any(|conflict| setter1.conflicts().contains(conflict))
+}
+
+// Set the argument to the first alternative it parses as, or fail with the error of the
+// first alternative
+fn set_alternative(
+    setters: &mut [&mut dyn ArgNonOptionSetter],
+    slot: &[usize],
+    arg: OsString,
+) -> Result<()> {
+    let mut first_error = None;
+
+    for &index in slot {
+        let setter = &mut setters[index];
+        if setter.is_set() && !setter.is_variadic() {
+            continue;
+        }
+
+        match setter.set(arg.clone()) {
+            Ok(()) => return Ok(()),
+            Err(error) => {
+                first_error.get_or_insert(error);
+            }
+        }
+    }
+
+    match first_error {
+        Some(error) => Err(error),
+        None => Err(Error::InvalidArgument {
+            arg: arg.to_string_lossy().into_owned(),
+        }),
+    }
+}
+
+fn misplaced_option(
+    error: Error,
+    before_dashdash: &[String],
+    setters: &[&mut dyn ArgOptionSetter],
+    globals: &[&mut dyn ArgOptionSetter],
+) -> Error {
+    let arg = match &error {
+        Error::InvalidArgument { arg } => arg,
+        Error::ParsingFailed { value, .. } => value,
+        _ => return error,
+    };
+
+    // Arguments following "--" are never options
+    if !arg.starts_with('-') || !before_dashdash.contains(arg) {
+        return error;
+    }
+
+    let option = match arg.split_once('=') {
+        Some((option, _)) => option,
+        None => arg,
+    };
+
+    let option = match option.starts_with("--") {
+        true => option,
+        false => match option.char_indices().nth(2) {
+            Some((end, _)) => &option[..end],
+            None => option,
+        },
+    };
+
+    match setters
+        .iter()
+        .find_map(|setter| setter.try_match(option))
+        .or_else(|| globals.iter().find_map(|setter| setter.try_match(option)))
+    {
+        Some(option) => Error::MisplacedOption {
+            option: option.to_string(),
+        },
+        None => error,
     }
 }
 
+// Arguments are grouped by conflict-id, prefixed by the kind of group: `!` at most one,
+// `?` exactly one, `+` at least one, or `&` all or none of the arguments in the group.
 fn check_conflicts_and_choices(
     options: &[&mut dyn ArgOptionSetter],
     non_options: &[&mut dyn ArgNonOptionSetter],
 ) -> Result<()> {
+    const NOT: char = '!';
+    const QUESTION: char = '?';
+    const PLUS: char = '+';
+    const AND: char = '&';
+    const DASHDASH: &str = "--";
+
+    let options = options.iter().map(|arg| {
+        let names = arg.names();
+        let primary_name = match names.iter().find(|name| name.starts_with(DASHDASH)) {
+            Some(first_long) => first_long,
+            _ => names[0],
+        };
+        let name = match arg.is_set() {
+            true => arg.used_name(),
+            false => primary_name,
+        };
+        (arg.is_present(), name, primary_name, arg.conflicts())
+    });
+
+    let non_options = non_options
+        .iter()
+        .map(|arg| (arg.is_set(), arg.name(), arg.name(), arg.conflicts()));
+
+    let args = options.chain(non_options).collect::<Vec<_>>();
     let mut conflicts = HashMap::new();
 
-    for arg1 in options.iter().filter(|arg| arg.is_set()) {
-        for conflict in arg1.conflicts() {
-            let name1 = arg1.used_name();
+    for (_, name1, _, groups) in args.iter().filter(|(is_set, ..)| *is_set) {
+        for conflict in groups
+            .iter()
+            .filter(|group| group.starts_with([NOT, QUESTION]))
+        {
             if let Some(name0) = conflicts.insert(conflict, name1) {
                 return Err(Error::ConflictingArguments {
                     arg0: name0.to_string(),
@@ -660,54 +2227,78 @@ fn check_conflicts_and_choices(
         }
     }
 
-    for arg1 in non_options.iter().filter(|arg| arg.is_set()) {
-        for conflict in arg1.conflicts() {
-            let name1 = arg1.name();
-            if let Some(name0) = conflicts.insert(conflict, name1) {
-                return Err(Error::ConflictingArguments {
-                    arg0: name0.to_string(),
-                    arg1: name1.to_string(),
-                });
+    let mut groups = Vec::<(&str, Vec<_>)>::new();
+
+    for (is_set, name, primary_name, conflicts) in &args {
+        for conflict in conflicts.iter() {
+            match groups.iter_mut().find(|(group, _)| group == conflict) {
+                Some((_, members)) => members.push((is_set, name, primary_name)),
+                None => groups.push((conflict, vec![(is_set, name, primary_name)])),
             }
         }
     }
 
-    let mut choices = HashMap::new();
-    const QUESTION: char = '?';
-    const DASHDASH: &str = "--";
+    for (group, members) in &groups {
+        let set = members.iter().find(|(is_set, ..)| **is_set);
+        let unset = members.iter().find(|(is_set, ..)| !**is_set);
 
-    for arg in options {
-        for conflict in arg
-            .conflicts()
-            .iter()
-            .filter(|conflict| conflict.starts_with(QUESTION))
-        {
-            let names = arg.names();
-            let primary_name = match names.iter().find(|name| name.starts_with(DASHDASH)) {
-                Some(first_long) => first_long,
-                _ => names[0],
-            };
-            choices.entry(conflict).or_insert(vec![]).push(primary_name);
+        match (set, unset) {
+            (None, _) if group.starts_with([QUESTION, PLUS]) => {
+                let alternatives = members
+                    .iter()
+                    .map(|(_, _, primary_name)| primary_name.to_string())
+                    .collect::<Vec<_>>();
+                return Err(Error::MissingChoice { alternatives });
+            }
+            (Some((_, name, _)), Some((_, _, primary_name))) if group.starts_with(AND) => {
+                return Err(Error::IncompleteGroup {
+                    arg: name.to_string(),
+                    missing: primary_name.to_string(),
+                });
+            }
+            _ => {}
         }
     }
 
-    for arg in non_options {
-        for conflict in arg
-            .conflicts()
+    Ok(())
+}
+
+// Requirements are referred to by the first long option name, or the first short option
+// name if there is no long, or the non-option name.
+fn check_requirements(
+    options: &[&mut dyn ArgOptionSetter],
+    non_options: &[&mut dyn ArgNonOptionSetter],
+) -> Result<()> {
+    let is_set = |name: &str| {
+        options
             .iter()
-            .filter(|conflict| conflict.starts_with(QUESTION))
-        {
-            choices.entry(conflict).or_insert(vec![]).push(arg.name());
-        }
-    }
+            .find(|arg| arg.names().contains(&name))
+            .map(|arg| arg.is_set())
+            .or_else(|| {
+                non_options
+                    .iter()
+                    .find(|arg| arg.name() == name)
+                    .map(|arg| arg.is_set())
+            })
+            .unwrap_or(false)
+    };
+
+    let options = options
+        .iter()
+        .filter(|arg| arg.is_set())
+        .map(|arg| (arg.used_name(), arg.requires()));
 
-    for (choice, alternatives) in choices {
-        if !conflicts.contains_key(choice) {
-            let alternatives = alternatives
-                .iter()
-                .map(|alternative| alternative.to_string())
-                .collect::<Vec<_>>();
-            return Err(Error::MissingChoice { alternatives });
+    let non_options = non_options
+        .iter()
+        .filter(|arg| arg.is_set())
+        .map(|arg| (arg.name(), arg.requires()));
+
+    for (name, requires) in options.chain(non_options) {
+        if let Some(missing) = requires.iter().find(|requires| !is_set(requires)) {
+            return Err(Error::MissingRequirement {
+                arg: name.to_string(),
+                requires: missing.to_string(),
+            });
         }
     }
 
@@ -718,6 +2309,7 @@ fn check_conflicts_and_choices(
 mod test {
     use crate::Args;
     use crate::Error;
+    use crate::Value;
     use crate::arg::ArgNonOptionSetter;
     use crate::arg::non_option;
     use crate::arg::option;
@@ -726,7 +2318,7 @@ mod test {
     #[test]
     fn set_non_option_0() {
         let mut l = Lexer::new(Args::from(["0"]));
-        let result = super::set_non_options(&mut l, &mut []);
+        let result = super::set_non_options(&mut l, &mut [], &[]);
 
         let error = result.err().unwrap();
         assert!(matches!(error, Error::InvalidArgument { arg }
@@ -736,11 +2328,11 @@ mod test {
 
     #[test]
     fn set_non_option_1() {
-        let mut a = non_option("<a>").value::<u64>();
-        let mut b = non_option("<b>").value::<u64>();
+        let mut a = non_option("<a>").value::<u64>(Value::parse);
+        let mut b = non_option("<b>").value::<u64>(Value::parse);
 
         let mut l = Lexer::new(Args::from(["0"]));
-        let result = super::set_non_options(&mut l, &mut [a.as_setter(), b.as_setter()]);
+        let result = super::set_non_options(&mut l, &mut [a.as_setter(), b.as_setter()], &[]);
 
         let error = result.err().unwrap();
         assert!(matches!(error, Error::MissingArgument { arg }
@@ -750,15 +2342,19 @@ mod test {
 
     #[test]
     fn set_non_option_2() {
-        let mut a = non_option("[a]").value::<u64>().optional();
-        let mut b = non_option("<b>").value::<u64>();
-        let mut c = non_option("[c...]").value::<u64>().optional().variadic();
-        let mut d = non_option("<d>").value::<u64>();
+        let mut a = non_option("[a]").value::<u64>(Value::parse).optional();
+        let mut b = non_option("<b>").value::<u64>(Value::parse);
+        let mut c = non_option("[c...]")
+            .value::<u64>(Value::parse)
+            .optional()
+            .variadic();
+        let mut d = non_option("<d>").value::<u64>(Value::parse);
 
         let mut l = Lexer::new(Args::from(["0", "1"]));
         let result = super::set_non_options(
             &mut l,
             &mut [a.as_setter(), b.as_setter(), c.as_setter(), d.as_setter()],
+            &[],
         );
 
         assert!(result.is_ok());
@@ -770,15 +2366,19 @@ mod test {
 
     #[test]
     fn set_non_option_3() {
-        let mut a = non_option("<a>").value::<u64>().optional();
-        let mut b = non_option("<b>").value::<u64>();
-        let mut c = non_option("[c...]").value::<u64>().optional().variadic();
-        let mut d = non_option("<d>").value::<u64>();
+        let mut a = non_option("<a>").value::<u64>(Value::parse).optional();
+        let mut b = non_option("<b>").value::<u64>(Value::parse);
+        let mut c = non_option("[c...]")
+            .value::<u64>(Value::parse)
+            .optional()
+            .variadic();
+        let mut d = non_option("<d>").value::<u64>(Value::parse);
 
         let mut l = Lexer::new(Args::from(["0", "1", "2"]));
         let result = super::set_non_options(
             &mut l,
             &mut [a.as_setter(), b.as_setter(), c.as_setter(), d.as_setter()],
+            &[],
         );
 
         assert!(result.is_ok());
@@ -790,15 +2390,19 @@ mod test {
 
     #[test]
     fn set_non_option_4() {
-        let mut a = non_option("[a]").value::<u64>().optional();
-        let mut b = non_option("<b>").value::<u64>();
-        let mut c = non_option("[c...]").value::<u64>().optional().variadic();
-        let mut d = non_option("<d>").value::<u64>();
+        let mut a = non_option("[a]").value::<u64>(Value::parse).optional();
+        let mut b = non_option("<b>").value::<u64>(Value::parse);
+        let mut c = non_option("[c...]")
+            .value::<u64>(Value::parse)
+            .optional()
+            .variadic();
+        let mut d = non_option("<d>").value::<u64>(Value::parse);
 
         let mut l = Lexer::new(Args::from(["0", "1", "2", "3", "4", "5"]));
         let result = super::set_non_options(
             &mut l,
             &mut [a.as_setter(), b.as_setter(), c.as_setter(), d.as_setter()],
+            &[],
         );
 
         assert!(result.is_ok());
@@ -810,9 +2414,11 @@ mod test {
 
     #[test]
     fn check_conflict_none_set() {
-        let mut a = option(&["-a"]).value::<u64>().conflicts(&["!"]);
-        let mut b = option(&["-b"]).value::<u64>().conflicts(&["!"]);
-        let mut c = non_option("<c>").value::<u64>().conflicts(&["!"]);
+        let mut a = option(&["-a"]).value::<u64>(Value::parse).conflicts(&["!"]);
+        let mut b = option(&["-b"]).value::<u64>(Value::parse).conflicts(&["!"]);
+        let mut c = non_option("<c>")
+            .value::<u64>(Value::parse)
+            .conflicts(&["!"]);
 
         let result =
             super::check_conflicts_and_choices(&[a.as_setter(), b.as_setter()], &[c.as_setter()]);
@@ -822,9 +2428,11 @@ mod test {
 
     #[test]
     fn check_conflict_one_set() {
-        let mut a = option(&["-a"]).value::<u64>().conflicts(&["!"]);
-        let mut b = option(&["-b"]).value::<u64>().conflicts(&["!"]);
-        let mut c = non_option("<c>").value::<u64>().conflicts(&["!"]);
+        let mut a = option(&["-a"]).value::<u64>(Value::parse).conflicts(&["!"]);
+        let mut b = option(&["-b"]).value::<u64>(Value::parse).conflicts(&["!"]);
+        let mut c = non_option("<c>")
+            .value::<u64>(Value::parse)
+            .conflicts(&["!"]);
 
         b.set("-b", "47".into()).unwrap();
 
@@ -836,9 +2444,11 @@ mod test {
 
     #[test]
     fn check_conflict_two_set() {
-        let mut a = option(&["-a"]).value::<u64>().conflicts(&["!"]);
-        let mut b = option(&["-b"]).value::<u64>().conflicts(&["!"]);
-        let mut c = non_option("<c>").value::<u64>().conflicts(&["!"]);
+        let mut a = option(&["-a"]).value::<u64>(Value::parse).conflicts(&["!"]);
+        let mut b = option(&["-b"]).value::<u64>(Value::parse).conflicts(&["!"]);
+        let mut c = non_option("<c>")
+            .value::<u64>(Value::parse)
+            .conflicts(&["!"]);
 
         b.set("-b", "47".into()).unwrap();
         c.set("47".into()).unwrap();
@@ -854,9 +2464,11 @@ mod test {
 
     #[test]
     fn check_conflict_all_set() {
-        let mut a = option(&["-a"]).value::<u64>().conflicts(&["!"]);
-        let mut b = option(&["-b"]).value::<u64>().conflicts(&["!"]);
-        let mut c = non_option("<c>").value::<u64>().conflicts(&["!"]);
+        let mut a = option(&["-a"]).value::<u64>(Value::parse).conflicts(&["!"]);
+        let mut b = option(&["-b"]).value::<u64>(Value::parse).conflicts(&["!"]);
+        let mut c = non_option("<c>")
+            .value::<u64>(Value::parse)
+            .conflicts(&["!"]);
 
         a.set("-a", "47".into()).unwrap();
         b.set("-b", "47".into()).unwrap();
@@ -873,10 +2485,10 @@ mod test {
 
     #[test]
     fn check_choice_none_set() {
-        let mut a = option(&["-a"]).value::<u64>().conflicts(&["?"]);
-        let mut b = option(&["-b"]).value::<u64>().conflicts(&["?"]);
+        let mut a = option(&["-a"]).value::<u64>(Value::parse).conflicts(&["?"]);
+        let mut b = option(&["-b"]).value::<u64>(Value::parse).conflicts(&["?"]);
         let mut c = non_option("<c>")
-            .value::<u64>()
+            .value::<u64>(Value::parse)
             .optional()
             .conflicts(&["?"]);
 
@@ -891,10 +2503,10 @@ mod test {
 
     #[test]
     fn check_choice_one_set() {
-        let mut a = option(&["-a"]).value::<u64>().conflicts(&["?"]);
-        let mut b = option(&["-b"]).value::<u64>().conflicts(&["?"]);
+        let mut a = option(&["-a"]).value::<u64>(Value::parse).conflicts(&["?"]);
+        let mut b = option(&["-b"]).value::<u64>(Value::parse).conflicts(&["?"]);
         let mut c = non_option("<c>")
-            .value::<u64>()
+            .value::<u64>(Value::parse)
             .optional()
             .conflicts(&["?"]);
 
@@ -908,9 +2520,11 @@ mod test {
 
     #[test]
     fn check_choice_two_set() {
-        let mut a = option(&["-a"]).value::<u64>().conflicts(&["?"]);
-        let mut b = option(&["-b"]).value::<u64>().conflicts(&["?"]);
-        let mut c = non_option("<c>").value::<u64>().conflicts(&["?"]);
+        let mut a = option(&["-a"]).value::<u64>(Value::parse).conflicts(&["?"]);
+        let mut b = option(&["-b"]).value::<u64>(Value::parse).conflicts(&["?"]);
+        let mut c = non_option("<c>")
+            .value::<u64>(Value::parse)
+            .conflicts(&["?"]);
 
         b.set("-b", "47".into()).unwrap();
         c.set("47".into()).unwrap();
@@ -923,4 +2537,97 @@ mod test {
             if arg0 == "-b" && arg1 == "<c>"
         ));
     }
+
+    #[test]
+    fn check_at_least_one_none_set() {
+        let mut a = option(&["-a"]).value::<u64>(Value::parse).conflicts(&["+"]);
+        let mut b = option(&["-b", "--bb"])
+            .value::<u64>(Value::parse)
+            .conflicts(&["+"]);
+        let mut c = non_option("<c>")
+            .value::<u64>(Value::parse)
+            .conflicts(&["+"]);
+
+        let result =
+            super::check_conflicts_and_choices(&[a.as_setter(), b.as_setter()], &[c.as_setter()]);
+
+        let error = result.err().unwrap();
+        assert!(matches!(error, Error::MissingChoice { alternatives }
+            if alternatives == ["-a", "--bb", "<c>"]
+        ));
+    }
+
+    #[test]
+    fn check_at_least_one_two_set() {
+        let mut a = option(&["-a"]).value::<u64>(Value::parse).conflicts(&["+"]);
+        let mut b = option(&["-b"]).value::<u64>(Value::parse).conflicts(&["+"]);
+        let mut c = non_option("<c>")
+            .value::<u64>(Value::parse)
+            .conflicts(&["+"]);
+
+        a.set("-a", "47".into()).unwrap();
+        c.set("47".into()).unwrap();
+
+        let result =
+            super::check_conflicts_and_choices(&[a.as_setter(), b.as_setter()], &[c.as_setter()]);
+
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn check_all_or_none_one_set() {
+        let mut a = option(&["-a"]).value::<u64>(Value::parse).conflicts(&["&"]);
+        let mut b = option(&["-b"]).value::<u64>(Value::parse).conflicts(&["&"]);
+        let mut c = non_option("<c>")
+            .value::<u64>(Value::parse)
+            .conflicts(&["&"]);
+
+        a.set("-a", "47".into()).unwrap();
+        c.set("47".into()).unwrap();
+
+        let result =
+            super::check_conflicts_and_choices(&[a.as_setter(), b.as_setter()], &[c.as_setter()]);
+
+        let error = result.err().unwrap();
+        assert!(matches!(error, Error::IncompleteGroup { arg, missing }
+            if arg == "-a" && missing == "-b"
+        ));
+    }
+
+    #[test]
+    fn check_requirement_set() {
+        let mut a = option(&["-a", "--aa"])
+            .value::<u64>(Value::parse)
+            .requires(&["<c>"]);
+        let mut b = option(&["-b"]).value::<u64>(Value::parse);
+        let mut c = non_option("<c>")
+            .value::<u64>(Value::parse)
+            .requires(&["--aa", "-b"]);
+
+        a.set("-a", "47".into()).unwrap();
+        b.set("-b", "47".into()).unwrap();
+        c.set("47".into()).unwrap();
+
+        let result = super::check_requirements(&[a.as_setter(), b.as_setter()], &[c.as_setter()]);
+
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn check_requirement_missing() {
+        let mut a = option(&["-a"]).value::<u64>(Value::parse).requires(&["-b"]);
+        let mut b = option(&["-b"])
+            .value::<u64>(Value::parse)
+            .requires(&["<c>"]);
+        let mut c = non_option("<c>").value::<u64>(Value::parse).optional();
+
+        a.set("-a", "47".into()).unwrap();
+
+        let result = super::check_requirements(&[a.as_setter(), b.as_setter()], &[c.as_setter()]);
+
+        let error = result.err().unwrap();
+        assert!(matches!(error, Error::MissingRequirement { arg, requires }
+            if arg == "-a" && requires == "-b"
+        ));
+    }
 }
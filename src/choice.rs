@@ -0,0 +1,118 @@
+#![doc(hidden)]
+
+use std::fmt::Display;
+use std::fmt::Formatter;
+
+// Error returned by the `FromStr` implementation of generated choice enums.
+#[derive(Debug)]
+pub struct InvalidChoice {
+    choices: &'static [&'static str],
+    suggestion: Option<&'static str>,
+}
+
+impl InvalidChoice {
+    pub fn new(value: &str, choices: &'static [&'static str]) -> Self {
+        Self {
+            choices,
+            suggestion: suggest(value, choices),
+        }
+    }
+}
+
+impl std::error::Error for InvalidChoice {}
+
+impl Display for InvalidChoice {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let choices = self
+            .choices
+            .iter()
+            .map(|choice| format!("'{choice}'"))
+            .collect::<Vec<_>>();
+
+        match choices.split_last() {
+            Some((last, [])) => write!(f, "expected {last}")?,
+            Some((last, rest)) => write!(f, "expected {} or {last}", rest.join(", "))?,
+            None => write!(f, "no valid values")?,
+        }
+
+        match self.suggestion {
+            Some(suggestion) => write!(f, ", did you mean '{suggestion}'?"),
+            None => Ok(()),
+        }
+    }
+}
+
+// Suggest the choice closest to the value, if it's close enough to be a likely typo,
+// or if the value is a prefix of the choice.
+pub(crate) fn suggest<'a>(value: &str, choices: &[&'a str]) -> Option<&'a str> {
+    const MAX_DISTANCE: usize = 2;
+
+    choices
+        .iter()
+        .map(|choice| (distance(value, choice), *choice))
+        .filter(|(distance, choice)| {
+            (*distance <= MAX_DISTANCE && *distance < choice.chars().count())
+                || (!value.is_empty() && choice.starts_with(value))
+        })
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, choice)| choice)
+}
+
+// Levenshtein distance, case-insensitive
+fn distance(a: &str, b: &str) -> usize {
+    let a = a.to_lowercase().chars().collect::<Vec<_>>();
+    let b = b.to_lowercase().chars().collect::<Vec<_>>();
+    let mut row = (0..=b.len()).collect::<Vec<_>>();
+
+    for (i, ca) in a.iter().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+
+        for (j, cb) in b.iter().enumerate() {
+            let substitute = diagonal + usize::from(ca != cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitute.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod test {
+    use super::InvalidChoice;
+    use super::distance;
+    use super::suggest;
+
+    const CHOICES: &[&str] = &["always", "never", "auto"];
+
+    #[test]
+    fn distance_edits() {
+        assert!(distance("", "") == 0);
+        assert!(distance("auto", "auto") == 0);
+        assert!(distance("Auto", "auto") == 0);
+        assert!(distance("aut", "auto") == 1);
+        assert!(distance("alwyas", "always") == 2);
+        assert!(distance("", "never") == 5);
+    }
+
+    #[test]
+    fn suggest_close() {
+        assert!(suggest("alway", CHOICES) == Some("always"));
+        assert!(suggest("nevr", CHOICES) == Some("never"));
+        assert!(suggest("Auto", CHOICES) == Some("auto"));
+        assert!(suggest("n", CHOICES) == Some("never"));
+        assert!(suggest("sometimes", CHOICES).is_none());
+        assert!(suggest("", CHOICES).is_none());
+    }
+
+    #[test]
+    fn display() {
+        assert!(
+            InvalidChoice::new("nevr", CHOICES).to_string()
+                == "expected 'always', 'never' or 'auto', did you mean 'never'?"
+        );
+        assert!(InvalidChoice::new("x", &["yes", "no"]).to_string() == "expected 'yes' or 'no'");
+        assert!(InvalidChoice::new("x", &["on"]).to_string() == "expected 'on'");
+    }
+}
@@ -0,0 +1,488 @@
+#![doc(hidden)]
+
+use crate::Error;
+use crate::Result;
+use crate::arg::ArgNonOptionSetter;
+use crate::arg::ArgOptionSetter;
+use std::cell::OnceCell;
+use std::ffi::OsString;
+use std::fs::read_to_string;
+use std::io::ErrorKind;
+use std::iter::Peekable;
+use std::path::Path;
+use std::rc::Rc;
+use std::str::Chars;
+
+const DASHDASH: &str = "--";
+const NO: &str = "no-";
+const TRUE: &str = "true";
+const FALSE: &str = "false";
+
+enum ConfigValue {
+    Scalar(String),
+    Array(Vec<String>),
+}
+
+struct Entry {
+    section: String,
+    line: usize,
+    key: String,
+    value: ConfigValue,
+}
+
+enum Line {
+    Section(String),
+    Entry(Entry),
+}
+
+// Configuration file, and the section of it read by a (sub)command, e.g. "remote.add".
+// The top-level command reads the entries preceding the first section. The file is read
+// once, when first needed, and its entries are shared by the commands.
+pub struct Config<'a> {
+    file: &'a Path,
+    section: String,
+    entries: Rc<OnceCell<Vec<Entry>>>,
+}
+
+impl<'a> Config<'a> {
+    pub fn new(file: &'a Path) -> Self {
+        Self {
+            file,
+            section: String::new(),
+            entries: Rc::default(),
+        }
+    }
+
+    pub(crate) fn file(&self) -> &'a Path {
+        self.file
+    }
+
+    pub(crate) fn command(&self, command: &str) -> Self {
+        let section = match self.section.is_empty() {
+            true => command.to_string(),
+            false => format!("{}.{command}", self.section),
+        };
+
+        Self {
+            file: self.file,
+            section,
+            entries: Rc::clone(&self.entries),
+        }
+    }
+
+    fn entries(&self) -> Result<&[Entry]> {
+        if let Some(entries) = self.entries.get() {
+            return Ok(entries);
+        }
+
+        let entries = read(self.file)?;
+        Ok(self.entries.get_or_init(|| entries))
+    }
+}
+
+// Read the entries of all sections of a configuration file. A missing file is not an
+// error, but has no entries.
+fn read(file: &Path) -> Result<Vec<Entry>> {
+    let content = match read_to_string(file) {
+        Ok(content) => content,
+        Err(error) if error.kind() == ErrorKind::NotFound => return Ok(vec![]),
+        Err(error) => {
+            return Err(Error::ConfigFileFailed {
+                file: file.display().to_string(),
+                error,
+            });
+        }
+    };
+
+    let mut entries = vec![];
+    let mut section = String::new();
+    for (index, text) in content.lines().enumerate() {
+        let line = line(index + 1, text).map_err(|message| Error::InvalidConfig {
+            file: file.display().to_string(),
+            line: index + 1,
+            message,
+        })?;
+
+        match line {
+            Some(Line::Section(name)) => section = name,
+            Some(Line::Entry(entry)) => entries.push(Entry {
+                section: section.clone(),
+                ..entry
+            }),
+            None => {}
+        }
+    }
+
+    Ok(entries)
+}
+
+// Set options not already set from the command line or the environment, using the
+// "key = value" entries in the section of a configuration file. The global options of
+// the commands above can also be set in the section. Entries of options in a group of
+// conflicting arguments (`!` or `?`) with a member already present are not applied either.
+pub(crate) fn set_options(
+    config: &Config,
+    setters: &mut [&mut dyn ArgOptionSetter],
+    globals: &mut [&mut dyn ArgOptionSetter],
+    non_options: &[&mut dyn ArgNonOptionSetter],
+) -> Result<()> {
+    fn reborrow<'a>(setter: &'a mut &mut dyn ArgOptionSetter) -> &'a mut dyn ArgOptionSetter {
+        &mut **setter
+    }
+
+    let entries = config.entries()?;
+    let mut setters = setters
+        .iter_mut()
+        .map(reborrow)
+        .chain(globals.iter_mut().map(reborrow))
+        .collect::<Vec<_>>();
+
+    let preset = setters
+        .iter()
+        .map(|setter| setter.is_set())
+        .collect::<Vec<_>>();
+
+    let taken = setters
+        .iter()
+        .filter(|setter| setter.is_present())
+        .flat_map(|setter| setter.conflicts())
+        .chain(
+            non_options
+                .iter()
+                .filter(|setter| setter.is_set())
+                .flat_map(|setter| setter.conflicts()),
+        )
+        .filter(|conflict| conflict.starts_with(['!', '?']))
+        .map(|conflict| conflict.to_string())
+        .collect::<Vec<_>>();
+
+    for entry in entries
+        .iter()
+        .filter(|entry| entry.section == config.section)
+    {
+        set_option(entry, &mut setters, &preset, &taken).map_err(|message| {
+            Error::InvalidConfig {
+                file: config.file.display().to_string(),
+                line: entry.line,
+                message,
+            }
+        })?;
+    }
+
+    Ok(())
+}
+
+fn set_option(
+    entry: &Entry,
+    setters: &mut [&mut dyn ArgOptionSetter],
+    preset: &[bool],
+    taken: &[String],
+) -> std::result::Result<(), String> {
+    let name = format!("{DASHDASH}{}", entry.key);
+
+    let Some((index, option)) = setters
+        .iter()
+        .enumerate()
+        .filter(|(_, setter)| !setter.is_action())
+        .find_map(|(index, setter)| setter.try_match(&name).map(|option| (index, option)))
+    else {
+        return Err(format!("unknown option '{name}'"));
+    };
+
+    if preset[index]
+        || setters[index]
+            .conflicts()
+            .iter()
+            .any(|conflict| taken.iter().any(|taken| taken == conflict))
+    {
+        return Ok(());
+    }
+
+    let setter = &mut setters[index];
+    let set = |setter: &mut &mut dyn ArgOptionSetter, option, values: Vec<String>| {
+        let values = values.into_iter().map(OsString::from).collect();
+        setter
+            .set(option, values)
+            .map_err(|error| error.to_string())
+    };
+
+    match (&entry.value, setter.num_values()) {
+        (ConfigValue::Scalar(value), 0) if setter.takes_optional_value() => {
+            set(setter, option, vec![value.clone()])
+        }
+        (ConfigValue::Scalar(value), 0) if value == TRUE => set(setter, option, vec![]),
+        (ConfigValue::Scalar(value), 0) if value == FALSE => {
+            let negation = format!("{DASHDASH}{NO}{}", entry.key);
+            match setter.try_match(&negation) {
+                Some(negation) => set(setter, negation, vec![]),
+                None => Ok(()),
+            }
+        }
+        (ConfigValue::Scalar(_), 0) => Err(format!("expected '{TRUE}' or '{FALSE}'")),
+        (ConfigValue::Scalar(value), 1) => set(setter, option, vec![value.clone()]),
+        (ConfigValue::Array(values), 1) => {
+            for value in values {
+                set(setter, option, vec![value.clone()])?;
+            }
+            Ok(())
+        }
+        (ConfigValue::Array(values), n) if values.len() == n => set(setter, option, values.clone()),
+        (_, n) => Err(format!("expected array of {n} values")),
+    }
+}
+
+fn line(line: usize, text: &str) -> std::result::Result<Option<Line>, String> {
+    let mut parser = Parser {
+        chars: text.chars().peekable(),
+    };
+
+    parser.skip_whitespace();
+
+    match parser.chars.peek() {
+        None | Some('#') | Some(';') => return Ok(None),
+        Some('[') => return parser.section().map(|name| Some(Line::Section(name))),
+        _ => {}
+    }
+
+    let key = parser.key();
+    if key.is_empty() {
+        return Err(String::from("expected key"));
+    }
+
+    parser.skip_whitespace();
+    if parser.chars.next() != Some('=') {
+        return Err(String::from("expected '='"));
+    }
+
+    parser.skip_whitespace();
+    let value = match parser.chars.peek() {
+        Some('[') => ConfigValue::Array(parser.array()?),
+        _ => ConfigValue::Scalar(parser.scalar(false)?),
+    };
+
+    parser.skip_whitespace();
+    match parser.chars.peek() {
+        None | Some('#') | Some(';') => Ok(Some(Line::Entry(Entry {
+            section: String::new(),
+            line,
+            key,
+            value,
+        }))),
+        Some(_) => Err(String::from("unexpected characters after value")),
+    }
+}
+
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+}
+
+impl Parser<'_> {
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|c| c.is_whitespace()).is_some() {}
+    }
+
+    fn key(&mut self) -> String {
+        let mut key = String::new();
+        while let Some(c) = self
+            .chars
+            .next_if(|c| c.is_alphanumeric() || *c == '_' || *c == '-')
+        {
+            key.push(c);
+        }
+
+        // Long option and command names are lowercase, and `_` can be used in place of `-`
+        key.replace('_', "-").to_lowercase()
+    }
+
+    // Section names are dot-separated command names, e.g. "[remote.add]"
+    fn section(&mut self) -> std::result::Result<String, String> {
+        let mut names = vec![];
+        self.chars.next();
+
+        loop {
+            self.skip_whitespace();
+            let name = self.key();
+            if name.is_empty() {
+                return Err(String::from("expected section name"));
+            }
+            names.push(name);
+
+            self.skip_whitespace();
+            match self.chars.next() {
+                Some('.') => continue,
+                Some(']') => break,
+                _ => return Err(String::from("expected ']'")),
+            }
+        }
+
+        self.skip_whitespace();
+        match self.chars.peek() {
+            None | Some('#') | Some(';') => Ok(names.join(".")),
+            Some(_) => Err(String::from("unexpected characters after section")),
+        }
+    }
+
+    fn scalar(&mut self, in_array: bool) -> std::result::Result<String, String> {
+        match self.chars.peek() {
+            Some('"') => self.basic_string(),
+            Some('\'') => self.literal_string(),
+            _ => {
+                let mut value = String::new();
+                while let Some(c) = self
+                    .chars
+                    .next_if(|c| !(matches!(c, '#' | ';') || in_array && matches!(c, ',' | ']')))
+                {
+                    value.push(c);
+                }
+                Ok(value.trim_end().to_string())
+            }
+        }
+    }
+
+    fn basic_string(&mut self) -> std::result::Result<String, String> {
+        let mut value = String::new();
+        self.chars.next();
+
+        while let Some(c) = self.chars.next() {
+            match c {
+                '"' => return Ok(value),
+                '\\' => match self.chars.next() {
+                    Some('n') => value.push('\n'),
+                    Some('t') => value.push('\t'),
+                    Some(c @ ('"' | '\\')) => value.push(c),
+                    _ => return Err(String::from("invalid escape sequence")),
+                },
+                c => value.push(c),
+            }
+        }
+
+        Err(String::from("unterminated string"))
+    }
+
+    fn literal_string(&mut self) -> std::result::Result<String, String> {
+        let mut value = String::new();
+        self.chars.next();
+
+        for c in self.chars.by_ref() {
+            match c {
+                '\'' => return Ok(value),
+                c => value.push(c),
+            }
+        }
+
+        Err(String::from("unterminated string"))
+    }
+
+    fn array(&mut self) -> std::result::Result<Vec<String>, String> {
+        let mut values = vec![];
+        self.chars.next();
+
+        loop {
+            self.skip_whitespace();
+            if self.chars.next_if_eq(&']').is_some() {
+                return Ok(values);
+            }
+
+            values.push(self.scalar(true)?);
+
+            self.skip_whitespace();
+            match self.chars.next() {
+                Some(',') => continue,
+                Some(']') => return Ok(values),
+                _ => return Err(String::from("unterminated array")),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Config;
+    use super::ConfigValue;
+    use super::Line;
+    use super::line;
+    use std::path::Path;
+
+    macro_rules! assert_scalar {
+        ($line:literal, $key:literal, $value:literal) => {
+            assert!(matches!(line(1, $line),
+                Ok(Some(Line::Entry(entry))) if entry.key == $key
+                    && matches!(&entry.value, ConfigValue::Scalar(value) if value == $value)
+            ));
+        };
+    }
+
+    macro_rules! assert_array {
+        ($line:literal, $key:literal, $values:expr) => {
+            assert!(matches!(line(1, $line),
+                Ok(Some(Line::Entry(entry))) if entry.key == $key
+                    && matches!(&entry.value, ConfigValue::Array(values) if *values == $values)
+            ));
+        };
+    }
+
+    macro_rules! assert_section {
+        ($line:literal, $name:literal) => {
+            assert!(matches!(line(1, $line), Ok(Some(Line::Section(name))) if name == $name));
+        };
+    }
+
+    macro_rules! assert_error {
+        ($line:literal, $error:literal) => {
+            assert!(matches!(line(1, $line), Err(error) if error == $error));
+        };
+    }
+
+    #[test]
+    fn comments() {
+        assert!(matches!(line(1, ""), Ok(None)));
+        assert!(matches!(line(1, "   "), Ok(None)));
+        assert!(matches!(line(1, "# comment"), Ok(None)));
+        assert!(matches!(line(1, "  ; comment"), Ok(None)));
+    }
+
+    #[test]
+    fn scalars() {
+        assert_scalar!("jobs = 4", "jobs", "4");
+        assert_scalar!("  log_level=debug  # comment", "log-level", "debug");
+        assert_scalar!("Dry_Run = true", "dry-run", "true");
+        assert_scalar!(r#"name = "a \"b\" # c""#, "name", "a \"b\" # c");
+        assert_scalar!(r"path = 'C:\dir' ; comment", "path", r"C:\dir");
+        assert_scalar!("empty =", "empty", "");
+    }
+
+    #[test]
+    fn arrays() {
+        assert_array!("features = []", "features", [""; 0]);
+        assert_array!("features = [a, 'b', \"c\"]", "features", ["a", "b", "c"]);
+        assert_array!("point = [ 1 , 2 ] # comment", "point", ["1", "2"]);
+    }
+
+    #[test]
+    fn sections() {
+        assert_section!("[add]", "add");
+        assert_section!("  [ Remote . add_x ]  # comment", "remote.add-x");
+
+        let config = Config::new(Path::new("test.conf"));
+        assert_eq!(config.section, "");
+        assert_eq!(config.command("remote").section, "remote");
+        assert_eq!(
+            config.command("remote").command("add").section,
+            "remote.add"
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert_error!("[]", "expected section name");
+        assert_error!("[remote.]", "expected section name");
+        assert_error!("[remote", "expected ']'");
+        assert_error!("[remote] x", "unexpected characters after section");
+        assert_error!("= value", "expected key");
+        assert_error!("key value", "expected '='");
+        assert_error!("key = \"value", "unterminated string");
+        assert_error!("key = [a, b", "unterminated array");
+        assert_error!("key = \"a\" b", "unexpected characters after value");
+        assert_error!(r#"key = "\x""#, "invalid escape sequence");
+    }
+}
@@ -4,28 +4,45 @@ use std::fmt::Formatter;
 use std::fmt::Result;
 
 /// Errors returned by argument parser.
+#[non_exhaustive]
 pub enum Error {
     /// Invalid option.
     InvalidOption {
         /// The option.
         option: String,
+        /// The declared option closest to the option, if any.
+        suggestion: Option<String>,
+    },
+    /// Option appearing after a non-option argument.
+    MisplacedOption {
+        /// The option.
+        option: String,
     },
     /// Invalid argument.
     InvalidArgument {
         /// The argument.
         arg: String,
     },
+    /// Abbreviated long option or command matching more than one candidate.
+    AmbiguousArgument {
+        /// The argument.
+        arg: String,
+        /// The candidates.
+        candidates: Vec<String>,
+    },
     /// Invalid command.
     InvalidCommand {
         /// The argument.
         arg: String,
+        /// The declared command closest to the argument, if any.
+        suggestion: Option<String>,
     },
     /// Missing argument.
     MissingArgument {
         /// The argument.
         arg: String,
     },
-    /// Missing choice.
+    /// Missing choice, or missing argument of a group requiring at least one.
     MissingChoice {
         /// The alternatives.
         alternatives: Vec<String>,
@@ -34,6 +51,8 @@ pub enum Error {
     MissingValue {
         /// The option.
         option: String,
+        /// The missing value, if the option takes more than one value.
+        value: Option<String>,
     },
     /// Unexpected value for option.
     UnexpectedValue {
@@ -49,13 +68,103 @@ pub enum Error {
         /// Second argument.
         arg1: String,
     },
+    /// Missing argument of a group requiring all or none.
+    IncompleteGroup {
+        /// The argument present.
+        arg: String,
+        /// The argument missing.
+        missing: String,
+    },
+    /// Missing argument required by another argument.
+    MissingRequirement {
+        /// The argument.
+        arg: String,
+        /// The required argument.
+        requires: String,
+    },
+    /// Argument not valid unicode, for a value that requires it.
+    InvalidUnicode {
+        /// The argument, lossily converted to unicode.
+        arg: String,
+    },
     /// Failed to parse value.
     ParsingFailed {
         /// The value.
         value: String,
+        /// The error returned by [`str::parse()`] or the parser function.
+        error: Box<dyn std::error::Error>,
+    },
+    /// Value rejected by validation.
+    ValidationFailed {
+        /// The argument.
+        arg: String,
+        /// The value, lossily converted to unicode.
+        value: String,
+        /// The reason the value was rejected.
+        message: String,
+    },
+    /// Invalid default value of option, i.e. a bug in the arguments declaration.
+    InvalidDefault {
+        /// The option.
+        option: String,
+        /// The default value.
+        value: String,
+        /// The reason the default value was rejected.
+        message: String,
+    },
+    /// Key-value pair without a key or without a `=` separating the key and the value.
+    MalformedPair {
+        /// The argument.
+        arg: String,
+        /// The value, lossily converted to unicode.
+        value: String,
+    },
+    /// Key appearing more than once.
+    DuplicateKey {
+        /// The argument.
+        arg: String,
+        /// The key, lossily converted to unicode.
+        key: String,
+    },
+    /// Failed to parse value of environment variable.
+    EnvParsingFailed {
+        /// The environment variable.
+        env: String,
+        /// The value, lossily converted to unicode.
+        value: String,
         /// The error returned by [`str::parse()`].
         error: Box<dyn std::error::Error>,
     },
+    /// Failed to read configuration file.
+    ConfigFileFailed {
+        /// The configuration file.
+        file: String,
+        /// The error returned when reading the configuration file.
+        error: std::io::Error,
+    },
+    /// Invalid entry in configuration file.
+    InvalidConfig {
+        /// The configuration file.
+        file: String,
+        /// The line number.
+        line: usize,
+        /// The reason the entry is invalid.
+        message: String,
+    },
+    /// Failed to read response file.
+    ResponseFileFailed {
+        /// The response file.
+        file: String,
+        /// The error returned when reading the response file.
+        error: std::io::Error,
+    },
+    /// Failed to run external command.
+    ExternalCommandFailed {
+        /// The external command.
+        command: String,
+        /// The error returned when running the external command.
+        error: std::io::Error,
+    },
     /// Version information requested. Returned if option `--version` was used.
     Version {
         /// The automatically generated version message.
@@ -71,14 +180,30 @@ pub enum Error {
 impl Display for Error {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
         match self {
-            Self::InvalidOption { option } => {
-                write!(f, "invalid option '{option}'")
+            Self::InvalidOption { option, suggestion } => {
+                write!(f, "invalid option '{option}'")?;
+                write_suggestion(f, suggestion)
+            }
+            Self::MisplacedOption { option } => {
+                write!(
+                    f,
+                    "misplaced option '{option}', options must come before non-option arguments"
+                )
             }
             Self::InvalidArgument { arg } => {
                 write!(f, "invalid argument '{arg}'")
             }
-            Self::InvalidCommand { arg } => {
-                write!(f, "invalid command '{arg}'")
+            Self::InvalidCommand { arg, suggestion } => {
+                write!(f, "invalid command '{arg}'")?;
+                write_suggestion(f, suggestion)
+            }
+            Self::AmbiguousArgument { arg, candidates } => {
+                let candidates = candidates
+                    .iter()
+                    .map(|candidate| format!("'{candidate}'"))
+                    .collect::<Vec<_>>()
+                    .join(" or ");
+                write!(f, "ambiguous argument '{arg}', could be {candidates}")
             }
             Self::MissingArgument { arg } => {
                 write!(f, "missing argument '{arg}'")
@@ -91,18 +216,84 @@ impl Display for Error {
                     .join(" or ");
                 write!(f, "missing argument {alts}")
             }
-            Self::MissingValue { option } => {
+            Self::MissingValue {
+                option,
+                value: None,
+            } => {
                 write!(f, "missing value for option '{option}'")
             }
+            Self::MissingValue {
+                option,
+                value: Some(value),
+            } => {
+                write!(f, "missing value '{value}' for option '{option}'")
+            }
             Self::UnexpectedValue { option, value } => {
                 write!(f, "unexpected value for option '{option}': {value}")
             }
             Self::ConflictingArguments { arg0, arg1 } => {
                 write!(f, "conflicting arguments '{arg0}' and '{arg1}'")
             }
+            Self::IncompleteGroup { arg, missing } => {
+                write!(f, "argument '{arg}' must be used together with '{missing}'")
+            }
+            Self::MissingRequirement { arg, requires } => {
+                write!(f, "argument '{arg}' requires '{requires}'")
+            }
+            Self::InvalidUnicode { arg } => {
+                write!(f, "invalid unicode in argument '{arg}'")
+            }
             Self::ParsingFailed { value, error } => {
                 write!(f, "cannot parse argument '{value}': {error}")
             }
+            Self::ValidationFailed {
+                arg,
+                value,
+                message,
+            } => {
+                write!(f, "invalid value '{value}' for '{arg}': {message}")
+            }
+            Self::InvalidDefault {
+                option,
+                value,
+                message,
+            } => {
+                write!(
+                    f,
+                    "invalid default value '{value}' for option '{option}': {message}"
+                )
+            }
+            Self::MalformedPair { arg, value } => {
+                write!(
+                    f,
+                    "malformed key-value pair '{value}' for '{arg}', expected 'key=value'"
+                )
+            }
+            Self::DuplicateKey { arg, key } => {
+                write!(f, "duplicate key '{key}' for '{arg}'")
+            }
+            Self::EnvParsingFailed { env, value, error } => {
+                write!(
+                    f,
+                    "cannot parse environment variable {env}='{value}': {error}"
+                )
+            }
+            Self::ConfigFileFailed { file, error } => {
+                write!(f, "cannot read configuration file '{file}': {error}")
+            }
+            Self::InvalidConfig {
+                file,
+                line,
+                message,
+            } => {
+                write!(f, "{file}:{line}: {message}")
+            }
+            Self::ResponseFileFailed { file, error } => {
+                write!(f, "cannot read response file '{file}': {error}")
+            }
+            Self::ExternalCommandFailed { command, error } => {
+                write!(f, "cannot run external command '{command}': {error}")
+            }
             Self::Version { message } => {
                 write!(f, "{message}")
             }
@@ -113,6 +304,13 @@ impl Display for Error {
     }
 }
 
+fn write_suggestion(f: &mut Formatter<'_>, suggestion: &Option<String>) -> Result {
+    match suggestion {
+        Some(suggestion) => write!(f, ", did you mean '{suggestion}'?"),
+        None => Ok(()),
+    }
+}
+
 impl Debug for Error {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
         Display::fmt(self, f)
@@ -3,6 +3,11 @@
 use crate::Args;
 use crate::Error;
 use crate::Result;
+use crate::os;
+use crate::response::Expander;
+use std::collections::VecDeque;
+use std::ffi::OsStr;
+use std::ffi::OsString;
 use std::mem::replace;
 
 enum State {
@@ -10,10 +15,10 @@ enum State {
     Any,
 
     // Next argument is a short-option (in the middle of "-abc")
-    Short { remaining: String },
+    Short { remaining: OsString },
 
     // Next argument is an option value (in the middle of "-o=value" or "--option=value")
-    Value { value: String },
+    Value { value: OsString },
 
     // Next argument is a non-option
     None,
@@ -22,13 +27,21 @@ enum State {
 pub struct Lexer {
     state: State,
     option: String,
+    arg: String,
     args: Args,
+    permute: bool,
+    command: Option<fn(&str) -> bool>,
+    command_at: Option<usize>,
+    skipped: Vec<OsString>,
+    dashdash: Option<usize>,
+    negative_numbers: bool,
+    response_files: Option<Expander>,
+    verbatim_at: Option<usize>,
 }
 
 const DASHDASH: &str = "--";
 const DASH: &str = "-";
-const EQUALS: &str = "=";
-const SHORT_LENGTH: usize = 2;
+const EQUALS: u8 = b'=';
 
 impl Lexer {
     #[inline]
@@ -36,36 +49,109 @@ impl Lexer {
         Self {
             state: State::Any,
             option: String::new(),
+            arg: String::new(),
             args,
+            permute: false,
+            command: None,
+            command_at: None,
+            skipped: vec![],
+            dashdash: None,
+            negative_numbers: false,
+            response_files: None,
+            verbatim_at: None,
         }
     }
 
+    // Expand "@file" arguments preceding the first "--", except option values, see
+    // verbatim_at()
     #[inline]
-    fn next_short(&mut self, mut short: String) -> &'_ str {
-        if let Some((remaining_at, _)) = short.char_indices().nth(SHORT_LENGTH) {
-            let mut remaining = short.split_off(remaining_at);
-            if remaining.starts_with(EQUALS) {
-                remaining.remove(0);
-                self.state = State::Value { value: remaining };
+    pub(crate) fn response_files(&mut self) {
+        self.response_files = Some(Expander::default());
+    }
+
+    // Non-options from this position on, i.e. a command or a raw non-option, are taken as
+    // is, and "@file" arguments among them are not expanded
+    #[inline]
+    pub(crate) fn verbatim_at(&mut self, at: usize) {
+        self.verbatim_at = Some(at);
+    }
+
+    // Treat numeric arguments, such as "-5" or "-3.14", as non-options
+    #[inline]
+    pub(crate) fn negative_numbers(&mut self) {
+        self.negative_numbers = true;
+    }
+
+    #[inline]
+    fn is_negative_number(&self, arg: &OsStr) -> bool {
+        let Some(arg) = arg.to_str() else {
+            return false;
+        };
+
+        self.negative_numbers
+            && arg
+                .strip_prefix(DASH)
+                .and_then(|arg| arg.strip_prefix('.').or(Some(arg)))
+                .is_some_and(|arg| arg.starts_with(|c: char| c.is_ascii_digit()))
+            && arg.parse::<f64>().is_ok()
+    }
+
+    // Allow options to appear after non-options. Non-options are skipped over and
+    // set aside until a "--" or a command is seen.
+    #[inline]
+    pub(crate) fn permute(&mut self, command: Option<fn(&str) -> bool>) {
+        self.permute = true;
+        self.command = command;
+    }
+
+    // Any argument at the position of a command with external commands is a command
+    #[inline]
+    pub(crate) fn command_at(&mut self, at: usize) {
+        self.command_at = Some(at);
+    }
+
+    #[inline]
+    fn skip(&self, arg: &OsStr) -> bool {
+        self.permute
+            && !self
+                .command
+                .is_some_and(|is_command| arg.to_str().is_some_and(is_command))
+            && self.command_at != Some(self.skipped.len())
+    }
+
+    #[inline]
+    fn next_short(&mut self, mut short: OsString) -> &'_ str {
+        let remaining_at = DASH.len() + os::char_len(&short.as_encoded_bytes()[DASH.len()..]);
+
+        if remaining_at < short.len() {
+            let mut remaining = os::split_off(&mut short, remaining_at);
+            if remaining.as_encoded_bytes()[0] == EQUALS {
+                self.state = State::Value {
+                    value: os::split_off(&mut remaining, 1),
+                };
             } else {
-                remaining.insert_str(0, DASH);
-                self.state = State::Short { remaining };
+                let mut dash_remaining = OsString::from(DASH);
+                dash_remaining.push(remaining);
+                self.state = State::Short {
+                    remaining: dash_remaining,
+                };
             }
         }
 
-        self.option = short;
+        self.option = short.to_string_lossy().into_owned();
         &self.option
     }
 
     #[inline]
-    fn next_long(&mut self, mut long: String) -> &'_ str {
-        if let Some(equals) = long.find(EQUALS) {
-            let mut value = long.split_off(equals);
-            value.remove(0);
-            self.state = State::Value { value };
+    fn next_long(&mut self, mut long: OsString) -> &'_ str {
+        if let Some(equals) = long.as_encoded_bytes().iter().position(|&b| b == EQUALS) {
+            let mut value = os::split_off(&mut long, equals);
+            self.state = State::Value {
+                value: os::split_off(&mut value, 1),
+            };
         }
 
-        self.option = long;
+        self.option = long.to_string_lossy().into_owned();
         &self.option
     }
 
@@ -79,20 +165,37 @@ impl Lexer {
     pub(crate) fn next_option(&mut self) -> Result<Option<&'_ str>> {
         match replace(&mut self.state, State::Any) {
             State::Any => {
-                if let Some(arg) = self.args.peek() {
-                    if let Some(arg) = arg.strip_prefix(DASHDASH) {
-                        if arg.is_empty() {
+                while let Some(arg) = self.args.peek() {
+                    let bytes = arg.as_encoded_bytes();
+                    if let Some(expander) = &mut self.response_files
+                        && Expander::is_response_file(arg)
+                    {
+                        let arg = self.args.take();
+                        expander.expand(arg, &mut self.args)?;
+                        continue;
+                    } else if bytes.starts_with(DASHDASH.as_bytes()) {
+                        if bytes.len() == DASHDASH.len() {
                             let _ = self.args.take();
+                            self.dashdash = Some(self.skipped.len());
                         } else {
                             let long = self.args.take();
+                            self.arg = long.to_string_lossy().into_owned();
                             return Ok(Some(self.next_long(long)));
                         }
-                    } else if let Some(arg) = arg.strip_prefix(DASH)
-                        && !arg.is_empty()
+                    } else if bytes.starts_with(DASH.as_bytes())
+                        && bytes.len() > DASH.len()
+                        && !self.is_negative_number(arg)
                     {
                         let short = self.args.take();
+                        self.arg = short.to_string_lossy().into_owned();
                         return Ok(Some(self.next_short(short)));
+                    } else if self.skip(arg) {
+                        let non_option = self.args.take();
+                        self.skipped.push(non_option);
+                        continue;
                     }
+
+                    break;
                 }
 
                 self.next_none();
@@ -101,34 +204,50 @@ impl Lexer {
             State::Short { remaining } => Ok(Some(self.next_short(remaining))),
             State::Value { value } => Err(Error::UnexpectedValue {
                 option: self.option.clone(),
-                value,
+                value: value.to_string_lossy().into_owned(),
             }),
             State::None => panic!(),
         }
     }
 
+    // The whole argument of the last option, e.g. "-abc" for "-b"
+    #[inline]
+    pub(crate) fn arg(&self) -> &'_ str {
+        &self.arg
+    }
+
     #[inline]
-    pub(crate) fn next_value(&mut self) -> Result<String> {
+    pub(crate) fn next_value(&mut self) -> Result<OsString> {
         match replace(&mut self.state, State::Any) {
             State::Any => match self.args.pop() {
                 Some(value) => {
                     assert!(!self.option.is_empty());
-                    self.option.clear();
                     Ok(value)
                 }
                 _ => Err(Error::MissingValue {
                     option: self.option.clone(),
+                    value: None,
                 }),
             },
-            State::Short { mut remaining } => {
-                remaining.remove(0);
-                Ok(remaining)
-            }
+            State::Short { mut remaining } => Ok(os::split_off(&mut remaining, DASH.len())),
             State::Value { value } => Ok(value),
             State::None => panic!(),
         }
     }
 
+    #[inline]
+    pub(crate) fn next_optional_value(&mut self) -> Option<OsString> {
+        match replace(&mut self.state, State::Any) {
+            State::Any => {
+                self.option.clear();
+                None
+            }
+            State::Short { mut remaining } => Some(os::split_off(&mut remaining, DASH.len())),
+            State::Value { value } => Some(value),
+            State::None => panic!(),
+        }
+    }
+
     #[inline]
     pub(crate) fn non_options(&mut self) -> Result<&mut Args> {
         if let State::Any = self.state
@@ -137,11 +256,81 @@ impl Lexer {
             panic!();
         };
 
+        // Non-options following the options are expanded up to the first "--", or up to
+        // the position of a command or a raw non-option. When permuting, the non-options
+        // preceding a command have already been expanded.
+        if let Some(mut expander) = self.response_files.take()
+            && self.dashdash.is_none()
+            && !self.permute
+        {
+            let mut args = VecDeque::new();
+            while self.verbatim_at.is_none_or(|at| args.len() < at)
+                && let Some(arg) = self.args.pop()
+            {
+                if Expander::is_response_file(&arg) {
+                    expander.expand(arg, &mut self.args)?;
+                    continue;
+                }
+
+                let dashdash = arg == DASHDASH;
+                args.push_back(arg);
+                if dashdash {
+                    break;
+                }
+            }
+
+            args.append(&mut self.args.0);
+            self.args.0 = args;
+        }
+
+        for non_option in self.skipped.drain(..).rev() {
+            self.args.push_front(non_option);
+        }
+
         match self.state {
             State::None => Ok(&mut self.args),
             _ => panic!(),
         }
     }
+
+    // The non-options preceding the first "--", see trailing()
+    #[inline]
+    pub(crate) fn before_dashdash(&mut self) -> Result<Vec<String>> {
+        let dashdash = self.dashdash;
+        let args = self.non_options()?;
+
+        let at = dashdash
+            .or_else(|| args.0.iter().position(|arg| arg == DASHDASH))
+            .unwrap_or(args.0.len());
+
+        Ok(args
+            .0
+            .iter()
+            .take(at)
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect())
+    }
+
+    // Split off the non-options following the first "--", which is either the "--" that
+    // ended the options, or a "--" among the non-options
+    #[inline]
+    pub(crate) fn trailing(&mut self) -> Result<Args> {
+        let dashdash = self.dashdash;
+        let args = self.non_options()?;
+
+        let at = match dashdash {
+            Some(at) => at,
+            None => match args.0.iter().position(|arg| arg == DASHDASH) {
+                Some(at) => {
+                    args.0.remove(at);
+                    at
+                }
+                None => args.0.len(),
+            },
+        };
+
+        Ok(Args(args.0.split_off(at), None, None))
+    }
 }
 
 #[cfg(test)]
@@ -301,6 +490,29 @@ mod test {
         assert_none!(l);
     }
 
+    #[test]
+    fn optional_value() {
+        let mut l = Lexer::new(Args::from(["-s", "--long", "VALUE"]));
+        assert_option!(l, "-s");
+        assert!(l.next_optional_value().is_none());
+        assert_option!(l, "--long");
+        assert!(l.next_optional_value().is_none());
+        assert_none!(l);
+        assert_non_options!(l, ["VALUE"]);
+    }
+
+    #[test]
+    fn optional_value_attached() {
+        let mut l = Lexer::new(Args::from(["-sVALUE", "-t=VALUE", "--long=VALUE"]));
+        assert_option!(l, "-s");
+        assert!(matches!(l.next_optional_value(), Some(value) if value == "VALUE"));
+        assert_option!(l, "-t");
+        assert!(matches!(l.next_optional_value(), Some(value) if value == "VALUE"));
+        assert_option!(l, "--long");
+        assert!(matches!(l.next_optional_value(), Some(value) if value == "VALUE"));
+        assert_none!(l);
+    }
+
     #[test]
     fn non_options() {
         let mut l = Lexer::new(Args::from(["abc", "-abc", "--abc"]));
@@ -329,6 +541,54 @@ mod test {
         assert_non_options!(l, ["abc", "--", "-abc", "--abc"]);
     }
 
+    #[test]
+    fn before_dashdash() {
+        let mut l = Lexer::new(Args::from(["abc", "--", "-def", "--", "ghi"]));
+        assert_none!(l);
+        assert!(l.before_dashdash().unwrap() == ["abc"]);
+        assert_non_options!(l, ["abc", "--", "-def", "--", "ghi"]);
+
+        let mut l = Lexer::new(Args::from(["--", "abc", "--", "-def"]));
+        assert_none!(l);
+        assert!(l.before_dashdash().unwrap().is_empty());
+        assert_non_options!(l, ["abc", "--", "-def"]);
+    }
+
+    #[test]
+    fn trailing() {
+        let mut l = Lexer::new(Args::from(["abc", "--", "-def", "--", "ghi"]));
+        assert_none!(l);
+        assert!(l.trailing().unwrap().0 == Args::from(["-def", "--", "ghi"]).0);
+        assert_non_options!(l, ["abc"]);
+    }
+
+    #[test]
+    fn trailing_after_options() {
+        let mut l = Lexer::new(Args::from(["-s", "--", "abc", "--", "def"]));
+        assert_option!(l, "-s");
+        assert_none!(l);
+        assert!(l.trailing().unwrap().0 == Args::from(["abc", "--", "def"]).0);
+        assert_non_options!(l, [""; 0]);
+    }
+
+    #[test]
+    fn trailing_permute() {
+        let mut l = Lexer::new(Args::from(["abc", "-s", "def", "--", "-t", "ghi"]));
+        l.permute(None);
+        assert_option!(l, "-s");
+        assert_none!(l);
+        assert!(l.trailing().unwrap().0 == Args::from(["-t", "ghi"]).0);
+        assert_non_options!(l, ["abc", "def"]);
+    }
+
+    #[test]
+    fn trailing_none() {
+        let mut l = Lexer::new(Args::from(["abc", "-def"]));
+        assert_none!(l);
+        assert!(l.trailing().unwrap().0.is_empty());
+        assert_non_options!(l, ["abc", "-def"]);
+    }
+
     #[test]
     fn mixed() {
         let mut l = Lexer::new(Args::from([
@@ -390,13 +650,105 @@ mod test {
         );
     }
 
+    #[test]
+    fn permute() {
+        let mut l = Lexer::new(Args::from(["abc", "-s", "def", "--long", "VALUE", "ghi"]));
+        l.permute(None);
+        assert_option!(l, "-s");
+        assert_option!(l, "--long");
+        assert_value!(l, "VALUE");
+        assert_none!(l);
+        assert_non_options!(l, ["abc", "def", "ghi"]);
+    }
+
+    #[test]
+    fn permute_dashdash() {
+        let mut l = Lexer::new(Args::from(["abc", "-s", "--", "-def", "--ghi"]));
+        l.permute(None);
+        assert_option!(l, "-s");
+        assert_none!(l);
+        assert_non_options!(l, ["abc", "-def", "--ghi"]);
+    }
+
+    #[test]
+    fn permute_command() {
+        let mut l = Lexer::new(Args::from(["abc", "-s", "cmd", "-t", "def"]));
+        l.permute(Some(|arg| arg == "cmd"));
+        assert_option!(l, "-s");
+        assert_none!(l);
+        assert_non_options!(l, ["abc", "cmd", "-t", "def"]);
+    }
+
+    #[test]
+    fn permute_command_at() {
+        let mut l = Lexer::new(Args::from(["abc", "-s", "ext", "-t", "def"]));
+        l.permute(Some(|arg| arg == "cmd"));
+        l.command_at(1);
+        assert_option!(l, "-s");
+        assert_none!(l);
+        assert_non_options!(l, ["abc", "ext", "-t", "def"]);
+    }
+
+    #[test]
+    fn negative_numbers() {
+        let mut l = Lexer::new(Args::from(["-s", "-5", "-3.14", "-.5", "-1e3"]));
+        l.negative_numbers();
+        assert_option!(l, "-s");
+        assert_value!(l, "-5");
+        assert_none!(l);
+        assert_non_options!(l, ["-3.14", "-.5", "-1e3"]);
+    }
+
+    #[test]
+    fn negative_numbers_not_numeric() {
+        let mut l = Lexer::new(Args::from(["-5x", "-inf", "-.x"]));
+        l.negative_numbers();
+        assert_option!(l, "-5");
+        assert_option!(l, "-x");
+        assert_option!(l, "-i");
+        assert_option!(l, "-n");
+        assert_option!(l, "-f");
+        assert_option!(l, "-.");
+        assert_option!(l, "-x");
+        assert_none!(l);
+    }
+
+    #[test]
+    fn negative_numbers_disabled() {
+        let mut l = Lexer::new(Args::from(["-5"]));
+        assert_option!(l, "-5");
+        assert_none!(l);
+    }
+
+    #[test]
+    fn negative_numbers_permute() {
+        let mut l = Lexer::new(Args::from(["abc", "-5", "-s", "-3.14"]));
+        l.permute(None);
+        l.negative_numbers();
+        assert_option!(l, "-s");
+        assert_none!(l);
+        assert_non_options!(l, ["abc", "-5", "-3.14"]);
+    }
+
+    #[test]
+    fn arg() {
+        let mut l = Lexer::new(Args::from(["-abc", "--long=VALUE"]));
+        assert_option!(l, "-a");
+        assert!(l.arg() == "-abc");
+        assert_option!(l, "-b");
+        assert!(l.arg() == "-abc");
+        assert_option!(l, "-c");
+        assert_option!(l, "--long");
+        assert!(l.arg() == "--long=VALUE");
+    }
+
     #[test]
     fn error_missing_value() {
         let mut l = Lexer::new(Args::from(["-s"]));
         assert_option!(l, "-s");
         assert!(matches!(
             l.next_value(),
-            Err(Error::MissingValue { option }) if option == "-s"
+            Err(Error::MissingValue { option, value: None }) if option == "-s"
         ));
     }
 
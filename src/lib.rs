@@ -6,7 +6,8 @@
 //!
 //! * Straightforward declaration of arguments with proc-macro.
 //! * Supports [POSIX] / [GNU] argument syntax conventions.
-//! * Supports arguments of any type that implements [`FromStr`](core::str::FromStr) + [`Debug`](core::fmt::Debug).
+//! * Supports arguments of any type that implements [`FromStr`](core::str::FromStr) + [`Debug`](core::fmt::Debug),
+//!   or that is parsed using a custom parser function.
 //! * Supports (sub)commands, with aliases.
 //! * Supports declaration of conflicting arguments.
 //! * Supports automatic `--version` and `--help` handling, with possibility to opt-out.
@@ -143,6 +144,79 @@
 //! }
 //! ```
 //!
+//! A command can also name the arguments `struct` of the (sub)command, following the command
+//! names and a `:`, e.g. `add: AddArgs`, in which case the command enum variant holds the
+//! parsed `struct` instead of an [`Args`]. The whole command line is then parsed at once, and
+//! errors (or `--help`) in the (sub)command are reported by the same `from_env()` call.
+//! Commands with and without a `struct` can be mixed.
+//!
+//! The arguments of a (sub)command can also be declared in place, enclosed by braces after
+//! the command, which generates a `struct` named after the command, e.g. `RemoteArgs` for
+//! `remote`, unless a `struct` name is given, e.g. `remote: Remote { ... }`. Commands
+//! declared directly within the braces make up a required `<command>` argument of the
+//! (sub)command, with a command enum named after the command, e.g. `RemoteCommand`, and
+//! nested commands generate `struct`s named after the path of commands leading to them, e.g.
+//! `RemoteAddArgs` for `remote add`. Commands can be nested to any depth, and all of them are
+//! checked by the compiler.
+//!
+//! Commands can also include `_`, which adds an `External(String, Args)` variant to the
+//! command enum, holding any command not declared, e.g. for dispatching to plugins the way
+//! `git` and `cargo` do. [`Args::run_external()`] runs the program `{program}-{command}`,
+//! e.g. `tool-foo` for `tool foo`, found in `PATH`, where `{program}` is the file stem of
+//! the program.
+//!
+//! An option declared with `global` is also accepted after any of the nested (sub)commands
+//! declared in the same `immargs!`, or after a (sub)command naming its arguments `struct`,
+//! and is set in the `struct` where it is declared. The help message of a (sub)command lists
+//! the global options under `global options:`. An option of the (sub)command itself takes
+//! precedence over a global option with the same name.
+//!
+//! ```no_run
+//! # use immargs::immargs;
+//! # immargs! { AddArgs, -a --all, }
+//! # immargs! { RemoveArgs, <file>... String, }
+//! immargs! {
+//!     MainArgs,
+//!     <command> Command {
+//!         add: AddArgs       "add file(s)",             // Parsed into Command::Add(AddArgs)
+//!         remove rm: RemoveArgs "remove file(s)",       // Parsed into Command::Remove(RemoveArgs)
+//!         commit co c        "commit changes",          // Command::Commit(Args), as before
+//!     }
+//! }
+//!
+//! let main_args = MainArgs::from_env();
+//!
+//! match main_args.command {
+//!     Command::Add(args) => println!("add all: {}", args.all),
+//!     Command::Remove(args) => println!("remove: {:?}", args.file),
+//!     Command::Commit(args) => { /* args.into() */ }
+//! }
+//!
+//! immargs! {
+//!     GitArgs,
+//!     -C <path> String global,                          // Also accepted after any (sub)command
+//!     <command> GitCommand {
+//!         status {                                      // Parsed into GitCommand::Status(StatusArgs)
+//!             -s --short,
+//!         },
+//!         remote "manage remotes" {                     // Parsed into GitCommand::Remote(RemoteArgs)
+//!             add { <name> String, <url> String },      // RemoteCommand::Add(RemoteAddArgs)
+//!             remove rm { <name> String },              // RemoteCommand::Remove(RemoteRemoveArgs)
+//!         },
+//!     }
+//! }
+//!
+//! match GitArgs::from_env().command {
+//!     GitCommand::Status(args) => println!("status short: {}", args.short),
+//!     GitCommand::Remote(RemoteArgs { command: RemoteCommand::Add(args) }) => {
+//!         println!("remote add {} {}", args.name, args.url)
+//!     }
+//!     GitCommand::Remote(RemoteArgs { command: RemoteCommand::Remove(args) }) => {
+//!         println!("remote remove {}", args.name)
+//!     }
+//! }
+//! ```
+//!
 //! # Additional Examples
 //!
 //! Additional examples can be found in [examples] directory.
@@ -191,6 +265,10 @@
 //!   option _verbose_ holding the value `3`. In text, _variadic options_ are represented by
 //!   trailing `...`, e.g. `--file... <path>` or `--verbose...`.
 //!
+//! * __Negatable option__ - A value-less _long option_ that can also be negated by prefixing
+//!   it with `no-`, e.g. `--color` and `--no-color`. The last appearance takes precedence.
+//!   In text, _negatable options_ are represented by `--[no-]`, e.g. `--[no-]color`.
+//!
 //! * __Variadic non-option__ - A _non-option_ that is materialized from multiple separate
 //!   command line arguments of the same type. In text, _variadic non-options_ are represented
 //!   by trailing `...`, e.g. `<file>...` or `[<file>...]`.
@@ -204,15 +282,24 @@
 //! * Short/Long option with separate value, e.g. `-f 100` or `--foo 100`.
 //! * Short/Long option with attached value delimited by `=`, e.g. `-f=100` or `--foo=100`.
 //! * Short option with attached value without delimiter, e.g. `-f100`.
+//! * Short/Long option with optional value, which must be attached, e.g. `--foo=100` or `--foo`.
+//! * Short/Long option with multiple values, which are always separate except for the first,
+//!   e.g. `--foo 100 200` or `--foo=100 200`.
+//! * Short/Long option with comma-delimited list value, e.g. `--foo 100,200,300`.
+//! * Short/Long option or non-option with key-value pair split on the first `=`, e.g.
+//!   `-D name=value` or `NAME=value`.
 //! * Combined short options, e.g. `-abc` is equivalent to `-a -b -c`.
-//! * Short/Long options may appear in any order, but must come before any non-option arguments.
+//! * Short/Long options may appear in any order, but must come before any non-option arguments,
+//!   unless [permutation](#settings) is enabled.
 //! * Short/Long options may appear multiple times, the last appearance takes precedence unless
 //!   it's a _variadic_ (repeatable) option, where the number of times the option appears has
 //!   meaning, e.g. `-vvv` where each `-v` increases the verbosity level.
 //! * The order of non-option arguments carries meaning.
 //! * A standalone `-` argument is treated as a non-option argument.
+//! * A negative number, e.g. `-5` or `-3.14`, is treated as a non-option argument, unless
+//!   a short option, including a `global` option of a parent command, is a digit, e.g. `-1`.
 //! * A standalone `--` argument marks the end of options. Any following arguments are treated
-//!   as non-option arguments.
+//!   as non-option arguments, or as [trailing arguments](#trailing-and-raw-arguments).
 //!
 //! # The Arguments `struct`
 //!
@@ -254,14 +341,32 @@
 //! | - | - | - |
 //! | Option | `--foo` | bool |
 //! | Option with Value | `--foo <bar> T` | `Option<T>` |
+//! | Option with Default Value | `--foo <bar> T = 4` | `T` |
+//! | Negatable Option | `--[no_]foo` | `Option<bool>` |
+//! | Negatable Option with Default Value | `--[no_]foo = true` | `bool` |
 //! | Variadic Option | `--foo...` | `usize` |
 //! | Variadic Option with Value | `--foo... <bar> T` | `Vec<T>` |
+//! | Option with Multiple Values | `--foo <bar> <baz> T` | `Option<(T, T)>` |
+//! | Variadic Option with Multiple Values | `--foo... <bar> <baz> T` | `Vec<(T, T)>` |
+//! | Option with Delimited Value | `--foo <bar,...> T` | `Option<Vec<T>>` |
+//! | Option with Delimited Default Value | `--foo <bar,...> T = "1,2"` | `Vec<T>` |
+//! | Variadic Option with Delimited Value | `--foo... <bar,...> T` | `Vec<T>`, all values combined |
+//! | Option with Optional Value | `--foo [<bar>] T` | `Option<Option<T>>` |
+//! | Variadic Option with Optional Value | `--foo... [<bar>] T` | `Vec<Option<T>>` |
+//! | Option with Value Choices | `--foo <bar> T { x, y }` | `Option<T>`, where `T` is a generated `enum` |
+//! | Option with Key-Value Pair | `--foo <bar> (K, V)` | `Option<(K, V)>` |
+//! | Variadic Option with Key-Value Pairs | `--foo... <bar> (K, V)` | `Vec<(K, V)>`, with unique keys |
+//! | Variadic Option with Key-Value Map | `--foo... <bar> HashMap<K, V>` | `HashMap<K, V>` (or `BTreeMap<K, V>`) |
 //! | Required Non-option | `<foo> T` | `T` |
 //! | Optional Non-option | `[<foo>] T` | `Option<T>` |
 //! | Required Variadic Non-option | `<foo>... T` | `Vec<T>`, with length > 0 |
 //! | Optional Variadic Non-option | `[<foo>...] T` | `Vec<T>`, with length >= 0 |
-//! | Required Command | `<foo> T { /* commands */ }` | `T(`[`Args`]`)` |
-//! | Optional Command | `[<foo>] T { /* commands */ }` | `Option<T(`[`Args`]`)>` |
+//! | Key-Value Non-option | `<foo> (K, V)` | `(K, V)`, also with `[<foo>]`, `<foo>...` etc. |
+//! | Variadic Key-Value Map Non-option | `<foo>... HashMap<K, V>` | `HashMap<K, V>` (or `BTreeMap<K, V>`) |
+//! | Required Command | `<foo> T { /* commands */ }` | `T(`[`Args`]`)`, or `T(S)` for a command declared with `struct` `S` |
+//! | Optional Command | `[<foo>] T { /* commands */ }` | `Option<T(`[`Args`]`)>`, or `Option<T(S)>` |
+//! | Trailing Non-option | `-- <foo>... T` | `Vec<T>`, also with `[<foo>...]` |
+//! | Raw Non-option | `<foo>... raw` | `Vec<String>`, also with `[<foo>...]` |
 //!
 //! #### Methods
 //!
@@ -270,9 +375,15 @@
 //! | Method | Return Type |
 //! | - | - |
 //! | `from_env()` | `Self` |
-//! | `from<T: IntoIterator<Item: Into<String>>>(args: T)` | `Self` |
+//! | `from<T: IntoIterator<Item: Into<OsString>>>(args: T)` | `Self` |
+//! | `from_os<T: IntoIterator<Item = OsString>>(args: T)` | `Self` |
 //! | `try_from_env()` | [`Result`]`<Self>` |
-//! | `try_from<T: IntoIterator<Item: Into<String>>>(args: T)` | [`Result`]`<Self>` |
+//! | `try_from<T: IntoIterator<Item: Into<OsString>>>(args: T)` | [`Result`]`<Self>` |
+//! | `try_from_os<T: IntoIterator<Item = OsString>>(args: T)` | [`Result`]`<Self>` |
+//! | `from_env_with_config(config: impl AsRef<Path>)` | `Self` |
+//! | `from_with_config<T: IntoIterator<Item: Into<OsString>>>(args: T, config: impl AsRef<Path>)` | `Self` |
+//! | `try_from_env_with_config(config: impl AsRef<Path>)` | [`Result`]`<Self>` |
+//! | `try_from_with_config<T: IntoIterator<Item: Into<OsString>>>(args: T, config: impl AsRef<Path>)` | [`Result`]`<Self>` |
 //!
 //! Most applications would want to use `from_env()`, which uses arguments provided by
 //! [`std::env::args_os()`] and on failure prints an error message and terminates the
@@ -292,6 +403,14 @@
 //! allowed to be present on the command line, while `?` (choice) means that exactly one of
 //! the options in the group is must be present on the command line.
 //!
+//! Groups can also be declared using `+` (at least one), meaning that one or more of the
+//! arguments in the group must be present, or `&` (all or none), meaning that either all or
+//! none of the arguments in the group must be present. The usage line of the help message
+//! shows the groups as `[a | b]`, `(a | b)`, `(a | b)...` and `[a b]` respectively.
+//!
+//! A [negatable](#terminology) option that is explicitly negated, e.g. `--no-color`, is not
+//! present in any of its groups, i.e. it neither conflicts with nor satisfies other arguments.
+//!
 //! Example with one group of conflicting arguments, i.e. without an explicit _conflict-id_,
 //! where exacly one of them must be present on the command line:
 //!
@@ -321,6 +440,314 @@
 //! }
 //! ```
 //!
+//! Example with an at-least-one group and an all-or-none group:
+//!
+//! ```
+//! use immargs::immargs;
+//!
+//! immargs! {
+//!     --include... <pattern> String   +I,   // At least one of --include and [files...]
+//!     --user <name> String            &U,   // Both or neither of --user and --password
+//!     --password <pass> String        &U,
+//!     [<files>...] String             +I,
+//! }
+//! ```
+//!
+//! Non-options in the same `!` or `?` group are alternatives sharing one position, where each
+//! argument is set to the first alternative it can be parsed as. A non-option in a group with
+//! an option present on the command line is deselected, i.e. it doesn't take an argument, which
+//! allows grep-style "pattern or `-e` pattern" arguments:
+//!
+//! ```
+//! use immargs::immargs;
+//!
+//! immargs! {
+//!     -e --regexp <pattern> String   ?,
+//!     [<pattern>] String             ?,   // Not taking an argument if -e is present
+//!     <files>... String,
+//! }
+//!
+//! let args = ImmArgs::from(["test", "-e", "foo", "a.txt"]);
+//! assert!(args.pattern.is_none() && args.files == vec!["a.txt"]);
+//! ```
+//!
+//! # Required Arguments
+//!
+//! An argument can be declared to require one or more other arguments, declared using
+//! `requires(...)` listing the field names of the required arguments. If the argument is
+//! present on the command line, and a required argument is not, parsing fails with a
+//! [`MissingRequirement`](Error::MissingRequirement) error. Requirements are verified at
+//! compile time, i.e. the required arguments must exist and must not require each other in a
+//! cycle. Arguments set from [environment variables](#environment-variables) or
+//! [configuration files](#configuration-files) count as present, but default values do not.
+//!
+//! ```
+//! use immargs::immargs;
+//!
+//! immargs! {
+//!     --key <file> String requires(cert),   // Requires --cert
+//!     --cert <file> String,
+//!     --output_format <fmt> String requires(output),   // Requires [output]
+//!     [<output>] String,
+//! }
+//!
+//! let args = ImmArgs::try_from(["test", "--key", "key.pem"]);
+//! assert!(args.is_err());
+//! ```
+//!
+//! # Trailing and Raw Arguments
+//!
+//! A variadic non-option can be declared as _trailing_, using `--` before its name. It
+//! receives all arguments following a standalone `--`, which may include arguments that look
+//! like options and further `--` arguments. The `--` can appear either where it ends the
+//! options, or among the non-options. This allows a program to take two lists of arguments,
+//! for example when wrapping another program. A trailing non-option must be the last
+//! non-option, and cannot be used together with a command, since the arguments following
+//! the command are left for the command to parse.
+//!
+//! ```
+//! use immargs::immargs;
+//!
+//! immargs! {
+//!     -r --release,
+//!     [<target>...] String,
+//!     -- [<arg>...] String,
+//! }
+//!
+//! let args = ImmArgs::from(["test", "-r", "app", "--", "-v", "--", "file"]);
+//! assert!(args.target == vec!["app"]);
+//! assert!(args.arg == vec!["-v", "--", "file"]);
+//! ```
+//!
+//! A variadic non-option can also be declared as _raw_, using `raw` in place of its type.
+//! Once the first non-option is seen, options are no longer interpreted, and the raw
+//! non-option receives the remaining arguments as is, as a `Vec<String>`. A raw non-option
+//! must be the last non-option, and cannot be used together with a trailing non-option or
+//! [permutation](#settings).
+//!
+//! ```
+//! use immargs::immargs;
+//!
+//! immargs! {
+//!     -n --niceness <n> i8 = 10,
+//!     <command> String,
+//!     [<args>...] raw,
+//! }
+//!
+//! let args = ImmArgs::from(["test", "-n", "5", "ls", "-l", "--", "-n"]);
+//! assert!(args.niceness == 5);
+//! assert!(args.command == "ls");
+//! assert!(args.args == vec!["-l", "--", "-n"]);
+//! ```
+//!
+//! Neither can follow a command, which results in a compile error:
+//!
+//! ```compile_fail
+//! use immargs::immargs;
+//!
+//! immargs! {
+//!     <command> Command {
+//!         run,
+//!     },
+//!     -- [<args>...] String,
+//! }
+//! ```
+//!
+//! # Default Values
+//!
+//! A non-variadic option taking a single value can have a default value, declared using `=`
+//! following the value type. The default value is either a literal, which is parsed the same
+//! way as a command line argument, or a block expression evaluating to the field type. The
+//! field type of an option with a default value is `T` instead of `Option<T>`. A default
+//! value is only evaluated if the option is absent, and an invalid literal results in an
+//! [`InvalidDefault`](Error::InvalidDefault) error. The help message shows a literal default
+//! value as `[default: value]`. A negatable option can have a default value of `true` or
+//! `false`, making the field type `bool` instead of `Option<bool>`.
+//!
+//! ```
+//! use immargs::immargs;
+//!
+//! immargs! {
+//!     -j --jobs <n> usize = 4                   "number of jobs",
+//!     --[no_]progress = true                    "show progress",
+//!     --color <when> String = "auto"            "when to use colors",
+//!     --timeout <secs> f64 = { 60.0 * 5.0 }     "timeout in seconds",
+//!     -h --help                                 "print help message",
+//! }
+//! ```
+//!
+//! # Value Choices
+//!
+//! An option value can be restricted to a set of choices, declared using `{ ... }` following
+//! the value type, similar to how commands are declared. The value type is then a generated
+//! `enum` with one variant per choice, named in `UpperCamelCase`, implementing
+//! [`FromStr`](std::str::FromStr) and [`Display`](std::fmt::Display). A choice can have
+//! aliases, listed after the choice name. An invalid value results in a
+//! [`ParsingFailed`](Error::ParsingFailed) error listing the valid choices, and suggesting a
+//! close match if there is one. The help message shows the choices as
+//! `[possible values: ...]`.
+//!
+//! ```
+//! use immargs::immargs;
+//!
+//! immargs! {
+//!     --color <when> Color { always, never no, auto } = "auto"   "when to use colors",
+//!     -h --help                                                  "print help message",
+//! }
+//!
+//! let args = ImmArgs::from(["test", "--color", "no"]);
+//! assert!(args.color == Color::Never);
+//! ```
+//!
+//! # Custom Parsers
+//!
+//! A value type that does not implement [`FromStr`](std::str::FromStr) can be parsed using a
+//! custom parser function, declared using `with` followed by the function path. The function
+//! takes the value as `&str` or `String` and returns `Result<T, E>`, where `E` converts into
+//! `Box<dyn Error>`. A failure results in a [`ParsingFailed`](Error::ParsingFailed) error.
+//! Alternatively, a type can implement [`Value`] directly, to also handle values that are not
+//! valid unicode.
+//!
+//! ```
+//! use immargs::immargs;
+//! use std::time::Duration;
+//!
+//! fn parse_duration(value: &str) -> Result<Duration, String> {
+//!     match value.strip_suffix("ms") {
+//!         Some(ms) => ms.parse().map(Duration::from_millis),
+//!         None => value.parse().map(Duration::from_secs),
+//!     }
+//!     .map_err(|_| format!("invalid duration '{value}'"))
+//! }
+//!
+//! immargs! {
+//!     --timeout <dur> Duration with parse_duration = "30"   "timeout, in seconds or ms",
+//!     -h --help                                             "print help message",
+//! }
+//!
+//! let args = ImmArgs::from(["test", "--timeout", "500ms"]);
+//! assert!(args.timeout == Duration::from_millis(500));
+//! ```
+//!
+//! # Validation
+//!
+//! A value can be validated after it has been parsed, declared using either `in` followed by a
+//! range, e.g. `in 1..=100`, or `check(f)`, where `f` is a function or closure taking a
+//! reference to the value and returning `Result<(), String>`. Validation applies to each value
+//! of an option or non-option, including values from [environment variables](#environment-variables)
+//! and [configuration files](#configuration-files), but not to default values. For a key-value
+//! pair, validation applies to the value, e.g. `(String, u8) in 1..=8`. A rejected value
+//! results in a [`ValidationFailed`](Error::ValidationFailed) error naming the argument and the
+//! value.
+//!
+//! ```
+//! use immargs::immargs;
+//! use std::path::Path;
+//! use std::path::PathBuf;
+//!
+//! fn exists(path: &Path) -> Result<(), String> {
+//!     match path.exists() {
+//!         true => Ok(()),
+//!         false => Err(String::from("no such file or directory")),
+//!     }
+//! }
+//!
+//! immargs! {
+//!     -p --port <port> u16 in 1024..=65535     "port to listen on",
+//!     -h --help                                "print help message",
+//!     <file> PathBuf check(exists)             "file to serve",
+//! }
+//! ```
+//!
+//! # Environment Variables
+//!
+//! An option taking a single value can fall back to the value of an environment variable,
+//! declared using `env(VARIABLE)` following the value type. The environment variable is only
+//! used if the option is not present on the command line, takes precedence over a
+//! [default value](#default-values), and counts as the option being present when checking
+//! conflicts. A value that fails to parse results in an
+//! [`EnvParsingFailed`](Error::EnvParsingFailed) error naming the environment variable. The
+//! help message shows the environment variable as `[env: VARIABLE]`.
+//!
+//! ```
+//! use immargs::immargs;
+//!
+//! immargs! {
+//!     -l --log <level> u8 env(MYAPP_LOG)   "log level",
+//!     -h --help                            "print help message",
+//! }
+//! ```
+//!
+//! # Configuration Files
+//!
+//! The `*_with_config()` methods also read options from a configuration file, containing
+//! one `key = value` entry per line. A key is the name of a long option, without the leading
+//! `--`, in any case, and `_` can be used in place of `-`. A flag takes `true` or `false`, where `false`
+//! selects the negation of a [negatable](#terminology) flag. A value is either bare, quoted
+//! as `"..."` (with `\\`, `\"`, `\n` and `\t` escapes) or `'...'`, or an array `[a, b]` for a
+//! variadic option or an option taking more than one value. Lines starting with `#` or `;`
+//! are comments. A missing configuration file is ignored.
+//!
+//! Entries preceding the first section apply to the top-level command. Entries following a
+//! section `[command]` apply to the arguments `struct` of that command, and a nested command
+//! is named by the path of commands leading to it, e.g. `[remote.add]`. A section can also set
+//! the `global` options of the commands above it, taking precedence over the sections above.
+//! Entries of sections not belonging to the parsed commands are ignored.
+//!
+//! Options present on the command line or in an [environment variable](#environment-variables)
+//! take precedence over the configuration file, which in turn takes precedence over a
+//! [default value](#default-values). This also applies to [conflicting arguments](#conflicting-arguments),
+//! i.e. an entry is ignored if another argument of its group is present. Errors in the
+//! configuration file result in an [`InvalidConfig`](Error::InvalidConfig) error naming the
+//! file and line. Help and version options cannot be set from a configuration file.
+//!
+//! ```text
+//! # ~/.config/myapp.conf
+//! verbose = true
+//! log_level = "debug"
+//! features = [foo, bar]
+//!
+//! [add]
+//! force = true
+//! ```
+//!
+//! # Settings
+//!
+//! Settings change how the command line is parsed. Settings are declared using `#![setting]`,
+//! following the optional `struct` name. The settings of a command also apply to the commands
+//! declaring their arguments in place, but not to commands with a separate arguments `struct`.
+//!
+//! * `#![permute]` - Allow options to appear after non-options, e.g. `myprog file.txt -v`.
+//!   Options are collected from anywhere on the command line, up to a standalone `--` or a
+//!   command. Any arguments following the command are left for the command to parse. Strict
+//!   ordering is used if the environment variable `POSIXLY_CORRECT` is set.
+//!
+//! * `#![abbreviate]` - Allow long options and commands to be abbreviated, as long as the
+//!   abbreviation is unambiguous, e.g. `--verb` for `--verbose`. An exact match, also of a
+//!   `global` option, always takes precedence. An ambiguous abbreviation results in an
+//!   [`AmbiguousArgument`](Error::AmbiguousArgument) error.
+//!
+//! * `#![response_files]` - Expand `@file` arguments into the arguments listed in `file`.
+//!   Arguments in the file are separated by whitespace, can be quoted using `'...'` or `"..."`,
+//!   and `#` starts a comment. Response files can reference other response files, in which case
+//!   relative paths are resolved from the directory of the referencing file. Option values,
+//!   arguments following a standalone `--`, and the arguments of a command or a raw non-option
+//!   are not expanded, i.e. a command expands them only if it has this setting itself. Response
+//!   files need not be valid UTF-8, except on platforms other than Unix. A response file that
+//!   cannot be read results in a [`ResponseFileFailed`](Error::ResponseFileFailed) error.
+//!
+//! ```
+//! use immargs::immargs;
+//!
+//! immargs! {
+//!     Args,
+//!     #![permute]
+//!     #![abbreviate]
+//!     -v --verbose,
+//!     <file> String,
+//! }
+//! ```
+//!
 //! # Help and Version
 //!
 //! Options with long-option names `--help` and `--version` are special. These options are
@@ -334,38 +761,75 @@
 //!
 //! # Unicode
 //!
-//! Non-unicode command line arguments will be converted to unicode using
-//! [`to_string_lossy()`](std::ffi::OsStr::to_string_lossy) before they are parsed.
+//! Command line arguments are kept as [`OsString`]s while parsed, so non-unicode arguments are
+//! not corrupted. Values of types implementing [`FromOsStr`], such as [`OsString`] and
+//! [`PathBuf`](std::path::PathBuf), are built directly from the argument. Values of other
+//! types require the argument to be valid unicode, otherwise an
+//! [`InvalidUnicode`](Error::InvalidUnicode) error is returned. Options and commands are
+//! matched against the unicode form of the argument, converted using
+//! [`to_string_lossy()`](std::ffi::OsStr::to_string_lossy).
 
 #![warn(missing_docs)]
 #![forbid(unsafe_code)]
 
+use arg::ArgOptionSetter;
+pub use arg::FromOsStr;
+pub use arg::Value;
+use config::Config;
 pub use error::Error;
 pub use immargs_macros::immargs;
 use std::collections::VecDeque;
 use std::collections::vec_deque::IntoIter;
+use std::ffi::OsString;
+use std::path::Path;
+use std::process::ExitStatus;
 use utils::from_args;
+use utils::run_external;
 use utils::try_from_args;
 
 mod arg;
+mod choice;
+mod config;
 mod error;
 mod lexer;
 mod macros;
+mod os;
+mod response;
 mod utils;
 
 #[doc(hidden)]
 pub mod __private {
+    pub use crate::arg::ArgOptionSetter;
     pub use crate::arg::Command;
+    pub use crate::arg::Probe;
+    pub use crate::arg::ProbeFromOsStr;
+    pub use crate::arg::ProbeValue;
+    pub use crate::arg::Unwrap;
+    pub use crate::arg::Wrapper;
+    pub use crate::arg::check_range;
+    pub use crate::arg::default;
+    pub use crate::arg::default_delimited;
+    pub use crate::arg::invalid_command;
     pub use crate::arg::non_option;
     pub use crate::arg::option;
     pub use crate::arg::parse;
+    pub use crate::arg::parse_with;
+    pub use crate::arg::settings;
+    pub use crate::choice::InvalidChoice;
+    pub use crate::config::Config;
     pub use crate::utils::bin_name;
     pub use crate::utils::from;
     pub use crate::utils::from_args;
     pub use crate::utils::from_env;
+    pub use crate::utils::from_env_with_config;
+    pub use crate::utils::from_os;
+    pub use crate::utils::from_with_config;
     pub use crate::utils::try_from;
     pub use crate::utils::try_from_args;
     pub use crate::utils::try_from_env;
+    pub use crate::utils::try_from_env_with_config;
+    pub use crate::utils::try_from_os;
+    pub use crate::utils::try_from_with_config;
 }
 
 /// Result returned by argument parser.
@@ -378,58 +842,95 @@ pub type Result<T> = std::result::Result<T, Error>;
 /// (sub)command arguments into an arguments `struct`.
 pub trait FromArgs: Sized {
     #[doc(hidden)]
-    fn from_args(args: Args) -> Result<Self>;
+    fn from_args(args: Args, config: Option<&Path>) -> Result<Self>;
+
+    #[doc(hidden)]
+    fn from_args_with_globals(
+        args: Args,
+        config: Option<&Config>,
+        _globals: &mut [&mut dyn ArgOptionSetter],
+    ) -> Result<Self> {
+        Self::from_args(args, config.map(Config::file))
+    }
 }
 
 /// Command line arguments in raw form, i.e. not yet parsed.
 #[derive(Debug)]
-pub struct Args(VecDeque<String>);
+pub struct Args(VecDeque<OsString>, Option<String>, Option<OsString>);
 
 impl Args {
     #[inline]
     fn from_env() -> Self {
-        Self(
-            std::env::args_os()
-                .map(|arg| match arg.into_string() {
-                    Ok(arg) => arg,
-                    Err(arg) => arg.to_string_lossy().into_owned(),
-                })
-                .collect::<VecDeque<_>>(),
-        )
+        Self(std::env::args_os().collect::<VecDeque<_>>(), None, None)
     }
 
     #[inline]
-    fn from<T: IntoIterator<Item: Into<String>>>(args: T) -> Self {
+    fn from<T: IntoIterator<Item: Into<OsString>>>(args: T) -> Self {
         Self(
             args.into_iter()
                 .map(|arg| arg.into())
                 .collect::<VecDeque<_>>(),
+            None,
+            None,
         )
     }
 
     #[inline]
-    fn from_vec(vec: Vec<String>) -> Self {
-        Self(VecDeque::from(vec))
+    fn from_os<T: IntoIterator<Item = OsString>>(args: T) -> Self {
+        Self(args.into_iter().collect::<VecDeque<_>>(), None, None)
+    }
+
+    #[inline]
+    fn from_vec(vec: Vec<OsString>) -> Self {
+        Self(VecDeque::from(vec), None, None)
     }
 
     #[inline]
     fn set_bin_name(&mut self, bin_name: String) {
         self.0.pop_front();
-        self.0.push_front(bin_name)
+        self.0.push_front(OsString::from(bin_name))
+    }
+
+    // The file stem of the program, set when the program name is taken from the first
+    // argument, and passed on to (sub)command arguments
+    #[inline]
+    fn program(&self) -> Option<&str> {
+        self.1.as_deref()
     }
 
     #[inline]
-    fn peek(&mut self) -> Option<&String> {
+    fn set_program(&mut self, program: Option<String>) {
+        self.1 = program;
+    }
+
+    // The (sub)command, as given on the command line, these arguments were collected for
+    #[inline]
+    fn command(&self) -> Option<&OsString> {
+        self.2.as_ref()
+    }
+
+    #[inline]
+    fn set_command(&mut self, command: OsString) {
+        self.2 = Some(command);
+    }
+
+    #[inline]
+    fn peek(&self) -> Option<&OsString> {
         self.0.front()
     }
 
     #[inline]
-    fn take(&mut self) -> String {
+    fn push_front(&mut self, arg: OsString) {
+        self.0.push_front(arg)
+    }
+
+    #[inline]
+    fn take(&mut self) -> OsString {
         self.0.pop_front().unwrap()
     }
 
     #[inline]
-    fn pop(&mut self) -> Option<String> {
+    fn pop(&mut self) -> Option<OsString> {
         self.0.pop_front()
     }
 
@@ -504,10 +1005,47 @@ impl Args {
     pub fn try_into<T: FromArgs>(self) -> Result<T> {
         try_from_args(self)
     }
+
+    /// Runs an external (sub)command, i.e. the program `{program}-{command}` found in
+    /// `PATH`, where `{program}` is the file stem of the program and `{command}` is the
+    /// command these arguments were collected for, with the remaining arguments.
+    ///
+    /// The program is spawned as a child process, rather than replacing the current
+    /// process, and this method waits for it to finish. An
+    /// [`ExternalCommandFailed`](Error::ExternalCommandFailed) error is returned if the
+    /// program cannot be run, or if the program name is unknown, e.g. for an empty `argv[0]`.
+    ///
+    /// Example:
+    ///
+    /// ```no_run
+    /// use immargs::immargs;
+    ///
+    /// immargs! {
+    ///     <command> Command {
+    ///         list,
+    ///         _,                  // Any other command, e.g. `tool foo` runs `tool-foo`
+    ///     }
+    /// }
+    ///
+    /// match ImmArgs::from_env().command {
+    ///     Command::List(args) => { /* ... */ }
+    ///     Command::External(_, args) => match args.run_external() {
+    ///         Ok(status) => std::process::exit(status.code().unwrap_or(1)),
+    ///         Err(error) => {
+    ///             eprintln!("error: {error}");
+    ///             std::process::exit(1);
+    ///         }
+    ///     },
+    /// }
+    /// ```
+    #[inline]
+    pub fn run_external(self) -> Result<ExitStatus> {
+        run_external(self)
+    }
 }
 
 impl IntoIterator for Args {
-    type Item = String;
+    type Item = OsString;
     type IntoIter = IntoIter<Self::Item>;
 
     #[inline]
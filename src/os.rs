@@ -0,0 +1,147 @@
+#![doc(hidden)]
+
+use std::ffi::OsString;
+use std::mem::replace;
+use std::mem::take;
+
+// Length, in bytes, of the first character. An invalid sequence counts as one character.
+#[inline]
+pub(crate) fn char_len(bytes: &[u8]) -> usize {
+    match bytes.utf8_chunks().next() {
+        Some(chunk) => match chunk.valid().chars().next() {
+            Some(c) => c.len_utf8(),
+            None => chunk.invalid().len(),
+        },
+        None => 0,
+    }
+}
+
+// Split at byte index `at` of the encoded bytes, which must be on a character boundary.
+// Returns the tail and leaves the head in `arg`.
+#[cfg(unix)]
+pub(crate) fn split_off(arg: &mut OsString, at: usize) -> OsString {
+    use std::os::unix::ffi::OsStringExt;
+
+    let mut head = take(arg).into_vec();
+    let tail = head.split_off(at);
+    *arg = OsString::from_vec(head);
+    OsString::from_vec(tail)
+}
+
+#[cfg(windows)]
+pub(crate) fn split_off(arg: &mut OsString, at: usize) -> OsString {
+    use std::os::windows::ffi::OsStrExt;
+    use std::os::windows::ffi::OsStringExt;
+
+    // Encoded bytes are WTF-8, where an unpaired surrogate takes three bytes
+    const SURROGATE_LEN: usize = 3;
+
+    let wide = take(arg).encode_wide().collect::<Vec<_>>();
+    let mut len = 0;
+    let mut index = 0;
+
+    for c in char::decode_utf16(wide.iter().copied()) {
+        if len >= at {
+            break;
+        }
+
+        match c {
+            Ok(c) => {
+                len += c.len_utf8();
+                index += c.len_utf16();
+            }
+            Err(_) => {
+                len += SURROGATE_LEN;
+                index += 1;
+            }
+        }
+    }
+
+    *arg = OsString::from_wide(&wide[..index]);
+    OsString::from_wide(&wide[index..])
+}
+
+#[cfg(not(any(unix, windows)))]
+pub(crate) fn split_off(arg: &mut OsString, at: usize) -> OsString {
+    let bytes = take(arg);
+    let (head, tail) = bytes.as_encoded_bytes().split_at(at);
+    *arg = OsString::from(String::from_utf8_lossy(head).into_owned());
+    OsString::from(String::from_utf8_lossy(tail).into_owned())
+}
+
+// Convert bytes read from a file, which must be valid UTF-8 unless on Unix.
+#[cfg(unix)]
+pub(crate) fn from_bytes(bytes: Vec<u8>) -> Option<OsString> {
+    use std::os::unix::ffi::OsStringExt;
+
+    Some(OsString::from_vec(bytes))
+}
+
+#[cfg(not(unix))]
+pub(crate) fn from_bytes(bytes: Vec<u8>) -> Option<OsString> {
+    String::from_utf8(bytes).ok().map(OsString::from)
+}
+
+// Split on an ASCII delimiter.
+pub(crate) fn split(mut arg: OsString, delimiter: u8) -> Vec<OsString> {
+    assert!(delimiter.is_ascii());
+    let mut args = vec![];
+
+    while let Some(at) = arg.as_encoded_bytes().iter().position(|&b| b == delimiter) {
+        let mut tail = split_off(&mut arg, at);
+        let tail = split_off(&mut tail, 1);
+        args.push(replace(&mut arg, tail));
+    }
+
+    args.push(arg);
+    args
+}
+
+#[cfg(test)]
+mod test {
+    use super::char_len;
+    use super::split;
+    use super::split_off;
+    use std::ffi::OsString;
+
+    #[test]
+    fn char_len_utf8() {
+        assert!(char_len(b"") == 0);
+        assert!(char_len(b"abc") == 1);
+        assert!(char_len("åäö".as_bytes()) == 2);
+        assert!(char_len("€".as_bytes()) == 3);
+    }
+
+    #[test]
+    fn split_off_head_tail() {
+        let mut arg = OsString::from("--foo=bar");
+        let tail = split_off(&mut arg, 5);
+        assert!(arg == "--foo");
+        assert!(tail == "=bar");
+    }
+
+    #[test]
+    fn split_delimiter() {
+        assert!(split(OsString::from("a,bb,,ccc"), b',') == ["a", "bb", "", "ccc"]);
+        assert!(split(OsString::from("abc"), b',') == ["abc"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn split_non_utf8() {
+        use std::os::unix::ffi::OsStringExt;
+
+        let mut arg = OsString::from_vec(b"-o\xffbar".to_vec());
+        let tail = split_off(&mut arg, 2);
+        assert!(arg == "-o");
+        assert!(tail.into_vec() == b"\xffbar");
+
+        let args = split(OsString::from_vec(b"\xff,\xfe".to_vec()), b',');
+        assert!(
+            args.into_iter()
+                .map(|arg| arg.into_vec())
+                .collect::<Vec<_>>()
+                == [b"\xff", b"\xfe"]
+        );
+    }
+}
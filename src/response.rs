@@ -0,0 +1,152 @@
+#![doc(hidden)]
+
+use crate::Args;
+use crate::Error;
+use crate::Result;
+use crate::os;
+use std::ffi::OsStr;
+use std::ffi::OsString;
+use std::fs::read;
+use std::io;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::path::PathBuf;
+
+const AT: &str = "@";
+const COMMENT: u8 = b'#';
+const ESCAPE: u8 = b'\\';
+
+// Expands "@file" arguments, on behalf of the lexer, into the arguments listed in the
+// file. Arguments are separated by whitespace and can be quoted using '...' or "...".
+// A '#' at the start of an argument starts a comment, which runs to the end of the line.
+// Relative paths in nested "@file" arguments are resolved from the directory of the
+// enclosing file. Arguments are converted from the bytes of the file by os::from_bytes().
+#[derive(Default)]
+pub(crate) struct Expander {
+    // Files being expanded, with the number of arguments following their arguments
+    files: Vec<(usize, PathBuf)>,
+}
+
+impl Expander {
+    #[inline]
+    pub(crate) fn is_response_file(arg: &OsStr) -> bool {
+        arg.len() > AT.len() && arg.as_encoded_bytes().starts_with(AT.as_bytes())
+    }
+
+    // Replace the "@file" argument, just taken from `args`, with the arguments in the file
+    pub(crate) fn expand(&mut self, mut arg: OsString, args: &mut Args) -> Result<()> {
+        let path = PathBuf::from(os::split_off(&mut arg, AT.len()));
+        let error = |error| Error::ResponseFileFailed {
+            file: path.display().to_string(),
+            error,
+        };
+
+        // Files with all their arguments taken are no longer being expanded
+        let remaining = args.0.len();
+        while self.files.last().is_some_and(|(end, _)| *end > remaining) {
+            self.files.pop();
+        }
+
+        let canonical = path.canonicalize().map_err(error)?;
+        if self.files.iter().any(|(_, file)| *file == canonical) {
+            return Err(error(io::Error::other("recursive response file")));
+        }
+
+        let content = read(&path).map_err(error)?;
+        let dir = path.parent().unwrap_or(Path::new(""));
+
+        let mut expanded = vec![];
+        for arg in split(&content) {
+            let Some(mut arg) = os::from_bytes(arg) else {
+                return Err(error(io::Error::new(
+                    ErrorKind::InvalidData,
+                    "invalid UTF-8",
+                )));
+            };
+
+            if Self::is_response_file(&arg) {
+                let file = os::split_off(&mut arg, AT.len());
+                arg.push(dir.join(file));
+            }
+            expanded.push(arg);
+        }
+
+        self.files.push((remaining, canonical));
+        for arg in expanded.into_iter().rev() {
+            args.push_front(arg);
+        }
+
+        Ok(())
+    }
+}
+
+// Quotes, escapes, comments and whitespace are all ASCII, so the content can be split
+// without decoding it
+fn split(content: &[u8]) -> Vec<Vec<u8>> {
+    let mut args = vec![];
+    let mut arg: Option<Vec<u8>> = None;
+    let mut bytes = content.iter().copied();
+
+    while let Some(b) = bytes.next() {
+        match b {
+            COMMENT if arg.is_none() => {
+                bytes.by_ref().find(|&b| b == b'\n');
+            }
+            b'\'' => {
+                let arg = arg.get_or_insert_default();
+                arg.extend(bytes.by_ref().take_while(|&b| b != b'\''));
+            }
+            b'"' => {
+                let arg = arg.get_or_insert_default();
+                while let Some(b) = bytes.next() {
+                    match b {
+                        b'"' => break,
+                        ESCAPE => arg.extend(bytes.next()),
+                        b => arg.push(b),
+                    }
+                }
+            }
+            ESCAPE => arg.get_or_insert_default().extend(bytes.next()),
+            b if b.is_ascii_whitespace() => args.extend(arg.take()),
+            b => arg.get_or_insert_default().push(b),
+        }
+    }
+
+    args.extend(arg);
+    args
+}
+
+#[cfg(test)]
+mod test {
+    fn split(content: &str) -> Vec<String> {
+        super::split(content.as_bytes())
+            .into_iter()
+            .map(|arg| String::from_utf8(arg).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn split_whitespace() {
+        assert!(split("  -a\t--bb\n\r\nccc  ") == ["-a", "--bb", "ccc"]);
+    }
+
+    #[test]
+    fn split_quotes() {
+        assert!(split(r#"'a b' "c d" e'f g'h "" ''"#) == ["a b", "c d", "ef gh", "", ""]);
+    }
+
+    #[test]
+    fn split_escapes() {
+        assert!(split(r#"a\ b "c\"d" 'e\f' \\"#) == ["a b", "c\"d", "e\\f", "\\"]);
+    }
+
+    #[test]
+    fn split_comments() {
+        assert!(split("# comment\n-a # comment\n-b#c\n  #") == ["-a", "-b#c"]);
+    }
+
+    #[test]
+    fn split_non_utf8() {
+        assert!(super::split(b"a\xff 'b\xfe c'") == [b"a\xff".to_vec(), b"b\xfe c".to_vec()]);
+    }
+}
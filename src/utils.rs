@@ -4,31 +4,57 @@ use crate::Args;
 use crate::Error;
 use crate::FromArgs;
 use crate::Result;
+use std::ffi::OsString;
 use std::io::Write;
 use std::io::stdout;
+use std::path::Path;
 use std::path::PathBuf;
+use std::process::ExitStatus;
 use std::process::exit;
 
 #[inline]
-pub fn try_from<T: FromArgs, I: IntoIterator<Item: Into<String>>>(args: I) -> Result<T> {
-    T::from_args(Args::from(args))
+pub fn try_from<T: FromArgs, I: IntoIterator<Item: Into<OsString>>>(args: I) -> Result<T> {
+    T::from_args(Args::from(args), None)
+}
+
+#[inline]
+pub fn try_from_os<T: FromArgs, I: IntoIterator<Item = OsString>>(args: I) -> Result<T> {
+    T::from_args(Args::from_os(args), None)
 }
 
 #[inline]
 pub fn try_from_env<T: FromArgs>() -> Result<T> {
-    T::from_args(Args::from_env())
+    T::from_args(Args::from_env(), None)
 }
 
 #[inline]
 pub fn try_from_args<T: FromArgs>(args: Args) -> Result<T> {
-    T::from_args(args)
+    T::from_args(args, None)
 }
 
 #[inline]
-pub fn from<T: FromArgs, I: IntoIterator<Item: Into<String>>>(args: I) -> T {
+pub fn try_from_with_config<T: FromArgs, I: IntoIterator<Item: Into<OsString>>>(
+    args: I,
+    config: &Path,
+) -> Result<T> {
+    T::from_args(Args::from(args), Some(config))
+}
+
+#[inline]
+pub fn try_from_env_with_config<T: FromArgs>(config: &Path) -> Result<T> {
+    T::from_args(Args::from_env(), Some(config))
+}
+
+#[inline]
+pub fn from<T: FromArgs, I: IntoIterator<Item: Into<OsString>>>(args: I) -> T {
     exit_on_error(try_from(args))
 }
 
+#[inline]
+pub fn from_os<T: FromArgs, I: IntoIterator<Item = OsString>>(args: I) -> T {
+    exit_on_error(try_from_os(args))
+}
+
 #[inline]
 pub fn from_env<T: FromArgs>() -> T {
     exit_on_error(try_from_env())
@@ -39,14 +65,64 @@ pub fn from_args<T: FromArgs>(args: Args) -> T {
     exit_on_error(try_from_args(args))
 }
 
+#[inline]
+pub fn from_with_config<T: FromArgs, I: IntoIterator<Item: Into<OsString>>>(
+    args: I,
+    config: &Path,
+) -> T {
+    exit_on_error(try_from_with_config(args, config))
+}
+
+#[inline]
+pub fn from_env_with_config<T: FromArgs>(config: &Path) -> T {
+    exit_on_error(try_from_env_with_config(config))
+}
+
 #[inline]
 pub fn bin_name(args: &mut Args) -> String {
-    PathBuf::from(args.pop().unwrap_or_default())
-        .file_name()
+    let path = PathBuf::from(args.pop().unwrap_or_default());
+
+    if args.program().is_none() {
+        let program = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned());
+        args.set_program(program);
+    }
+
+    path.file_name()
         .map(|bin_name| bin_name.to_string_lossy().into_owned())
         .unwrap_or(String::from("<program>"))
 }
 
+// Run the stored command as the program "{program}-{command}", spawned as a child
+// process. Without a program name, e.g. for an empty argv[0], the command is not run,
+// since falling back to the bare command name would run an arbitrary program.
+pub fn run_external(mut args: Args) -> Result<ExitStatus> {
+    let command = args.command().cloned().unwrap_or_default();
+    let program = match args.program() {
+        Some(program) if !program.is_empty() && !command.is_empty() => {
+            let mut program = OsString::from(format!("{program}-"));
+            program.push(&command);
+            program
+        }
+        _ => {
+            return Err(Error::ExternalCommandFailed {
+                command: command.to_string_lossy().into_owned(),
+                error: std::io::Error::other("unknown program name"),
+            });
+        }
+    };
+    let _ = args.pop();
+
+    std::process::Command::new(&program)
+        .args(args)
+        .status()
+        .map_err(|error| Error::ExternalCommandFailed {
+            command: program.to_string_lossy().into_owned(),
+            error,
+        })
+}
+
 #[inline]
 fn exit_on_error<T>(args: Result<T>) -> T {
     match args {
@@ -0,0 +1,175 @@
+use immargs::Error;
+use immargs::immargs;
+
+#[test]
+fn abbreviate_long_option() {
+    immargs! {
+        #![abbreviate]
+        --verbose,
+        --log <level> u8,
+    }
+
+    let args = ImmArgs::from(["test", "--verb", "--lo=3"]);
+    assert!(args.verbose);
+    assert!(args.log == Some(3));
+}
+
+#[test]
+fn abbreviate_exact_match() {
+    immargs! {
+        #![abbreviate]
+        --all,
+        --all_files,
+    }
+
+    let args = ImmArgs::from(["test", "--all"]);
+    assert!(args.all);
+    assert!(!args.all_files);
+}
+
+#[test]
+fn abbreviate_empty_option() {
+    immargs! {
+        #![abbreviate]
+        --jobs <n> u8,
+    }
+
+    let args = ImmArgs::try_from(["test", "--=3"]);
+    assert!(matches!(&args, Err(Error::InvalidOption { option, .. }) if option == "--"));
+}
+
+#[test]
+fn abbreviate_alias() {
+    immargs! {
+        #![abbreviate]
+        --color --colour,
+    }
+
+    let args = ImmArgs::from(["test", "--col"]);
+    assert!(args.color);
+}
+
+#[test]
+fn abbreviate_ambiguous_option() {
+    immargs! {
+        #![abbreviate]
+        --verbose,
+        --version_info,
+    }
+
+    let args = ImmArgs::try_from(["test", "--ver"]);
+    assert!(
+        matches!(&args, Err(Error::AmbiguousArgument { arg, candidates })
+            if arg == "--ver" && candidates[..] == ["--verbose", "--version-info"]
+        )
+    );
+}
+
+#[test]
+fn abbreviate_short_option() {
+    immargs! {
+        #![abbreviate]
+        -v --verbose,
+    }
+
+    let args = ImmArgs::try_from(["test", "-verb"]);
+    assert!(matches!(&args, Err(Error::InvalidOption { option, .. }) if option == "-e"));
+}
+
+#[test]
+fn abbreviate_command() {
+    immargs! {
+        #![abbreviate]
+        <command> Command {
+            add,
+            remove rm,
+            rename,
+        },
+    }
+
+    let args = ImmArgs::from(["test", "ad", "arg0"]);
+    let Command::Add(args) = args.command else {
+        panic!();
+    };
+    let mut args = args.into_iter();
+    assert!(args.next().unwrap() == "test add");
+    assert!(args.next().unwrap() == "arg0");
+    assert!(args.next().is_none());
+
+    let args = ImmArgs::from(["test", "rem"]);
+    assert!(matches!(args.command, Command::Remove(_)));
+}
+
+#[test]
+fn abbreviate_ambiguous_command() {
+    immargs! {
+        #![abbreviate]
+        <command> Command {
+            add,
+            remove rm,
+            rename,
+        },
+    }
+
+    let args = ImmArgs::try_from(["test", "re"]);
+    assert!(
+        matches!(&args, Err(Error::AmbiguousArgument { arg, candidates })
+            if arg == "re" && candidates[..] == ["remove", "rename"]
+        )
+    );
+}
+
+#[test]
+fn abbreviate_disabled() {
+    immargs! {
+        --verbose,
+        <command> Command {
+            add,
+        },
+    }
+
+    let args = ImmArgs::try_from(["test", "--verb", "add"]);
+    assert!(matches!(&args, Err(Error::InvalidOption { option, .. }) if option == "--verb"));
+
+    let args = ImmArgs::try_from(["test", "ad"]);
+    assert!(matches!(&args, Err(Error::InvalidCommand { arg, .. }) if arg == "ad"));
+}
+
+#[test]
+fn abbreviate_global_option() {
+    immargs! {
+        --verbose global,
+        --color global,
+        --dry_run global,
+        <command> Command {
+            status: StatusArgs,
+        },
+    }
+
+    immargs! {
+        StatusArgs,
+        #![abbreviate]
+        --verbose_level <n> u32,
+        --colorize,
+    }
+
+    let args = ImmArgs::from(["test", "status", "--verbose"]);
+    assert!(args.verbose);
+    let Command::Status(args) = args.command;
+    assert!(args.verbose_level.is_none());
+
+    let args = ImmArgs::from(["test", "status", "--verbose-l", "2"]);
+    assert!(!args.verbose);
+    let Command::Status(args) = args.command;
+    assert!(args.verbose_level == Some(2));
+
+    let args = ImmArgs::try_from(["test", "status", "--col"]);
+    assert!(
+        matches!(&args, Err(Error::AmbiguousArgument { arg, candidates })
+            if arg == "--col" && candidates[..] == ["--colorize", "--color"]
+        )
+    );
+
+    let args = ImmArgs::from(["test", "status", "--dry"]);
+    assert!(args.dry_run);
+}
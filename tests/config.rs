@@ -0,0 +1,268 @@
+use immargs::Error;
+use immargs::immargs;
+use std::fs::remove_file;
+use std::fs::write;
+use std::ops::Deref;
+use std::path::Path;
+use std::path::PathBuf;
+
+// Configuration file, unique to the test process, removed when dropped
+struct TempFile(PathBuf);
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = remove_file(&self.0);
+    }
+}
+
+impl Deref for TempFile {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TempFile {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+fn file(name: &str, content: &str) -> TempFile {
+    let file = std::env::temp_dir().join(format!("immargs-{}-{name}", std::process::id()));
+    write(&file, content).unwrap();
+    TempFile(file)
+}
+
+#[test]
+fn config() {
+    immargs! {
+        -v --verbose,
+        --[no_]color,
+        -j --jobs <n> usize = 1,
+        --log_level <level> String,
+        --features... <feature> String,
+        --resize <w> <h> u32,
+        [<file>] String,
+    }
+
+    let config = file(
+        "config.conf",
+        indoc::indoc! {r#"
+            # comment
+            verbose = true
+            color = false
+            jobs = 4
+            LOG_LEVEL = "debug"    ; comment
+            features = [a, "b"]
+            resize = [640, 480]
+        "#},
+    );
+
+    let args = ImmArgs::try_from_with_config(["test", "file"], &config).unwrap();
+    assert!(args.verbose);
+    assert!(args.color == Some(false));
+    assert!(args.jobs == 4);
+    assert!(args.log_level.as_deref() == Some("debug"));
+    assert!(args.features == vec!["a", "b"]);
+    assert!(args.resize == Some((640, 480)));
+    assert!(args.file.as_deref() == Some("file"));
+
+    let args = ImmArgs::try_from_with_config(["test", "--jobs=8", "--features", "c"], &config);
+    assert!(matches!(args, Ok(args) if args.jobs == 8 && args.features == vec!["c"]));
+}
+
+#[test]
+fn config_missing() {
+    immargs! {
+        -j --jobs <n> usize = 1,
+    }
+
+    let config = std::env::temp_dir().join(format!("immargs-{}-missing.conf", std::process::id()));
+
+    let args = ImmArgs::try_from_with_config(["test"], config);
+    assert!(matches!(args, Ok(args) if args.jobs == 1));
+}
+
+#[test]
+fn config_conflict() {
+    immargs! {
+        --verbose !,
+        --quiet !,
+    }
+
+    let config = file("config_conflict.conf", "verbose = true\n");
+
+    let args = ImmArgs::try_from_with_config(["test", "--quiet"], &config);
+    assert!(matches!(args, Ok(args) if !args.verbose && args.quiet));
+
+    let args = ImmArgs::try_from_with_config(["test"], &config);
+    assert!(matches!(args, Ok(args) if args.verbose && !args.quiet));
+}
+
+#[test]
+fn config_action() {
+    immargs! {
+        -h --help,
+        -V --version,
+    }
+
+    let config = file("config_action_help.conf", "help = true\n");
+    let args = ImmArgs::try_from_with_config(["test"], &config);
+    assert!(matches!(&args, Err(e)
+        if e.to_string() == format!("{}:1: unknown option '--help'", config.display())
+    ));
+
+    let config = file("config_action_version.conf", "version = true\n");
+    let args = ImmArgs::try_from_with_config(["test"], &config);
+    assert!(matches!(&args, Err(Error::InvalidConfig { line: 1, .. })));
+}
+
+#[test]
+fn config_invalid() {
+    immargs! {
+        -j --jobs <n> usize,
+        --verbose,
+    }
+
+    let config = file("config_invalid_value.conf", "\njobs = many\n");
+    let args = ImmArgs::try_from_with_config(["test"], &config);
+    assert!(matches!(&args, Err(Error::InvalidConfig { file, line, .. })
+        if *file == config.display().to_string() && *line == 2
+    ));
+    assert!(matches!(&args, Err(e)
+        if e.to_string().starts_with(&format!("{}:2: cannot parse argument 'many'", config.display()))
+    ));
+
+    let config = file("config_invalid_option.conf", "jobs = 1\nunknown = 1\n");
+    let args = ImmArgs::try_from_with_config(["test"], &config);
+    assert!(matches!(&args, Err(e)
+        if e.to_string() == format!("{}:2: unknown option '--unknown'", config.display())
+    ));
+
+    let config = file("config_invalid_flag.conf", "verbose = 1\n");
+    let args = ImmArgs::try_from_with_config(["test"], &config);
+    assert!(matches!(&args, Err(e)
+        if e.to_string() == format!("{}:1: expected 'true' or 'false'", config.display())
+    ));
+}
+
+#[test]
+fn config_command() {
+    immargs! {
+        AddArgs,
+        -f --force,
+        -j --jobs <n> usize = 1,
+        <file>... String,
+    }
+
+    immargs! {
+        -v --verbose,
+        -j --jobs <n> usize = 1,
+        <command> Command {
+            add: AddArgs,
+            remote {
+                -v --verbose,
+                add {
+                    -t --tags,
+                    <name> String,
+                },
+            },
+        },
+    }
+
+    let config = file(
+        "config_command.conf",
+        indoc::indoc! {r#"
+            jobs = 2
+            [add]
+            force = true
+            jobs = 3
+            [remote]
+            verbose = true
+            [remote.add]
+            tags = true
+        "#},
+    );
+
+    let args = ImmArgs::from_with_config(["test", "add", "a"], &config);
+    assert!(args.jobs == 2);
+    let Command::Add(args) = args.command else {
+        panic!();
+    };
+    assert!(args.force);
+    assert!(args.jobs == 3);
+
+    let args = ImmArgs::from_with_config(["test", "add", "-j", "4", "a"], &config);
+    assert!(matches!(
+        args.command,
+        Command::Add(AddArgs { jobs: 4, .. })
+    ));
+
+    let args = ImmArgs::from_with_config(["test", "remote", "add", "origin"], &config);
+    assert!(!args.verbose);
+    let Command::Remote(args) = args.command else {
+        panic!();
+    };
+    assert!(args.verbose);
+    let RemoteCommand::Add(args) = args.command;
+    assert!(args.tags);
+
+    let config = file("config_command_invalid.conf", "[add]\nunknown = 1\n");
+    let args = ImmArgs::try_from_with_config(["test", "add", "a"], &config);
+    assert!(matches!(&args, Err(e)
+        if e.to_string() == format!("{}:2: unknown option '--unknown'", config.display())
+    ));
+
+    let args = ImmArgs::try_from_with_config(["test", "remote", "add", "origin"], &config);
+    assert!(args.is_ok());
+}
+
+#[test]
+fn config_command_global() {
+    immargs! {
+        -v --verbose,
+        --color <when> String global,
+        <command> Command {
+            log {
+                -n --max_count <n> usize = 10,
+            },
+            show {},
+        },
+    }
+
+    let config = file(
+        "config_command_global.conf",
+        indoc::indoc! {r#"
+            color = never
+            [log]
+            color = always
+            max_count = 5
+        "#},
+    );
+
+    let args = ImmArgs::from_with_config(["test", "log"], &config);
+    assert!(args.color.as_deref() == Some("always"));
+    let Command::Log(args) = args.command else {
+        panic!();
+    };
+    assert!(args.max_count == 5);
+
+    let args = ImmArgs::from_with_config(["test", "show"], &config);
+    assert!(args.color.as_deref() == Some("never"));
+
+    let args = ImmArgs::from_with_config(["test", "log", "--color", "auto"], &config);
+    assert!(args.color.as_deref() == Some("auto"));
+
+    let config = file(
+        "config_command_global_invalid.conf",
+        "[log]
+verbose = true
+",
+    );
+    let args = ImmArgs::try_from_with_config(["test", "log"], &config);
+    assert!(matches!(&args, Err(e)
+        if e.to_string() == format!("{}:2: unknown option '--verbose'", config.display())
+    ));
+}
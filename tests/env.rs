@@ -0,0 +1,73 @@
+use immargs::Error;
+use immargs::immargs;
+
+// Kept in a separate test binary, since it modifies the environment. All cases are
+// in one test, since the environment must not be modified by concurrent threads.
+#[test]
+fn env() {
+    immargs! {
+        -l --log <level> u8 env(IMMARGS_TEST_LOG),
+        -f --features... <feature,...> String env(IMMARGS_TEST_FEATURES),
+        -q --quiet !,
+        --color <when> String env(IMMARGS_TEST_COLOR) !,
+    }
+
+    let args = ImmArgs::from(["test"]);
+    assert!(args.log.is_none());
+    assert!(args.features.is_empty());
+
+    // SAFETY: No other threads in this test binary access the environment
+    unsafe {
+        std::env::set_var("IMMARGS_TEST_LOG", "3");
+        std::env::set_var("IMMARGS_TEST_FEATURES", "a,b");
+    }
+
+    let args = ImmArgs::from(["test"]);
+    assert!(args.log == Some(3));
+    assert!(args.features == vec!["a", "b"]);
+
+    let args = ImmArgs::from(["test", "--log", "5", "-f", "c"]);
+    assert!(args.log == Some(5));
+    assert!(args.features == vec!["c"]);
+
+    // SAFETY: No other threads in this test binary access the environment
+    unsafe { std::env::set_var("IMMARGS_TEST_LOG", "high") };
+
+    let args = ImmArgs::try_from(["test"]);
+    assert!(
+        matches!(&args, Err(Error::EnvParsingFailed { env, value, .. })
+            if env == "IMMARGS_TEST_LOG" && value == "high"
+        )
+    );
+    assert!(matches!(&args, Err(e)
+        if e.to_string().starts_with("cannot parse environment variable IMMARGS_TEST_LOG='high': ")
+    ));
+
+    let args = ImmArgs::try_from(["test", "--log", "1"]);
+    assert!(matches!(args, Ok(args) if args.log == Some(1)));
+
+    // SAFETY: No other threads in this test binary access the environment
+    unsafe { std::env::set_var("IMMARGS_TEST_COLOR", "never") };
+
+    let args = ImmArgs::try_from(["test", "--log", "1", "-q"]);
+    assert!(
+        matches!(&args, Err(Error::ConflictingArguments { arg0, arg1 })
+            if arg0 == "-q" && arg1 == "IMMARGS_TEST_COLOR"
+        )
+    );
+
+    immargs! {
+        ChoiceArgs,
+        --name <name> String env(IMMARGS_TEST_NAME) ?,
+        --id <id> u32 ?,
+    }
+
+    let args = ChoiceArgs::try_from(["test"]);
+    assert!(matches!(&args, Err(Error::MissingChoice { .. })));
+
+    // SAFETY: No other threads in this test binary access the environment
+    unsafe { std::env::set_var("IMMARGS_TEST_NAME", "name") };
+
+    let args = ChoiceArgs::try_from(["test"]);
+    assert!(matches!(args, Ok(args) if args.name.as_deref() == Some("name")));
+}
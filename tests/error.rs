@@ -1,12 +1,16 @@
 use immargs::Error;
 use immargs::immargs;
+use std::path::Path;
+use std::path::PathBuf;
 
 #[test]
 fn error_invalid_option_short() {
     immargs! {}
 
     let args = ImmArgs::try_from(["test", "-i"]);
-    assert!(matches!(&args, Err(Error::InvalidOption { option }) if option == "-i"));
+    assert!(
+        matches!(&args, Err(Error::InvalidOption { option, suggestion: None }) if option == "-i")
+    );
     assert!(matches!(&args, Err(e) if e.to_string() == "invalid option '-i'"));
 }
 
@@ -15,10 +19,71 @@ fn error_invalid_option_long() {
     immargs! {}
 
     let args = ImmArgs::try_from(["test", "--invalid"]);
-    assert!(matches!(&args, Err(Error::InvalidOption { option }) if option == "--invalid"));
+    assert!(
+        matches!(&args, Err(Error::InvalidOption { option, suggestion: None }) if option == "--invalid")
+    );
     assert!(matches!(&args, Err(e) if e.to_string() == "invalid option '--invalid'"));
 }
 
+#[test]
+fn error_invalid_option_suggestion() {
+    immargs! {
+        -v --verbose,
+        -n --dry_run,
+        -j <n> usize,
+    }
+
+    let args = ImmArgs::try_from(["test", "--verbsoe"]);
+    assert!(
+        matches!(&args, Err(Error::InvalidOption { option, suggestion: Some(suggestion) })
+            if option == "--verbsoe" && suggestion == "--verbose"
+        )
+    );
+    assert!(matches!(&args, Err(e)
+        if e.to_string() == "invalid option '--verbsoe', did you mean '--verbose'?"
+    ));
+
+    let args = ImmArgs::try_from(["test", "-verbose"]);
+    assert!(
+        matches!(&args, Err(Error::InvalidOption { option, suggestion: Some(suggestion) })
+            if option == "-e" && suggestion == "--verbose"
+        )
+    );
+
+    let args = ImmArgs::try_from(["test", "-dry-run"]);
+    assert!(matches!(&args, Err(e)
+        if e.to_string() == "invalid option '-d', did you mean '--dry-run'?"
+    ));
+
+    let args = ImmArgs::try_from(["test", "--j=4"]);
+    assert!(matches!(&args, Err(e)
+        if e.to_string() == "invalid option '--j', did you mean '-j'?"
+    ));
+
+    let args = ImmArgs::try_from(["test", "--dry"]);
+    assert!(matches!(&args, Err(e)
+        if e.to_string() == "invalid option '--dry', did you mean '--dry-run'?"
+    ));
+
+    let args = ImmArgs::try_from(["test", "-x"]);
+    assert!(matches!(
+        &args,
+        Err(Error::InvalidOption {
+            suggestion: None,
+            ..
+        })
+    ));
+
+    let args = ImmArgs::try_from(["test", "--quiet"]);
+    assert!(matches!(
+        &args,
+        Err(Error::InvalidOption {
+            suggestion: None,
+            ..
+        })
+    ));
+}
+
 #[test]
 fn error_invalid_argument() {
     immargs! {}
@@ -28,6 +93,25 @@ fn error_invalid_argument() {
     assert!(matches!(&args, Err(e) if e.to_string() == "invalid argument 'invalid'"));
 }
 
+#[test]
+fn error_ambiguous_argument() {
+    immargs! {
+        #![abbreviate]
+        --verbose,
+        --version_info,
+    }
+
+    let args = ImmArgs::try_from(["test", "--ver"]);
+    assert!(
+        matches!(&args, Err(Error::AmbiguousArgument { arg, candidates })
+            if arg == "--ver" && candidates[..] == ["--verbose", "--version-info"]
+        )
+    );
+    assert!(matches!(&args, Err(e)
+        if e.to_string() == "ambiguous argument '--ver', could be '--verbose' or '--version-info'"
+    ));
+}
+
 #[test]
 fn error_invalid_command() {
     immargs! {
@@ -39,10 +123,47 @@ fn error_invalid_command() {
     }
 
     let args = ImmArgs::try_from(["test", "invalid"]);
-    assert!(matches!(&args, Err(Error::InvalidCommand { arg }) if arg == "invalid"));
+    assert!(
+        matches!(&args, Err(Error::InvalidCommand { arg, suggestion: None }) if arg == "invalid")
+    );
     assert!(matches!(&args, Err(e) if e.to_string() == "invalid command 'invalid'"));
 }
 
+#[test]
+fn error_invalid_command_suggestion() {
+    immargs! {
+        <command> Command {
+            add,
+            remove rm,
+            list,
+        }
+    }
+
+    let args = ImmArgs::try_from(["test", "lsit"]);
+    assert!(
+        matches!(&args, Err(Error::InvalidCommand { arg, suggestion: Some(suggestion) })
+            if arg == "lsit" && suggestion == "list"
+        )
+    );
+    assert!(matches!(&args, Err(e)
+        if e.to_string() == "invalid command 'lsit', did you mean 'list'?"
+    ));
+
+    let args = ImmArgs::try_from(["test", "remvoe"]);
+    assert!(matches!(&args, Err(e)
+        if e.to_string() == "invalid command 'remvoe', did you mean 'remove'?"
+    ));
+
+    let args = ImmArgs::try_from(["test", "status"]);
+    assert!(matches!(
+        &args,
+        Err(Error::InvalidCommand {
+            suggestion: None,
+            ..
+        })
+    ));
+}
+
 #[test]
 fn error_missing_argument() {
     immargs! {
@@ -61,10 +182,32 @@ fn error_missing_value() {
     }
 
     let args = ImmArgs::try_from(["test", "-f"]);
-    assert!(matches!(&args, Err(Error::MissingValue { option }) if option == "-f"));
+    assert!(matches!(&args, Err(Error::MissingValue { option, value: None }) if option == "-f"));
     assert!(matches!(&args, Err(e) if e.to_string() == "missing value for option '-f'"));
 }
 
+#[test]
+fn error_missing_value_position() {
+    immargs! {
+        --resize <w> <h> u32,
+    }
+
+    let args = ImmArgs::try_from(["test", "--resize", "640"]);
+    assert!(
+        matches!(&args, Err(Error::MissingValue { option, value: Some(value) })
+            if option == "--resize" && value == "<h>"
+        )
+    );
+    assert!(
+        matches!(&args, Err(e) if e.to_string() == "missing value '<h>' for option '--resize'")
+    );
+
+    let args = ImmArgs::try_from(["test", "--resize"]);
+    assert!(
+        matches!(&args, Err(e) if e.to_string() == "missing value '<w>' for option '--resize'")
+    );
+}
+
 #[test]
 fn error_unexpected_value() {
     immargs! {
@@ -123,6 +266,84 @@ fn error_conflicting_arguments1() {
     ));
 }
 
+#[test]
+fn error_missing_at_least_one() {
+    immargs! {
+        --include... <pattern> String   +,
+        [<file>...] String              +,
+    }
+
+    let args = ImmArgs::try_from(["test", "--include", "*.rs", "a.rs", "b.rs"]);
+    assert!(matches!(args, Ok(args) if args.include == vec!["*.rs"] && args.file.len() == 2));
+
+    let args = ImmArgs::try_from(["test"]);
+    assert!(matches!(&args, Err(Error::MissingChoice { alternatives })
+        if *alternatives == ["--include", "<file>"]
+    ));
+    assert!(matches!(&args, Err(e)
+        if e.to_string() == "missing argument '--include' or '<file>'"
+    ));
+}
+
+#[test]
+fn error_incomplete_group() {
+    immargs! {
+        --user <name> String         &,
+        --password <pass> String     &,
+    }
+
+    let args = ImmArgs::try_from(["test"]);
+    assert!(args.is_ok());
+
+    let args = ImmArgs::try_from(["test", "--user", "name", "--password", "pass"]);
+    assert!(args.is_ok());
+
+    let args = ImmArgs::try_from(["test", "--password", "pass"]);
+    assert!(matches!(&args, Err(Error::IncompleteGroup { arg, missing })
+        if arg == "--password" && missing == "--user"
+    ));
+    assert!(matches!(&args, Err(e)
+        if e.to_string() == "argument '--password' must be used together with '--user'"
+    ));
+}
+
+#[test]
+fn error_missing_requirement() {
+    immargs! {
+        --key <file> String requires(cert),
+        --cert <file> String,
+        -f <format> String requires(output_),
+        [<output_>] String requires(cert),
+    }
+
+    let args = ImmArgs::try_from(["test", "--key", "key.pem", "--cert", "cert.pem"]);
+    assert!(args.is_ok());
+
+    let args = ImmArgs::try_from(["test", "--key", "key.pem"]);
+    assert!(
+        matches!(&args, Err(Error::MissingRequirement { arg, requires })
+            if arg == "--key" && requires == "--cert"
+        )
+    );
+    assert!(matches!(&args, Err(e)
+        if e.to_string() == "argument '--key' requires '--cert'"
+    ));
+
+    let args = ImmArgs::try_from(["test", "-f", "json"]);
+    assert!(
+        matches!(&args, Err(Error::MissingRequirement { arg, requires })
+            if arg == "-f" && requires == "<output>"
+        )
+    );
+
+    let args = ImmArgs::try_from(["test", "-f", "json", "out.json"]);
+    assert!(
+        matches!(&args, Err(Error::MissingRequirement { arg, requires })
+            if arg == "<output>" && requires == "--cert"
+        )
+    );
+}
+
 #[test]
 fn error_parsing_failed() {
     let parse_value = "ABC";
@@ -140,3 +361,114 @@ fn error_parsing_failed() {
         if e.to_string() == format!("cannot parse argument '{parse_value}': {parse_error}")
     ));
 }
+
+#[test]
+fn error_parsing_failed_delimited() {
+    immargs! {
+        --ports <port,...> u16,
+    }
+
+    let args = ImmArgs::try_from(["test", "--ports", "80,http,443"]);
+    assert!(matches!(&args, Err(Error::ParsingFailed { value, .. }) if value == "http"));
+}
+
+#[test]
+fn error_malformed_pair() {
+    immargs! {
+        -D --define... <kv> (String, u32),
+        [<vars>...] (String, String),
+    }
+
+    let args = ImmArgs::try_from(["test", "-D", "a"]);
+    assert!(matches!(&args, Err(Error::MalformedPair { arg, value })
+        if arg == "-D" && value == "a"
+    ));
+    assert!(matches!(&args, Err(e)
+        if e.to_string() == "malformed key-value pair 'a' for '-D', expected 'key=value'"
+    ));
+
+    let args = ImmArgs::try_from(["test", "--define==1"]);
+    assert!(matches!(&args, Err(Error::MalformedPair { arg, value })
+        if arg == "--define" && value == "=1"
+    ));
+
+    let args = ImmArgs::try_from(["test", "A=1", "B"]);
+    assert!(matches!(&args, Err(Error::MalformedPair { arg, value })
+        if arg == "<vars>" && value == "B"
+    ));
+
+    let args = ImmArgs::try_from(["test", "-D", "a=x"]);
+    assert!(matches!(&args, Err(Error::ParsingFailed { value, .. }) if value == "x"));
+}
+
+#[test]
+fn error_duplicate_key() {
+    immargs! {
+        -D --define... <kv> (u32, String),
+        [<vars>...] (String, String),
+    }
+
+    let args = ImmArgs::try_from(["test", "-D", "1=a", "--define", "01=b"]);
+    assert!(matches!(&args, Err(Error::DuplicateKey { arg, key })
+        if arg == "--define" && key == "01"
+    ));
+    assert!(matches!(&args, Err(e) if e.to_string() == "duplicate key '01' for '--define'"));
+
+    let args = ImmArgs::try_from(["test", "A=1", "A=2"]);
+    assert!(matches!(&args, Err(Error::DuplicateKey { arg, key })
+        if arg == "<vars>" && key == "A"
+    ));
+}
+
+#[test]
+fn error_invalid_choice() {
+    immargs! {
+        --color <when> Color { always, never, auto },
+    }
+
+    let args = ImmArgs::try_from(["test", "--color", "nevr"]);
+    assert!(matches!(&args, Err(Error::ParsingFailed { value, .. }) if value == "nevr"));
+    assert!(matches!(&args, Err(e)
+        if e.to_string() == "cannot parse argument 'nevr': \
+            expected 'always', 'never' or 'auto', did you mean 'never'?"
+    ));
+
+    let args = ImmArgs::try_from(["test", "--color", "sometimes"]);
+    assert!(matches!(&args, Err(e)
+        if e.to_string() == "cannot parse argument 'sometimes': \
+            expected 'always', 'never' or 'auto'"
+    ));
+}
+
+#[test]
+fn error_validation_failed() {
+    fn exists(path: &Path) -> Result<(), String> {
+        match path.exists() {
+            true => Ok(()),
+            false => Err(String::from("no such file")),
+        }
+    }
+
+    immargs! {
+        -p --port <port> u16 in 1024..=65535,
+        <path> PathBuf check(exists),
+    }
+
+    let args = ImmArgs::try_from(["test", "-p", "80", "."]);
+    assert!(
+        matches!(&args, Err(Error::ValidationFailed { arg, value, .. })
+            if arg == "-p" && value == "80"
+        )
+    );
+    assert!(matches!(&args, Err(e)
+        if e.to_string() == "invalid value '80' for '-p': expected value in range 1024..=65535"
+    ));
+
+    let args = ImmArgs::try_from(["test", "--port=1024", "immargs-no-such-file"]);
+    assert!(matches!(&args, Err(e)
+        if e.to_string() == "invalid value 'immargs-no-such-file' for '<path>': no such file"
+    ));
+
+    let args = ImmArgs::try_from(["test", "--port", "http", "."]);
+    assert!(matches!(&args, Err(Error::ParsingFailed { value, .. }) if value == "http"));
+}
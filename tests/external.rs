@@ -0,0 +1,89 @@
+#![cfg(unix)]
+
+use immargs::Error;
+use immargs::immargs;
+use std::fs::create_dir_all;
+use std::fs::remove_dir_all;
+use std::fs::set_permissions;
+use std::fs::write;
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+
+// Directory of stub programs, unique to the test process, removed when dropped
+struct TempDir(PathBuf);
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = remove_dir_all(&self.0);
+    }
+}
+
+fn program(name: &str, content: &str) -> TempDir {
+    let dir = std::env::temp_dir().join(format!("immargs-external-{}", std::process::id()));
+    create_dir_all(&dir).unwrap();
+    let file = dir.join(name);
+    write(&file, content).unwrap();
+    set_permissions(&file, PermissionsExt::from_mode(0o755)).unwrap();
+    TempDir(dir)
+}
+
+// Kept in a separate test binary, since it modifies the environment. All cases are
+// in one test, since the environment must not be modified by concurrent threads.
+#[test]
+fn external() {
+    immargs! {
+        <command> Command {
+            list,
+            _,
+        }
+    }
+
+    let dir = program(
+        "test-foo",
+        "#!/bin/sh\ntest \"$*\" = \"-x bar\" && exit 7\nexit 1\n",
+    );
+    let path = std::env::join_paths(
+        std::iter::once(dir.0.clone())
+            .chain(std::env::split_paths(&std::env::var_os("PATH").unwrap())),
+    )
+    .unwrap();
+
+    // SAFETY: No other threads in this test binary access the environment
+    unsafe { std::env::set_var("PATH", path) };
+
+    let args = ImmArgs::from(["test", "foo", "-x", "bar"]);
+    let Command::External(command, args) = args.command else {
+        panic!();
+    };
+    assert!(command == "foo");
+    assert!(matches!(args.run_external(), Ok(status) if status.code() == Some(7)));
+
+    let args = ImmArgs::from(["/bin/test.exe", "foo"]);
+    let Command::External(_, args) = args.command else {
+        panic!();
+    };
+    assert!(matches!(args.run_external(), Ok(status) if status.code() == Some(1)));
+
+    let args = ImmArgs::from(["test", "missing"]);
+    let Command::External(_, args) = args.command else {
+        panic!();
+    };
+    let result = args.run_external();
+    assert!(
+        matches!(&result, Err(Error::ExternalCommandFailed { command, error })
+            if command == "test-missing" && error.kind() == std::io::ErrorKind::NotFound
+        )
+    );
+    assert!(matches!(&result, Err(e)
+        if e.to_string().starts_with("cannot run external command 'test-missing': ")
+    ));
+
+    let args = ImmArgs::from(["", "ls"]);
+    let Command::External(_, args) = args.command else {
+        panic!();
+    };
+    let result = args.run_external();
+    assert!(matches!(&result, Err(e)
+        if e.to_string() == "cannot run external command 'ls': unknown program name"
+    ));
+}
@@ -58,6 +58,215 @@ fn help_option() {
     assert_help!(&args, help);
 }
 
+#[test]
+fn help_option_optional_value() {
+    immargs! {
+        --aaa [<value>] String      "Help aaa",
+        -b --bbb [<value>] String   "Help bbb",
+        -h --help                   "Print help message",
+    }
+
+    let help = indoc! {"
+        usage: test [options]
+
+        options:
+           --aaa[=<value>]         Help aaa
+           -b, --bbb[=<value>]     Help bbb
+           -h, --help              Print help message
+
+    "};
+
+    let args = ImmArgs::try_from(["test", "-h"]);
+    assert_help!(&args, help);
+}
+
+#[test]
+fn help_option_values() {
+    immargs! {
+        --aaa <x> <y> u32       "Help aaa",
+        -b --bbb <w> <h> u32    "Help bbb",
+        -h --help               "Print help message",
+    }
+
+    let help = indoc! {"
+        usage: test [options]
+
+        options:
+           --aaa <x> <y>         Help aaa
+           -b, --bbb <w> <h>     Help bbb
+           -h, --help            Print help message
+
+    "};
+
+    let args = ImmArgs::try_from(["test", "-h"]);
+    assert_help!(&args, help);
+}
+
+#[test]
+fn help_option_delimited() {
+    immargs! {
+        --aaa <value,...> u32       "Help aaa",
+        -b --bbb... <value,...> u32 "Help bbb",
+        -h --help                   "Print help message",
+    }
+
+    let help = indoc! {"
+        usage: test [options]
+
+        options:
+           --aaa <value,...>         Help aaa
+           -b, --bbb <value,...>     Help bbb
+           -h, --help                Print help message
+
+    "};
+
+    let args = ImmArgs::try_from(["test", "-h"]);
+    assert_help!(&args, help);
+}
+
+#[test]
+fn help_option_env() {
+    immargs! {
+        --aaa <value> u32 env(AAA)      "Help aaa",
+        -b --bbb <value> u32 env(BBB),
+        -h --help                       "Print help message",
+    }
+
+    let help = indoc! {"
+        usage: test [options]
+
+        options:
+           --aaa <value>         Help aaa [env: AAA]
+           -b, --bbb <value>     [env: BBB]
+           -h, --help            Print help message
+
+    "};
+
+    let args = ImmArgs::try_from(["test", "-h"]);
+    assert_help!(&args, help);
+}
+
+#[test]
+fn help_option_default() {
+    immargs! {
+        --aaa <value> u32 = 4 env(AAA)      "Help aaa",
+        -b --bbb <value> String = "x",
+        --ccc <value> u32 = { 1 + 2 }       "Help ccc",
+        -h --help                           "Print help message",
+    }
+
+    let help = indoc! {"
+        usage: test [options]
+
+        options:
+           --aaa <value>         Help aaa [env: AAA] [default: 4]
+           -b, --bbb <value>     [default: x]
+           --ccc <value>         Help ccc
+           -h, --help            Print help message
+
+    "};
+
+    let args = ImmArgs::try_from(["test", "-h"]);
+    assert_help!(&args, help);
+}
+
+#[test]
+fn help_option_choices() {
+    immargs! {
+        --color <when> Color { always, never, auto } = "auto"   "Help color",
+        -h --help                                               "Print help message",
+    }
+
+    let help = indoc! {"
+        usage: test [options]
+
+        options:
+           --color <when>     Help color [possible values: always, never, auto] [default: auto]
+           -h, --help         Print help message
+
+    "};
+
+    let args = ImmArgs::try_from(["test", "-h"]);
+    assert_help!(&args, help);
+}
+
+#[test]
+fn help_option_negatable() {
+    immargs! {
+        --[no_]aaa          "Help aaa",
+        -b --[no_]bbb       "Help bbb",
+        -h --help           "Print help message",
+    }
+
+    let help = indoc! {"
+        usage: test [options]
+
+        options:
+           --[no-]aaa         Help aaa
+           -b, --[no-]bbb     Help bbb
+           -h, --help         Print help message
+
+    "};
+
+    let args = ImmArgs::try_from(["test", "-h"]);
+    assert_help!(&args, help);
+}
+
+#[test]
+fn help_groups() {
+    immargs! {
+        --aaa      !A,
+        --bbb      !A,
+        -c         ?C,
+        --ddd      ?C,
+        --eee      +E,
+        --user     &U,
+        --password &U,
+        -h --help  "Print help message",
+        [<file>...] String +E,
+    }
+
+    let help = indoc! {"
+        usage: test [options] [--aaa | --bbb] (-c | --ddd) [--user --password] (--eee | <file>...)...
+
+        options:
+           --aaa
+           --bbb
+           -c
+           --ddd
+           --eee
+           --user
+           --password
+           -h, --help     Print help message
+
+    "};
+
+    let args = ImmArgs::try_from(["test", "-h"]);
+    assert_help!(&args, help);
+}
+
+#[test]
+fn help_non_option_alternatives() {
+    immargs! {
+        -e --regexp <pattern> String   ?,
+        -h --help                      "Print help message",
+        [<pattern>] String             ?,
+        <file>... String,
+    }
+
+    let help = indoc! {"
+        usage: test [options] (--regexp | <pattern>) <file>...
+
+        options:
+           -e, --regexp <pattern>
+           -h, --help                 Print help message
+
+    "};
+
+    let args = ImmArgs::try_from(["test", "-h"]);
+    assert_help!(&args, help);
+}
+
 #[test]
 fn help_non_option_required() {
     immargs! {
@@ -82,6 +291,30 @@ fn help_non_option_required() {
     assert_help!(&args, help);
 }
 
+#[test]
+fn help_non_option_trailing() {
+    immargs! {
+        -h --help                 "Print help message",
+        <aaa>... String           "Help aaa",
+        -- [<bbb>...] String      "Help bbb",
+    }
+
+    let help = indoc! {"
+        usage: test [options] <aaa>... [-- <bbb>...]
+
+        options:
+           -h, --help        Print help message
+
+        arguments:
+           <aaa>...          Help aaa
+           [-- <bbb>...]     Help bbb
+
+    "};
+
+    let args = ImmArgs::try_from(["test", "-h"]);
+    assert_help!(&args, help);
+}
+
 #[test]
 fn help_non_option_optional() {
     immargs! {
@@ -156,3 +389,128 @@ fn help_non_option_command() {
     let args = ImmArgs::try_from(["test", "-h"]);
     assert_help!(&args, help);
 }
+
+#[test]
+fn help_non_option_command_typed() {
+    immargs! {
+        AddArgs,
+        -h --help           "Print help message",
+        <file>... String    "File(s) to add",
+    }
+
+    immargs! {
+        -h --help           "Print help message",
+        <command> Command {
+            add: AddArgs    "Add file(s)",
+        },
+    }
+
+    let help = indoc! {"
+        usage: test add [options] <file>...
+
+        options:
+           -h, --help     Print help message
+
+        arguments:
+           <file>...      File(s) to add
+
+    "};
+
+    let args = ImmArgs::try_from(["test", "add", "-h"]);
+    assert_help!(&args, help);
+}
+
+#[test]
+fn help_non_option_command_nested() {
+    immargs! {
+        -h --help                   "Print help message",
+        <command> Command {
+            remote                  "Manage remotes" {
+                -h --help           "Print help message",
+                add                 "Add remote" {
+                    -h --help       "Print help message",
+                    <name> String   "Remote name",
+                },
+            },
+        },
+    }
+
+    let help = indoc! {"
+        usage: test remote [options] <command> [...]
+
+        options:
+           -h, --help     Print help message
+
+        commands:
+           add            Add remote
+
+    "};
+
+    let args = ImmArgs::try_from(["test", "remote", "-h"]);
+    assert_help!(&args, help);
+
+    let help = indoc! {"
+        usage: test remote add [options] <name>
+
+        options:
+           -h, --help     Print help message
+
+        arguments:
+           <name>         Remote name
+
+    "};
+
+    let args = ImmArgs::try_from(["test", "remote", "add", "-h"]);
+    assert_help!(&args, help);
+}
+
+#[test]
+fn help_option_global() {
+    immargs! {
+        -h --help                   "Print help message",
+        -v --verbose global         "Print more output",
+        <command> Command {
+            remote                  "Manage remotes" {
+                -h --help           "Print help message",
+                -q --quiet global   "Print less output",
+                add                 "Add remote" {
+                    -h --help       "Print help message",
+                    <name> String   "Remote name",
+                },
+            },
+        },
+    }
+
+    let help = indoc! {"
+        usage: test [options] <command> [...]
+
+        options:
+           -h, --help        Print help message
+           -v, --verbose     Print more output
+
+        commands:
+           remote            Manage remotes
+
+    "};
+
+    let args = ImmArgs::try_from(["test", "-h"]);
+    assert_help!(&args, help);
+
+    let help = indoc! {"
+        usage: test remote add [options] <name>
+
+        options:
+           -h, --help        Print help message
+
+        global options:
+           -v, --verbose     Print more output
+           -q, --quiet       Print less output
+
+        arguments:
+           <name>            Remote name
+
+    "};
+
+    let args = ImmArgs::try_from(["test", "remote", "add", "-h"]);
+    assert_help!(&args, help);
+}
@@ -1,4 +1,6 @@
+use immargs::Error;
 use immargs::immargs;
+use std::collections::BTreeMap;
 
 #[test]
 fn non_option_required() {
@@ -64,6 +66,176 @@ fn non_option_optional_variadic() {
     assert!(args.value[1] == "world");
 }
 
+#[test]
+fn non_option_key_value() {
+    immargs! {
+        <target> String,
+        [<vars>...] BTreeMap<String, String>,
+    }
+
+    let args = ImmArgs::from(["test", "all", "CC=gcc", "CFLAGS=-O2 -g"]);
+    assert!(args.target == "all");
+    assert!(
+        args.vars
+            == BTreeMap::from([
+                (String::from("CC"), String::from("gcc")),
+                (String::from("CFLAGS"), String::from("-O2 -g")),
+            ])
+    );
+
+    immargs! {
+        PairArgs,
+        <pair> (char, i32),
+        [<pairs>...] (u8, u8),
+    }
+
+    let args = PairArgs::from(["test", "x=-1", "1=2", "3=4"]);
+    assert!(args.pair == ('x', -1));
+    assert!(args.pairs == vec![(1, 2), (3, 4)]);
+}
+
+#[test]
+fn non_option_parser() {
+    fn parse_hex(value: &str) -> Result<u32, std::num::ParseIntError> {
+        u32::from_str_radix(value.trim_start_matches("0x"), 16)
+    }
+
+    immargs! {
+        <addr> u32 with parse_hex in 0x1000..,
+        [<data>...] u32 with parse_hex,
+    }
+
+    let args = ImmArgs::from(["test", "0x1000", "ff", "0x10"]);
+    assert!(args.addr == 0x1000);
+    assert!(args.data == vec![0xff, 0x10]);
+
+    let args = ImmArgs::try_from(["test", "0x100"]);
+    assert!(matches!(&args, Err(Error::ValidationFailed { value, .. }) if value == "0x100"));
+
+    let args = ImmArgs::try_from(["test", "0x1000", "xyz"]);
+    assert!(matches!(&args, Err(Error::ParsingFailed { value, .. }) if value == "xyz"));
+}
+
+#[test]
+fn non_option_key_value_check() {
+    immargs! {
+        <limit> (String, u32) in ..=100,
+    }
+
+    let args = ImmArgs::from(["test", "jobs=100"]);
+    assert!(args.limit == (String::from("jobs"), 100));
+
+    let args = ImmArgs::try_from(["test", "jobs=101"]);
+    assert!(matches!(&args, Err(Error::ValidationFailed { value, .. }) if value == "101"));
+}
+
+#[test]
+fn non_option_alternatives() {
+    use std::net::Ipv4Addr;
+
+    immargs! {
+        [<addr>] Ipv4Addr     !,
+        [<port>] u16          !,
+        [<host>] String       !,
+    }
+
+    let args = ImmArgs::from(["test", "127.0.0.1"]);
+    assert!(args.addr == Some(Ipv4Addr::LOCALHOST));
+    assert!(args.port.is_none() && args.host.is_none());
+
+    let args = ImmArgs::from(["test", "8080"]);
+    assert!(args.addr.is_none() && args.port == Some(8080) && args.host.is_none());
+
+    let args = ImmArgs::from(["test", "localhost"]);
+    assert!(args.addr.is_none() && args.port.is_none());
+    assert!(args.host.as_deref() == Some("localhost"));
+
+    let args = ImmArgs::try_from(["test", "localhost", "8080"]);
+    assert!(matches!(args, Err(Error::InvalidArgument { arg }) if arg == "8080"));
+}
+
+#[test]
+fn non_option_alternatives_selected() {
+    immargs! {
+        -e --regexp <pattern> String   ?,
+        [<pattern>] String             ?,
+        <file>... String,
+    }
+
+    let args = ImmArgs::from(["test", "foo", "a.txt", "b.txt"]);
+    assert!(args.pattern.as_deref() == Some("foo"));
+    assert!(args.regexp.is_none());
+    assert!(args.file == vec!["a.txt", "b.txt"]);
+
+    let args = ImmArgs::from(["test", "-e", "foo", "a.txt", "b.txt"]);
+    assert!(args.pattern.is_none());
+    assert!(args.regexp.as_deref() == Some("foo"));
+    assert!(args.file == vec!["a.txt", "b.txt"]);
+}
+
+#[test]
+fn non_option_trailing() {
+    immargs! {
+        -v --verbose,
+        [<arg>...] String,
+        -- [<rest>...] String,
+    }
+
+    let args = ImmArgs::from(["test", "-v", "a", "b", "--", "-c", "--", "d"]);
+    assert!(args.verbose);
+    assert!(args.arg == vec!["a", "b"]);
+    assert!(args.rest == vec!["-c", "--", "d"]);
+
+    let args = ImmArgs::from(["test", "--", "a", "-v"]);
+    assert!(!args.verbose);
+    assert!(args.arg.is_empty());
+    assert!(args.rest == vec!["a", "-v"]);
+
+    let args = ImmArgs::from(["test", "a", "b"]);
+    assert!(args.arg == vec!["a", "b"]);
+    assert!(args.rest.is_empty());
+}
+
+#[test]
+fn non_option_trailing_required() {
+    immargs! {
+        <program> String,
+        -- <arg>... u32,
+    }
+
+    let args = ImmArgs::from(["test", "prog", "--", "1", "2"]);
+    assert!(args.program == "prog");
+    assert!(args.arg == vec![1, 2]);
+
+    let args = ImmArgs::try_from(["test", "prog"]);
+    assert!(matches!(args, Err(Error::MissingArgument { arg }) if arg == "<arg>"));
+
+    let args = ImmArgs::try_from(["test", "prog", "1"]);
+    assert!(matches!(args, Err(Error::MissingArgument { arg }) if arg == "<arg>"));
+
+    let args = ImmArgs::try_from(["test", "prog", "--", "x"]);
+    assert!(matches!(&args, Err(Error::ParsingFailed { value, .. }) if value == "x"));
+}
+
+#[test]
+fn non_option_raw() {
+    immargs! {
+        -v --verbose,
+        <command> String,
+        [<args>...] raw,
+    }
+
+    let args = ImmArgs::from(["test", "-v", "ls", "-l", "--", "-v", "--all"]);
+    assert!(args.verbose);
+    assert!(args.command == "ls");
+    assert!(args.args == vec!["-l", "--", "-v", "--all"]);
+
+    let args = ImmArgs::from(["test", "--", "-x", "-y"]);
+    assert!(!args.verbose);
+    assert!(args.command == "-x");
+    assert!(args.args == vec!["-y"]);
+}
+
 #[test]
 fn non_option_required_variadic_redistribute0() {
     immargs! {
@@ -219,3 +391,271 @@ fn non_option_command_alias() {
     assert!(args.next().unwrap() == "arg1");
     assert!(args.next().is_none());
 }
+
+#[test]
+fn non_option_command_typed() {
+    immargs! {
+        AddArgs,
+        -f --force,
+        <file>... String,
+    }
+
+    immargs! {
+        -v --verbose,
+        <command> Command {
+            add: AddArgs,
+            remove rm,
+        },
+    }
+
+    let args = ImmArgs::from(["test", "-v", "add", "-f", "a", "b"]);
+    assert!(args.verbose);
+    let Command::Add(args) = args.command else {
+        panic!();
+    };
+    assert!(args.force);
+    assert!(args.file == vec!["a", "b"]);
+
+    let args = ImmArgs::from(["test", "rm", "arg0"]);
+    let Command::Remove(args) = args.command else {
+        panic!();
+    };
+    let mut args = args.into_iter();
+    assert!(args.next().unwrap() == "test remove");
+    assert!(args.next().unwrap() == "arg0");
+    assert!(args.next().is_none());
+
+    let args = ImmArgs::try_from(["test", "add", "-x"]);
+    assert!(matches!(args, Err(Error::InvalidOption { option, .. }) if option == "-x"));
+
+    let args = ImmArgs::try_from(["test", "add"]);
+    assert!(matches!(args, Err(Error::MissingArgument { arg }) if arg == "<file>"));
+}
+
+#[test]
+fn non_option_command_nested() {
+    immargs! {
+        -v --verbose,
+        <command> Command {
+            remote {
+                -v --verbose,
+                add {
+                    -f --fetch,
+                    <name> String,
+                    <url> String,
+                },
+                remove rm {
+                    <name> String,
+                },
+            },
+            status: StatusArgs {
+                -s --short,
+            },
+            log,
+        },
+    }
+
+    let args = ImmArgs::from(["test", "remote", "add", "-f", "origin", "url"]);
+    let Command::Remote(args) = args.command else {
+        panic!();
+    };
+    assert!(!args.verbose);
+    let RemoteCommand::Add(args) = args.command else {
+        panic!();
+    };
+    assert!(args.fetch);
+    assert!(args.name == "origin");
+    assert!(args.url == "url");
+
+    let args = ImmArgs::from(["test", "remote", "-v", "rm", "origin"]);
+    let Command::Remote(RemoteArgs {
+        verbose: true,
+        command: RemoteCommand::Remove(RemoteRemoveArgs { name }),
+    }) = args.command
+    else {
+        panic!();
+    };
+    assert!(name == "origin");
+
+    let args = ImmArgs::from(["test", "status", "-s"]);
+    assert!(matches!(
+        args.command,
+        Command::Status(StatusArgs { short: true })
+    ));
+
+    let args = ImmArgs::from(["test", "log", "arg0"]);
+    assert!(matches!(args.command, Command::Log(_)));
+
+    let args = ImmArgs::try_from(["test", "remote"]);
+    assert!(matches!(args, Err(Error::MissingArgument { arg }) if arg == "<command>"));
+
+    let args = ImmArgs::try_from(["test", "remote", "add", "origin"]);
+    assert!(matches!(args, Err(Error::MissingArgument { arg }) if arg == "<url>"));
+}
+
+#[test]
+fn non_option_command_external() {
+    immargs! {
+        -v --verbose,
+        <command> Command {
+            add,
+            remote {
+                list,
+                _,
+            },
+            _,
+        },
+    }
+
+    let args = ImmArgs::from(["test", "-v", "foo", "-x", "bar"]);
+    assert!(args.verbose);
+    let Command::External(command, args) = args.command else {
+        panic!();
+    };
+    assert!(command == "foo");
+    assert!(args.into_iter().collect::<Vec<_>>() == ["test foo", "-x", "bar"]);
+
+    let args = ImmArgs::from(["test", "remote", "bar"]);
+    let Command::Remote(RemoteArgs {
+        command: RemoteCommand::External(command, args),
+    }) = args.command
+    else {
+        panic!();
+    };
+    assert!(command == "bar");
+    assert!(args.into_iter().collect::<Vec<_>>() == ["test remote bar"]);
+
+    let args = ImmArgs::from(["test", "add"]);
+    assert!(matches!(args.command, Command::Add(_)));
+
+    let args = ImmArgs::try_from(["test"]);
+    assert!(matches!(args, Err(Error::MissingArgument { arg }) if arg == "<command>"));
+}
+
+#[test]
+fn non_option_command_global() {
+    immargs! {
+        -v --verbose global,
+        -C --directory <dir> String global,
+        <command> Command {
+            remote {
+                -q --quiet global,
+                add {
+                    <name> String,
+                },
+            },
+            status: StatusArgs,
+        },
+    }
+
+    immargs! {
+        StatusArgs,
+        -s --short,
+    }
+
+    let args = ImmArgs::from(["test", "remote", "add", "-v", "-q", "-C", "dir", "origin"]);
+    assert!(args.verbose);
+    assert!(args.directory.as_deref() == Some("dir"));
+    let Command::Remote(args) = args.command else {
+        panic!();
+    };
+    assert!(args.quiet);
+    let RemoteCommand::Add(args) = args.command;
+    assert!(args.name == "origin");
+
+    let args = ImmArgs::from(["test", "-C", "dir", "status", "-sv"]);
+    assert!(args.verbose);
+    assert!(args.directory.as_deref() == Some("dir"));
+    assert!(matches!(
+        args.command,
+        Command::Status(StatusArgs { short: true })
+    ));
+
+    let args = ImmArgs::from(["test", "remote", "add", "origin"]);
+    assert!(!args.verbose);
+
+    let args = ImmArgs::try_from(["test", "-q", "remote", "add", "origin"]);
+    assert!(matches!(args, Err(Error::InvalidOption { option, .. }) if option == "-q"));
+
+    let args = ImmArgs::try_from(["test", "remote", "add", "origin", "-v"]);
+    assert!(matches!(args, Err(Error::MisplacedOption { option }) if option == "-v"));
+
+    let args = ImmArgs::from(["test", "-C", "a", "status", "-C", "b"]);
+    assert!(args.directory.as_deref() == Some("b"));
+}
+
+#[test]
+fn non_option_negative_number() {
+    immargs! {
+        -v --verbose,
+        <offset> i64,
+        [<scale>...] f64,
+    }
+
+    let args = ImmArgs::from(["test", "-v", "-5", "-1.25", "2.5"]);
+    assert!(args.verbose);
+    assert!(args.offset == -5);
+    assert!(args.scale == vec![-1.25, 2.5]);
+}
+
+#[test]
+fn non_option_negative_number_digit_option() {
+    immargs! {
+        -_1,
+        [<offset>] i64,
+    }
+
+    let args = ImmArgs::from(["test", "-1"]);
+    assert!(args._1);
+    assert!(args.offset.is_none());
+
+    let args = ImmArgs::try_from(["test", "-5"]);
+    assert!(matches!(&args, Err(Error::InvalidOption { option, .. }) if option == "-5"));
+}
+
+#[test]
+fn non_option_negative_number_global_digit_option() {
+    immargs! {
+        -_1 global,
+        <command> Command {
+            move_ {
+                [<offset>] i64,
+            },
+        },
+    }
+
+    let args = ImmArgs::from(["test", "move", "-1"]);
+    assert!(args._1);
+    let Command::Move(args) = args.command;
+    assert!(args.offset.is_none());
+
+    let args = ImmArgs::try_from(["test", "move", "-5"]);
+    assert!(matches!(&args, Err(Error::InvalidOption { option, .. }) if option == "-5"));
+}
+
+#[test]
+fn non_option_command_uppercase() {
+    immargs! {
+        <command> Command {
+            Add A: AddArgs,
+            List,
+        },
+    }
+
+    immargs! {
+        AddArgs,
+        <file> String,
+    }
+
+    let args = ImmArgs::from(["test", "Add", "a"]);
+    let Command::Add(args) = args.command else {
+        panic!();
+    };
+    assert!(args.file == "a");
+
+    let args = ImmArgs::from(["test", "A", "b"]);
+    assert!(matches!(args.command, Command::Add(args) if args.file == "b"));
+
+    let args = ImmArgs::from(["test", "List"]);
+    assert!(matches!(args.command, Command::List(_)));
+}
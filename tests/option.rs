@@ -1,4 +1,6 @@
+use immargs::Error;
 use immargs::immargs;
+use std::collections::HashMap;
 
 #[test]
 fn option_single_short_char() {
@@ -91,3 +93,409 @@ fn option_variadic_value() {
     assert!(args.value[0] == "hello");
     assert!(args.value[1] == "world");
 }
+
+#[test]
+fn option_optional_value() {
+    immargs! {
+        -c --color [<when>] String,
+        [<file>] String,
+    }
+
+    let args = ImmArgs::from(["test"]);
+    assert!(args.color.is_none());
+
+    let args = ImmArgs::from(["test", "--color", "file"]);
+    assert!(args.color == Some(None));
+    assert!(args.file.as_deref() == Some("file"));
+
+    let args = ImmArgs::from(["test", "--color=always", "file"]);
+    assert!(args.color == Some(Some(String::from("always"))));
+    assert!(args.file.as_deref() == Some("file"));
+
+    let args = ImmArgs::from(["test", "-cnever"]);
+    assert!(args.color == Some(Some(String::from("never"))));
+}
+
+#[test]
+fn option_optional_value_variadic() {
+    immargs! {
+        -l --level... [<level>] u8,
+    }
+
+    let args = ImmArgs::from(["test", "-l", "--level=3", "-l5"]);
+    assert!(args.level == vec![None, Some(3), Some(5)]);
+}
+
+#[test]
+fn option_values() {
+    immargs! {
+        -r --resize <w> <h> u32,
+        [<file>] String,
+    }
+
+    let args = ImmArgs::from(["test"]);
+    assert!(args.resize.is_none());
+
+    let args = ImmArgs::from(["test", "--resize", "640", "480", "file"]);
+    assert!(args.resize == Some((640, 480)));
+    assert!(args.file.as_deref() == Some("file"));
+
+    let args = ImmArgs::from(["test", "--resize=640", "480"]);
+    assert!(args.resize == Some((640, 480)));
+
+    let args = ImmArgs::from(["test", "-r640", "480", "-r", "800", "600"]);
+    assert!(args.resize == Some((800, 600)));
+}
+
+#[test]
+fn option_values_variadic() {
+    immargs! {
+        -p --point... <x> <y> <z> i32,
+    }
+
+    let args = ImmArgs::from(["test", "-p", "1", "2", "3", "--point", "-4", "5", "-6"]);
+    assert!(args.point == vec![(1, 2, 3), (-4, 5, -6)]);
+}
+
+#[test]
+fn option_delimited() {
+    immargs! {
+        -p --ports <port,...> u16,
+    }
+
+    let args = ImmArgs::from(["test"]);
+    assert!(args.ports.is_none());
+
+    let args = ImmArgs::from(["test", "--ports", "80,443"]);
+    assert!(args.ports == Some(vec![80, 443]));
+
+    let args = ImmArgs::from(["test", "-p8080", "--ports="]);
+    assert!(args.ports == Some(vec![]));
+}
+
+#[test]
+fn option_delimited_variadic() {
+    immargs! {
+        -f --features... <feature,...> String,
+    }
+
+    let args = ImmArgs::from(["test", "-f", "a,b", "--features=c", "-fd,e"]);
+    assert!(args.features == vec!["a", "b", "c", "d", "e"]);
+}
+
+#[test]
+fn option_key_value() {
+    immargs! {
+        -o --opt <kv> (String, u32),
+    }
+
+    let args = ImmArgs::from(["test"]);
+    assert!(args.opt.is_none());
+
+    let args = ImmArgs::from(["test", "--opt", "a=1"]);
+    assert!(args.opt == Some((String::from("a"), 1)));
+
+    let args = ImmArgs::from(["test", "-ob=2", "--opt=c=3"]);
+    assert!(args.opt == Some((String::from("c"), 3)));
+}
+
+#[test]
+fn option_key_value_variadic() {
+    immargs! {
+        -D --define... <kv> (String, String),
+        --set... <kv> HashMap<String, u32>,
+        --sorted... <kv> std::collections::BTreeMap<u8, String>,
+    }
+
+    let args = ImmArgs::from(["test", "-D", "a=1", "-Db=x=y", "--define=c="]);
+    assert!(
+        args.define
+            == vec![
+                ("a".into(), "1".into()),
+                ("b".into(), "x=y".into()),
+                ("c".into(), "".into())
+            ]
+    );
+
+    let args = ImmArgs::from(["test", "--set", "a=1", "--set", "b=2"]);
+    assert!(args.set == HashMap::from([("a".into(), 1), ("b".into(), 2)]));
+
+    let args = ImmArgs::from(["test", "--sorted", "2=b", "--sorted", "1=a"]);
+    assert!(args.sorted.into_iter().collect::<Vec<_>>() == vec![(1, "a".into()), (2, "b".into())]);
+}
+
+#[test]
+fn option_choices() {
+    immargs! {
+        --color <when> Color { always, never no, auto },
+        --format... <fmt,...> Format { json, Plain_Text },
+        --level <level> Level { low, high } = "low",
+    }
+
+    let args = ImmArgs::from(["test"]);
+    assert!(args.color.is_none());
+    assert!(args.format.is_empty());
+    assert!(args.level == Level::Low);
+
+    let args = ImmArgs::from(["test", "--color", "never", "--format=Plain-Text,json"]);
+    assert!(args.color == Some(Color::Never));
+    assert!(args.format == vec![Format::PlainText, Format::Json]);
+
+    let args = ImmArgs::from(["test", "--color=no", "--level", "high"]);
+    assert!(args.color == Some(Color::Never));
+    assert!(args.level == Level::High);
+    assert!(args.level.to_string() == "high");
+    assert!(Color::Auto.as_str() == "auto");
+    assert!(
+        "always"
+            .parse::<Color>()
+            .is_ok_and(|color| color == Color::Always)
+    );
+}
+
+#[test]
+fn option_check() {
+    fn even(value: &u32) -> Result<(), String> {
+        match value % 2 {
+            0 => Ok(()),
+            _ => Err(String::from("expected even number")),
+        }
+    }
+
+    immargs! {
+        -p --port <port> u16 in 1024..=65535,
+        --ratio <r> f64 in -1.0..1.0,
+        --even... <n,...> u32 check(even),
+        --size <w> <h> u32 in 1..,
+        -D --define... <kv> (String, u8) in 1..=8,
+    }
+
+    let args = ImmArgs::from(["test", "-p", "8080", "--ratio=-0.5", "--even", "2,4"]);
+    assert!(args.port == Some(8080));
+    assert!(args.ratio == Some(-0.5));
+    assert!(args.even == vec![2, 4]);
+
+    let args = ImmArgs::try_from(["test", "--port", "80"]);
+    assert!(args.is_err());
+
+    let args = ImmArgs::try_from(["test", "--ratio", "1.0"]);
+    assert!(args.is_err());
+
+    let args = ImmArgs::try_from(["test", "--even", "2,3"]);
+    assert!(args.is_err());
+
+    let args = ImmArgs::try_from(["test", "--size", "1", "0"]);
+    assert!(args.is_err());
+
+    let args = ImmArgs::from(["test", "-D", "a=1", "-D", "b=8"]);
+    assert!(args.define == vec![(String::from("a"), 1), (String::from("b"), 8)]);
+
+    let args = ImmArgs::try_from(["test", "-D", "a=1", "-D", "b=9"]);
+    assert!(
+        matches!(&args, Err(Error::ValidationFailed { arg, value, message })
+            if arg == "-D" && value == "9" && message == "expected value in range 1..=8"
+        )
+    );
+}
+
+#[test]
+fn option_parser() {
+    use std::time::Duration;
+
+    fn parse_duration(value: &str) -> Result<Duration, String> {
+        match value.strip_suffix("ms") {
+            Some(ms) => ms.parse().map(Duration::from_millis),
+            None => value.parse().map(Duration::from_secs),
+        }
+        .map_err(|_| format!("invalid duration '{value}'"))
+    }
+
+    fn parse_upper(value: String) -> Result<String, std::convert::Infallible> {
+        Ok(value.to_uppercase())
+    }
+
+    fn non_empty(value: &str) -> Result<(), String> {
+        match value.is_empty() {
+            false => Ok(()),
+            true => Err(String::from("expected non-empty value")),
+        }
+    }
+
+    immargs! {
+        -t --timeout <dur> Duration with parse_duration = "30",
+        --delays... <dur,...> Duration with parse_duration,
+        --range <from> <to> Duration with parse_duration,
+        --name <name> String with parse_upper check(non_empty),
+    }
+
+    let args = ImmArgs::from(["test"]);
+    assert!(args.timeout == Duration::from_secs(30));
+    assert!(args.delays.is_empty());
+    assert!(args.range.is_none());
+    assert!(args.name.is_none());
+
+    let args = ImmArgs::from(["test", "-t500ms", "--delays", "1,2ms", "--range", "1", "2"]);
+    assert!(args.timeout == Duration::from_millis(500));
+    assert!(args.delays == vec![Duration::from_secs(1), Duration::from_millis(2)]);
+    assert!(args.range == Some((Duration::from_secs(1), Duration::from_secs(2))));
+
+    let args = ImmArgs::from(["test", "--name", "abc"]);
+    assert!(args.name.as_deref() == Some("ABC"));
+
+    let args = ImmArgs::try_from(["test", "--name", ""]);
+    assert!(args.is_err());
+
+    let args = ImmArgs::try_from(["test", "--timeout", "soon"]);
+    assert!(matches!(&args, Err(e)
+        if e.to_string() == "cannot parse argument 'soon': invalid duration 'soon'"
+    ));
+}
+
+#[test]
+fn option_default() {
+    immargs! {
+        -j --jobs <n> usize = 4,
+        --name <name> String = "unnamed",
+        --offset <n> i32 = -1,
+        --ratio <r> f64 = { 1.0 / 3.0 },
+        --ports <port,...> u16 = "80,443",
+    }
+
+    let args = ImmArgs::from(["test"]);
+    assert!(args.jobs == 4);
+    assert!(args.name == "unnamed");
+    assert!(args.offset == -1);
+    assert!(args.ratio == 1.0 / 3.0);
+    assert!(args.ports == vec![80, 443]);
+
+    let args = ImmArgs::from(["test", "-j8", "--name", "x", "--offset=2", "--ratio", "0.5"]);
+    assert!(args.jobs == 8);
+    assert!(args.name == "x");
+    assert!(args.offset == 2);
+    assert!(args.ratio == 0.5);
+
+    let args = ImmArgs::from(["test", "--ports", "8080"]);
+    assert!(args.ports == vec![8080]);
+}
+
+#[test]
+fn option_default_invalid() {
+    immargs! {
+        -h --help,
+        -j --jobs <n> usize = "many",
+    }
+
+    let args = ImmArgs::try_from(["test"]);
+    assert!(
+        matches!(&args, Err(Error::InvalidDefault { option, value, .. })
+            if option == "--jobs" && value == "many"
+        )
+    );
+    assert!(matches!(&args, Err(e)
+        if e.to_string() == "invalid default value 'many' for option '--jobs': invalid digit found in string"
+    ));
+
+    let args = ImmArgs::try_from(["test", "--jobs", "1"]);
+    assert!(matches!(args, Ok(args) if args.jobs == 1));
+
+    let args = ImmArgs::try_from(["test", "-h"]);
+    assert!(matches!(args, Err(Error::Help { .. })));
+}
+
+#[test]
+fn option_default_lazy() {
+    use std::cell::Cell;
+
+    thread_local! {
+        static EVALUATED: Cell<bool> = const { Cell::new(false) };
+    }
+
+    fn jobs() -> usize {
+        EVALUATED.set(true);
+        4
+    }
+
+    immargs! {
+        -j --jobs <n> usize = { jobs() },
+    }
+
+    let args = ImmArgs::from(["test", "-j", "2"]);
+    assert!(args.jobs == 2);
+    assert!(!EVALUATED.get());
+
+    let args = ImmArgs::from(["test"]);
+    assert!(args.jobs == 4);
+    assert!(EVALUATED.get());
+}
+
+#[test]
+fn option_negatable() {
+    immargs! {
+        -c --[no_]color,
+    }
+
+    let args = ImmArgs::from(["test"]);
+    assert!(args.color.is_none());
+
+    let args = ImmArgs::from(["test", "--color"]);
+    assert!(args.color == Some(true));
+
+    let args = ImmArgs::from(["test", "--no-color"]);
+    assert!(args.color == Some(false));
+
+    let args = ImmArgs::from(["test", "--no-color", "-c"]);
+    assert!(args.color == Some(true));
+
+    let args = ImmArgs::from(["test", "--color", "--no-color"]);
+    assert!(args.color == Some(false));
+}
+
+#[test]
+fn option_negatable_default() {
+    immargs! {
+        --[no_]color = true,
+        --[no_]pager = { false },
+    }
+
+    let args = ImmArgs::from(["test"]);
+    assert!(args.color);
+    assert!(!args.pager);
+
+    let args = ImmArgs::from(["test", "--no-color", "--pager"]);
+    assert!(!args.color);
+    assert!(args.pager);
+}
+
+#[test]
+fn option_negatable_conflict() {
+    immargs! {
+        --[no_]color   !,
+        --plain        !,
+    }
+
+    let args = ImmArgs::from(["test", "--color", "--no-color"]);
+    assert!(args.color == Some(false));
+    assert!(!args.plain);
+
+    let args = ImmArgs::try_from(["test", "--color", "--plain"]);
+    assert!(matches!(args, Err(Error::ConflictingArguments { .. })));
+
+    let args = ImmArgs::from(["test", "--no-color", "--plain"]);
+    assert!(args.color == Some(false));
+    assert!(args.plain);
+}
+
+#[test]
+fn option_negatable_choice() {
+    immargs! {
+        --[no_]color   ?,
+        --plain        ?,
+    }
+
+    let args = ImmArgs::try_from(["test", "--no-color"]);
+    assert!(matches!(args, Err(Error::MissingChoice { .. })));
+
+    let args = ImmArgs::from(["test", "--no-color", "--color"]);
+    assert!(args.color == Some(true));
+    assert!(!args.plain);
+}
@@ -0,0 +1,150 @@
+use immargs::Error;
+use immargs::immargs;
+
+#[test]
+fn permute_option_after_non_option() {
+    immargs! {
+        #![permute]
+        -v --verbose,
+        -l --log <level> u8,
+        <src> String,
+        <dest> String,
+    }
+
+    let args = ImmArgs::from(["test", "src", "-v", "dest", "--log", "3"]);
+    assert!(args.verbose);
+    assert!(args.log == Some(3));
+    assert!(args.src == "src");
+    assert!(args.dest == "dest");
+}
+
+#[test]
+fn permute_dashdash() {
+    immargs! {
+        #![permute]
+        -v --verbose,
+        <value>... String,
+    }
+
+    let args = ImmArgs::from(["test", "hello", "-v", "--", "-v", "world"]);
+    assert!(args.verbose);
+    assert!(args.value.len() == 3);
+    assert!(args.value[0] == "hello");
+    assert!(args.value[1] == "-v");
+    assert!(args.value[2] == "world");
+}
+
+#[test]
+fn permute_stops_at_command() {
+    immargs! {
+        #![permute]
+        -v --verbose,
+        [<dir>] String,
+        <command> Command {
+            add,
+            remove,
+        },
+    }
+
+    let args = ImmArgs::from(["test", "dir", "-v", "add", "-f", "file"]);
+    assert!(args.verbose);
+    assert!(args.dir.as_deref() == Some("dir"));
+    let Command::Add(args) = args.command else {
+        panic!();
+    };
+    let mut args = args.into_iter();
+    assert!(args.next().unwrap() == "test add");
+    assert!(args.next().unwrap() == "-f");
+    assert!(args.next().unwrap() == "file");
+    assert!(args.next().is_none());
+}
+
+#[test]
+fn permute_nested_command() {
+    immargs! {
+        #![permute]
+        -C --directory <path> String global,
+        <command> Command {
+            remote {
+                add {
+                    -v --verbose,
+                    <name> String,
+                    <url> String,
+                },
+            },
+        },
+    }
+
+    let args = ImmArgs::from(["test", "remote", "add", "-C", "x", "n", "u", "-v"]);
+    assert!(args.directory.as_deref() == Some("x"));
+    let Command::Remote(args) = args.command;
+    let RemoteCommand::Add(args) = args.command;
+    assert!(args.verbose);
+    assert!(args.name == "n" && args.url == "u");
+}
+
+#[test]
+fn permute_stops_at_external_command() {
+    immargs! {
+        #![permute]
+        -v --verbose,
+        <dir> String,
+        <command> Command {
+            list,
+            _,
+        },
+    }
+
+    let args = ImmArgs::from(["test", "dir", "-v", "list"]);
+    assert!(args.verbose);
+    assert!(args.dir == "dir");
+    assert!(matches!(args.command, Command::List(_)));
+
+    let args = ImmArgs::from(["test", "-v", "dir", "foo", "-v", "file"]);
+    assert!(args.verbose);
+    assert!(args.dir == "dir");
+    let Command::External(command, args) = args.command else {
+        panic!();
+    };
+    assert!(command == "foo");
+    assert!(args.into_iter().collect::<Vec<_>>() == ["test foo", "-v", "file"]);
+}
+
+#[test]
+fn strict_option_after_non_option() {
+    immargs! {
+        -v --verbose,
+        <file> String,
+    }
+
+    let args = ImmArgs::try_from(["test", "file", "-v"]);
+    assert!(matches!(&args, Err(Error::MisplacedOption { option }) if option == "-v"));
+    assert!(matches!(&args, Err(e)
+        if e.to_string() == "misplaced option '-v', options must come before non-option arguments"
+    ));
+}
+
+#[test]
+fn strict_option_with_value_after_non_option() {
+    immargs! {
+        -l --log <level> u8,
+        <file>... u32,
+    }
+
+    let args = ImmArgs::try_from(["test", "1", "2", "--log=3"]);
+    assert!(matches!(&args, Err(Error::MisplacedOption { option }) if option == "--log"));
+}
+
+#[test]
+fn strict_option_after_dashdash() {
+    immargs! {
+        -v --verbose,
+        <number> u32,
+    }
+
+    let args = ImmArgs::try_from(["test", "--", "-v"]);
+    assert!(matches!(&args, Err(Error::ParsingFailed { value, .. }) if value == "-v"));
+
+    let args = ImmArgs::try_from(["test", "1", "-v", "--"]);
+    assert!(matches!(&args, Err(Error::MisplacedOption { option }) if option == "-v"));
+}
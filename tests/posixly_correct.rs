@@ -0,0 +1,21 @@
+use immargs::Error;
+use immargs::immargs;
+
+// Kept in a separate test binary, since it modifies the environment
+#[test]
+fn posixly_correct() {
+    immargs! {
+        #![permute]
+        -v --verbose,
+        <file> String,
+    }
+
+    let args = ImmArgs::try_from(["test", "file", "-v"]);
+    assert!(matches!(args, Ok(args) if args.verbose && args.file == "file"));
+
+    // SAFETY: No other threads in this test binary access the environment
+    unsafe { std::env::set_var("POSIXLY_CORRECT", "1") };
+
+    let args = ImmArgs::try_from(["test", "file", "-v"]);
+    assert!(matches!(&args, Err(Error::MisplacedOption { option }) if option == "-v"));
+}
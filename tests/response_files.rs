@@ -0,0 +1,228 @@
+use immargs::Error;
+use immargs::immargs;
+use std::fs::create_dir_all;
+use std::fs::remove_dir_all;
+use std::fs::write;
+use std::ops::Deref;
+use std::path::Path;
+use std::path::PathBuf;
+
+// Directory of response files, unique to the test, removed when dropped
+struct TempDir(PathBuf);
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = remove_dir_all(&self.0);
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+fn dir(name: &str) -> TempDir {
+    let dir = std::env::temp_dir().join(format!("immargs-{}-{name}", std::process::id()));
+    create_dir_all(&dir).unwrap();
+    TempDir(dir)
+}
+
+fn at(path: &Path) -> String {
+    format!("@{}", path.display())
+}
+
+#[test]
+fn response_files() {
+    immargs! {
+        #![response_files]
+        -v --verbose,
+        -o --output <file> String,
+        <file>... String,
+    }
+
+    let dir = dir("response_files");
+    let file = dir.join("args.txt");
+    write(
+        &file,
+        "# comment\n-v --output 'out file'\n\"a b\" c # comment\n",
+    )
+    .unwrap();
+
+    let args = ImmArgs::from(["test", &at(&file), "d"]);
+    assert!(args.verbose);
+    assert!(args.output.as_deref() == Some("out file"));
+    assert!(args.file == vec!["a b", "c", "d"]);
+}
+
+#[test]
+fn response_files_nested() {
+    immargs! {
+        #![response_files]
+        <file>... String,
+    }
+
+    let dir = dir("response_files_nested");
+    create_dir_all(dir.join("nested")).unwrap();
+    write(dir.join("outer.txt"), "a @nested/inner.txt d").unwrap();
+    write(dir.join("nested/inner.txt"), "b @more.txt").unwrap();
+    write(dir.join("nested/more.txt"), "c").unwrap();
+
+    let args = ImmArgs::from(["test", &at(&dir.join("outer.txt"))]);
+    assert!(args.file == vec!["a", "b", "c", "d"]);
+}
+
+#[test]
+fn response_files_literal() {
+    immargs! {
+        #![response_files]
+        <file>... String,
+    }
+
+    let args = ImmArgs::from(["test", "@"]);
+    assert!(args.file == vec!["@"]);
+
+    let args = ImmArgs::from(["test", "--", "@file"]);
+    assert!(args.file == vec!["@file"]);
+}
+
+#[test]
+fn response_files_option_value() {
+    immargs! {
+        #![response_files]
+        -o --output <file> String,
+        <file>... String,
+    }
+
+    let dir = dir("response_files_option_value");
+    let file = dir.join("args.txt");
+    write(&file, "a").unwrap();
+
+    let args = ImmArgs::from(["test", "-o", &at(&file), &at(&file)]);
+    assert!(args.output == Some(at(&file)));
+    assert!(args.file == vec!["a"]);
+
+    let args = ImmArgs::from(["test", "-o", "--", &at(&file)]);
+    assert!(args.output.as_deref() == Some("--"));
+    assert!(args.file == vec!["a"]);
+
+    let args = ImmArgs::from(["test", &at(&file), "b", "--", &at(&file)]);
+    assert!(args.file == vec!["a", "b", "--", &at(&file)]);
+}
+
+#[test]
+fn response_files_disabled() {
+    immargs! {
+        <file>... String,
+    }
+
+    let args = ImmArgs::from(["test", "@file"]);
+    assert!(args.file == vec!["@file"]);
+}
+
+#[test]
+fn response_files_missing() {
+    immargs! {
+        #![response_files]
+        <file>... String,
+    }
+
+    let dir = dir("response_files_missing");
+    let file = dir.join("missing.txt");
+
+    let args = ImmArgs::try_from(["test", &at(&file)]);
+    assert!(
+        matches!(&args, Err(Error::ResponseFileFailed { file: f, .. })
+            if *f == file.display().to_string()
+        )
+    );
+}
+
+#[test]
+fn response_files_recursive() {
+    immargs! {
+        #![response_files]
+        <file>... String,
+    }
+
+    let dir = dir("response_files_recursive");
+    write(dir.join("a.txt"), "a @b.txt").unwrap();
+    write(dir.join("b.txt"), "b @a.txt").unwrap();
+
+    let args = ImmArgs::try_from(["test", &at(&dir.join("a.txt"))]);
+    assert!(matches!(&args, Err(e) if e.to_string().ends_with("recursive response file")));
+}
+
+#[test]
+fn response_files_command() {
+    immargs! {
+        CommitArgs,
+        -m --message <msg> String,
+        [<file>...] String,
+    }
+
+    immargs! {
+        #![response_files]
+        -v --verbose,
+        <command> Command {
+            commit: CommitArgs,
+        },
+    }
+
+    let dir = dir("response_files_command");
+    let file = dir.join("args.txt");
+    write(&file, "-v commit a").unwrap();
+
+    let args = ImmArgs::from(["test", "commit", "-m", "@me"]);
+    let Command::Commit(args) = args.command;
+    assert!(args.message.as_deref() == Some("@me"));
+
+    let args = ImmArgs::from(["test", "commit", &at(&file)]);
+    let Command::Commit(args) = args.command;
+    assert!(args.file == vec![at(&file)]);
+
+    let args = ImmArgs::from(["test", &at(&file), &at(&file)]);
+    assert!(args.verbose);
+    let Command::Commit(args) = args.command;
+    assert!(args.file == vec!["a", &at(&file)]);
+}
+
+#[test]
+fn response_files_raw() {
+    immargs! {
+        #![response_files]
+        <command> String,
+        [<args>...] raw,
+    }
+
+    let dir = dir("response_files_raw");
+    let file = dir.join("args.txt");
+    write(&file, "ls -l").unwrap();
+
+    let args = ImmArgs::from(["test", &at(&file), &at(&file)]);
+    assert!(args.command == "ls");
+    assert!(args.args == vec!["-l", &at(&file)]);
+}
+
+#[cfg(unix)]
+#[test]
+fn response_files_non_utf8() {
+    use std::ffi::OsString;
+    use std::os::unix::ffi::OsStrExt;
+
+    immargs! {
+        #![response_files]
+        <file>... OsString,
+    }
+
+    let dir = dir("response_files_non_utf8");
+    let file = dir.join("args.txt");
+    write(&file, b"a\xff 'b c'").unwrap();
+
+    let args = ImmArgs::from(["test", &at(&file)]);
+    assert!(args.file.len() == 2);
+    assert!(args.file[0].as_bytes() == b"a\xff");
+    assert!(args.file[1] == "b c");
+}
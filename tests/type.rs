@@ -1,3 +1,4 @@
+use immargs::Value;
 use immargs::immargs;
 use std::ffi::OsString;
 use std::net::Ipv4Addr;
@@ -192,3 +193,102 @@ fn type_ipv4addr() {
     let args = ImmArgs::from(["test", "--value", "127.0.0.1"]);
     assert!(args.value.unwrap() == Ipv4Addr::new(127, 0, 0, 1));
 }
+
+#[test]
+fn type_value() {
+    #[derive(Debug, PartialEq)]
+    struct Bytes(Vec<u8>);
+
+    impl Value for Bytes {
+        fn parse(value: OsString) -> immargs::Result<Self> {
+            Ok(Self(value.into_encoded_bytes()))
+        }
+    }
+
+    immargs! {
+        --value <value> Bytes,
+    }
+
+    let args = ImmArgs::from(["test", "--value", "abc"]);
+    assert!(args.value == Some(Bytes(b"abc".to_vec())));
+}
+
+#[cfg(unix)]
+#[test]
+fn type_non_unicode() {
+    use immargs::Error;
+    use std::os::unix::ffi::OsStringExt;
+
+    immargs! {
+        -f --file <file> PathBuf,
+        -n --name <name> String,
+        [<arg>...] OsString,
+    }
+
+    let non_unicode = || OsString::from_vec(b"abc\xff".to_vec());
+    let attached = |prefix: &[u8]| OsString::from_vec([prefix, b"abc\xff"].concat());
+
+    let args = ImmArgs::from_os([OsString::from("test"), OsString::from("-f"), non_unicode()]);
+    assert!(args.file == Some(PathBuf::from(non_unicode())));
+
+    let args = ImmArgs::from_os([OsString::from("test"), attached(b"-f")]);
+    assert!(args.file == Some(PathBuf::from(non_unicode())));
+
+    let args = ImmArgs::from_os([OsString::from("test"), attached(b"--file=")]);
+    assert!(args.file == Some(PathBuf::from(non_unicode())));
+
+    let args = ImmArgs::from_os([OsString::from("test"), non_unicode(), non_unicode()]);
+    assert!(args.arg == vec![non_unicode(), non_unicode()]);
+
+    let args = ImmArgs::try_from_os([OsString::from("test"), OsString::from("-n"), non_unicode()]);
+    assert!(matches!(&args, Err(Error::InvalidUnicode { arg }) if arg == "abc\u{fffd}"));
+    assert!(
+        matches!(&args, Err(e) if e.to_string() == "invalid unicode in argument 'abc\u{fffd}'")
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn type_from_os_str() {
+    use immargs::FromOsStr;
+    use std::os::unix::ffi::OsStringExt;
+    use std::str::FromStr;
+
+    #[derive(Debug, PartialEq)]
+    struct Name(OsString);
+
+    impl FromStr for Name {
+        type Err = std::convert::Infallible;
+
+        fn from_str(value: &str) -> Result<Self, Self::Err> {
+            Ok(Self(OsString::from(value)))
+        }
+    }
+
+    impl FromOsStr for Name {
+        fn from_os_str(value: OsString) -> immargs::Result<Self> {
+            Ok(Self(value))
+        }
+    }
+
+    immargs! {
+        -n --name <name> Name,
+        -d --define... <define> (String, PathBuf),
+        <names>... Name,
+    }
+
+    let non_unicode = || OsString::from_vec(b"abc\xff".to_vec());
+    let define = OsString::from_vec(b"key=abc\xff".to_vec());
+
+    let args = ImmArgs::from_os([
+        OsString::from("test"),
+        OsString::from("-n"),
+        non_unicode(),
+        OsString::from("-d"),
+        define,
+        non_unicode(),
+    ]);
+    assert!(args.name == Some(Name(non_unicode())));
+    assert!(args.define == [(String::from("key"), PathBuf::from(non_unicode()))]);
+    assert!(args.names == [Name(non_unicode())]);
+}